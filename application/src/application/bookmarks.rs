@@ -0,0 +1,32 @@
+use crate::{bindings::Action, input::EventState};
+
+use super::App;
+
+/// One per number key by default, the index is the key's number.
+pub const BOOKMARK_COUNT: usize = 10;
+
+//Seconds the camera takes to fly to a bookmark
+const BOOKMARK_TRANSITION_DURATION: f32 = 1.2;
+
+impl<'a> App<'a> {
+    /// By default, Ctrl and a number stores the view in the bookmark and saves the game, the number alone flies back to it.
+    pub fn camera_bookmarks(&mut self) {
+        for index in 0..BOOKMARK_COUNT {
+            if self
+                .input
+                .action_state(Action::StoreBookmark(index), EventState::Pressed)
+            {
+                self.bookmarks[index] = Some(self.camera.view());
+                self.save_game();
+            } else if self
+                .input
+                .action_state(Action::RecallBookmark(index), EventState::Pressed)
+            {
+                if let Some(view) = self.bookmarks[index] {
+                    self.camera
+                        .transition_to(view, BOOKMARK_TRANSITION_DURATION);
+                }
+            }
+        }
+    }
+}
@@ -1,40 +1,325 @@
-use std::f32::consts::PI;
+use std::{f32::consts::PI, fmt::Display, str::FromStr};
 
-use nalgebra::{Matrix4, Vector2, Vector3};
-use objects::{getters::Getters, transformations::Transformations};
-use winit::event::MouseButton;
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use objects::{getters::Getters, hitbox::ray::Ray, transformations::Transformations};
 
-use crate::{input::{Input, EventState}, MAP_SIZE};
+use crate::{
+    bindings::Action,
+    input::{EventState, Input},
+};
+
+/// How the view space is flattened on the screen.
+/// The view space is the world scaled by the zoom, where a unit is half the screen's height at the camera's focus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    //Depth is linear between the planes, which are around the focus
+    Orthographic { near: f32, far: f32 },
+    //The eye is placed so the focus is framed like with the orthographic projection,
+    //the planes are distances from the eye
+    Perspective { fov: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub const ORTHOGRAPHIC: Self = Self::Orthographic {
+        near: -200.,
+        far: 200.,
+    };
+    pub const PERSPECTIVE: Self = Self::Perspective {
+        fov: PI / 4.,
+        near: 0.05,
+        far: 400.,
+    };
+
+    /// Matrix from view space to clip space, with the depth going from 0 to 1 like Vulkan expects.
+    pub fn matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        match *self {
+            Self::Orthographic { near, far } => {
+                let mut matrix = Matrix4::new_nonuniform_scaling(&Vector3::new(
+                    1. / aspect_ratio,
+                    1.,
+                    1. / (far - near),
+                ));
+                matrix[(2, 3)] = -near / (far - near);
+                matrix
+            }
+            Self::Perspective { fov, near, far } => {
+                let eye_distance = 1. / (fov / 2.).tan();
+                let mut matrix = Matrix4::new_nonuniform_scaling(&Vector3::new(
+                    eye_distance / aspect_ratio,
+                    eye_distance,
+                    far / (far - near),
+                ));
+                matrix[(2, 3)] = far * (eye_distance - near) / (far - near);
+                //The w is the distance from the eye
+                matrix[(3, 2)] = 1.;
+                matrix[(3, 3)] = eye_distance;
+                matrix
+            }
+        }
+    }
+
+    /// Ray in view space going through a point of the screen, from -1 to 1 on both axes.
+    pub fn view_ray(&self, relative_position: Vector2<f32>, aspect_ratio: f32) -> Ray {
+        let focus_point = Vector3::new(relative_position.x * aspect_ratio, relative_position.y, 0.);
+        match *self {
+            Self::Orthographic { .. } => Ray::new(focus_point, Vector3::z()),
+            Self::Perspective { fov, .. } => {
+                let eye = Vector3::new(0., 0., -1. / (fov / 2.).tan());
+                Ray::new(eye, focus_point - eye)
+            }
+        }
+    }
+}
+
+/// Tuning of the camera's controls.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSettings {
+    //Radians per pixel the mouse moves while rotating
+    pub rotation_sensitivity: f32,
+    //In view units per second, a view unit is half the screen's height
+    pub move_speed: f32,
+    //How fast the velocity reaches the input's while moving, and stops without input, per second
+    pub acceleration: f32,
+    pub damping: f32,
+    //Scale change per wheel step
+    pub zoom_step: f32,
+    //Sideways movement per horizontal wheel step, in view units
+    pub wheel_pan_step: f32,
+    //How fast the scale eases towards the wheel's, per second
+    pub zoom_speed: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    //The view moves when the mouse is this close to the window's border, in pixels
+    pub edge_scroll_margin: f32,
+    //How fast a focus animation closes the distance to its target, per second
+    pub focus_speed: f32,
+    //Part of the ground the view covers that has to stay over the map, from 0 to 1
+    pub min_visible_map: f32,
+    //How fast the view springs back over the map, and slows down moving away from it, per second
+    pub bounds_stiffness: f32,
+    pub edge_resistance: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            rotation_sensitivity: 0.001,
+            move_speed: 1.,
+            acceleration: 10.,
+            damping: 6.,
+            zoom_step: 1.2,
+            wheel_pan_step: 0.1,
+            zoom_speed: 12.,
+            min_scale: 0.02,
+            max_scale: 0.5,
+            edge_scroll_margin: 4.,
+            focus_speed: 5.,
+            min_visible_map: 0.25,
+            bounds_stiffness: 8.,
+            edge_resistance: 12.,
+        }
+    }
+}
+
+//Range the vertical rotation is kept in, in radians from the ground
+const MIN_TILT: f32 = PI / 12.;
+const MAX_TILT: f32 = PI / 2.;
+
+/// State the camera can be rebuilt from, for the bookmarks and the save game.
+/// Written as its numbers separated by spaces, in the order of the fields.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraView {
+    //Map position at the center of the screen
+    pub focus: Vector2<f32>,
+    pub tilt: f32,
+    pub yaw: f32,
+    pub scale: f32,
+}
+
+impl CameraView {
+    /// Interpolates between the views, the yaw turns the short way and the zoom changes at a steady rate.
+    pub fn lerp(&self, other: &CameraView, t: f32) -> CameraView {
+        let yaw_change = (other.yaw - self.yaw + PI).rem_euclid(2. * PI) - PI;
+        CameraView {
+            focus: self.focus.lerp(&other.focus, t),
+            tilt: self.tilt + (other.tilt - self.tilt) * t,
+            yaw: self.yaw + yaw_change * t,
+            scale: self.scale * (other.scale / self.scale).powf(t),
+        }
+    }
+}
+
+impl Display for CameraView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.focus.x, self.focus.y, self.tilt, self.yaw, self.scale
+        )
+    }
+}
+
+impl FromStr for CameraView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split_whitespace()
+            .map(|value| {
+                value
+                    .parse::<f32>()
+                    .map_err(|err| format!("{err} in `{s}`"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.iter().any(|value| !value.is_finite()) {
+            return Err(format!("non-finite value in `{s}`"));
+        }
+        match values[..] {
+            [x, y, tilt, yaw, scale] if scale > 0. => Ok(CameraView {
+                focus: Vector2::new(x, y),
+                tilt: tilt.clamp(MIN_TILT, MAX_TILT),
+                yaw,
+                scale,
+            }),
+            _ => Err(format!("expected `x y tilt yaw scale`, got `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Transition {
+    from: CameraView,
+    to: CameraView,
+    //In seconds
+    duration: f32,
+    elapsed: f32,
+}
+
+//A focus animation ends this close to its target, in world units
+const FOCUS_THRESHOLD: f32 = 0.01;
 
 pub struct Camera {
     transform: Matrix4<f32>,
+    pub projection: Projection,
+    pub settings: CameraSettings,
+    //Size of the map the view is kept over, from the origin
+    pub bounds: Option<Vector2<f32>>,
     //Keeping track of camera's properties, same values can be extracted from transform matrix
     position: Vector2<f32>,
     tilt: f32,
+    yaw: f32,
     scale: f32,
+    //Movement state, the translation is applied to the transform every frame
+    velocity: Vector2<f32>,
+    target_scale: f32,
+    //Map position the center of the screen is moving to
+    focus_target: Option<Vector2<f32>>,
+    transition: Option<Transition>,
 }
 
 impl Camera {
     #[inline]
     pub fn init(position: Vector2<f32>, tilt: f32, scale: f32) -> Self {
-        Self {
-            transform: *Matrix4::identity()
-                .scale_object(scale)
-                .translate(position.x, 0., position.y)
-                .orbit(0., PI / 4., 0., Vector3::zeros())
-                .orbit_local(tilt, 0., 0., Vector3::zeros()),
+        let mut camera = Self {
+            transform: Matrix4::identity(),
+            projection: Projection::ORTHOGRAPHIC,
+            settings: CameraSettings::default(),
+            bounds: None,
             position,
             tilt,
+            yaw: PI / 4.,
             scale,
+            velocity: Vector2::zeros(),
+            target_scale: scale,
+            focus_target: None,
+            transition: None,
+        };
+        camera.set_view(&camera.view());
+        camera
+    }
+    /// The state the camera can be rebuilt from.
+    #[inline]
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            //The translation moves the map's origin, the center of the screen is the opposite point
+            focus: -self.position,
+            tilt: self.tilt,
+            yaw: self.yaw,
+            scale: self.scale,
         }
     }
+    /// Rebuilds the camera from the view, stopping its movement.
+    pub fn set_view(&mut self, view: &CameraView) {
+        self.transform = *Matrix4::identity()
+            .scale_object(view.scale)
+            .translate(-view.focus.x, 0., -view.focus.y)
+            .orbit(0., view.yaw, 0., Vector3::zeros())
+            .orbit_local(view.tilt, 0., 0., Vector3::zeros());
+        self.position = -view.focus;
+        self.tilt = view.tilt;
+        self.yaw = view.yaw;
+        self.scale = view.scale;
+
+        self.velocity = Vector2::zeros();
+        self.target_scale = view.scale;
+        self.focus_target = None;
+    }
+    /// Moves the camera from one view to the other over the duration in seconds, any movement input stops it.
+    #[inline]
+    pub fn play_transition(&mut self, from: CameraView, to: CameraView, duration: f32) {
+        self.set_view(&from);
+        self.transition = Some(Transition {
+            from,
+            to,
+            duration: duration.max(f32::EPSILON),
+            elapsed: 0.,
+        });
+    }
+    /// Same as [`Camera::play_transition`], from the current view.
+    #[inline]
+    pub fn transition_to(&mut self, to: CameraView, duration: f32) {
+        self.play_transition(self.view(), to, duration);
+    }
     #[inline]
     pub fn get_transform(&self) -> &Matrix4<f32> {
         &self.transform
     }
     #[inline]
-    pub fn get_position(&self) -> &Vector2<f32> {
-        &self.position
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        self.projection.matrix(aspect_ratio)
+    }
+    /// Ray in world space going from the camera through a point of the screen, from -1 to 1 on both axes.
+    #[inline]
+    pub fn screen_ray(&self, relative_position: Vector2<f32>, aspect_ratio: f32) -> Ray {
+        self.transform.try_inverse().unwrap()
+            * &self.projection.view_ray(relative_position, aspect_ratio)
+    }
+    /// Where a world point is on the screen, from -1 to 1 on both axes like [`Camera::screen_ray`]'s point.
+    /// Returns *None* if the point is behind a perspective camera.
+    pub fn world_to_screen(&self, point: Vector3<f32>, aspect_ratio: f32) -> Option<Vector2<f32>> {
+        let clip_position = self.projection_matrix(aspect_ratio) * self.transform * point.push(1.);
+        (clip_position.w > 0.).then(|| clip_position.xy() / clip_position.w)
+    }
+    /// Same as [`Camera::screen_ray`], starting at grass level.
+    /// Returns *None* if the ray doesn't reach grass level, only possible with a perspective projection.
+    pub fn ground_ray(&self, relative_position: Vector2<f32>, aspect_ratio: f32) -> Option<Ray> {
+        let camera_ray = self.screen_ray(relative_position, aspect_ratio);
+
+        let grass_level_tile = Vector4::new(0., -1., 0., 0.);
+        let (grass_level_intersection, distance) =
+            camera_ray.plane_intersection_point(grass_level_tile)?;
+        //Orthographic rays start at the focus, which can be under the grass
+        if distance < 0. && matches!(self.projection, Projection::Perspective { .. }) {
+            return None;
+        }
+        Some(Ray::new(grass_level_intersection, camera_ray.direction))
+    }
+    #[inline]
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            Projection::Orthographic { .. } => Projection::PERSPECTIVE,
+            Projection::Perspective { .. } => Projection::ORTHOGRAPHIC,
+        };
     }
     #[inline]
     pub fn translate_camera(&mut self, translation: Vector2<f32>) -> &mut Self {
@@ -57,64 +342,204 @@ impl Camera {
         self.transform.orbit_local(tilt, 0., 0., Vector3::zeros());
         self
     }
+    /// Direction on the map the keys and the edge scrolling move the view to, not normalized.
+    fn movement_direction(&self, input: &Input) -> Vector2<f32> {
+        //Directions on the map, the translation moves the view the opposite way
+        let forward = self.transform.y_axis().xz().normalize();
+        let right = -self.transform.x_axis().xz().normalize();
+        let mut direction = Vector2::zeros();
+        //Keyboard inputs, collecting directions
+        if input.action_state(Action::PanForward, EventState::Down) {
+            direction += forward;
+        }
+        if input.action_state(Action::PanBackward, EventState::Down) {
+            direction -= forward;
+        }
+        if input.action_state(Action::PanLeft, EventState::Down) {
+            direction -= right;
+        }
+        if input.action_state(Action::PanRight, EventState::Down) {
+            direction += right;
+        }
+        //Edge scrolling, the mouse touching a border of the window, not while text is typed
+        if input.mouse.in_window && input.text_focus().is_none() {
+            let margin = self.settings.edge_scroll_margin;
+            if input.mouse.pos.y < margin {
+                direction += forward;
+            }
+            if input.mouse.pos.y > input.window_size.y - margin {
+                direction -= forward;
+            }
+            if input.mouse.pos.x < margin {
+                direction -= right;
+            }
+            if input.mouse.pos.x > input.window_size.x - margin {
+                direction += right;
+            }
+        }
+
+        direction
+    }
+    /// Starts moving the center of the screen to the map position, any movement input cancels it.
+    #[inline]
+    pub fn focus_on(&mut self, map_position: Vector2<f32>) {
+        self.focus_target = Some(map_position);
+        self.velocity = Vector2::zeros();
+    }
     #[inline]
-    pub fn camera_move(&mut self, input: &Input, delta_time_seconds: f32) {
+    pub fn camera_move(&mut self, input: &Input, aspect_ratio: f32, delta_time_seconds: f32) {
+        //Fraction of the way an exponential ease covers this frame
+        let ease = |speed: f32| 1. - (-speed * delta_time_seconds).exp();
+
+        //The mouse bindings still reach the UI while text is typed, the camera leaves them alone
+        let rotating = input.text_focus().is_none()
+            && input.action_state(Action::RotateCamera, EventState::Down);
+        let direction = self.movement_direction(input);
+        let moving = direction != Vector2::zeros();
+        //The wheel is left to the text field with the focus, like the keys
+        let (wheel, horizontal_wheel) = match input.text_focus() {
+            Some(_) => (0., 0.),
+            None => (input.get_mouse_wheel(), input.get_horizontal_mouse_wheel()),
+        };
+        let wheel_panning = horizontal_wheel != 0.;
+
+        //CINEMATIC TRANSITION
+        //Any input takes the control back
+        if let Some(mut transition) = self.transition.take() {
+            if !rotating && !moving && !wheel_panning && wheel == 0. {
+                transition.elapsed += delta_time_seconds;
+                let progress = (transition.elapsed / transition.duration).min(1.);
+                //Smoothstep, starting and stopping slowly
+                let eased_progress = progress * progress * (3. - 2. * progress);
+                self.set_view(&transition.from.lerp(&transition.to, eased_progress));
+                if progress < 1. {
+                    self.transition = Some(transition);
+                }
+                return;
+            }
+        }
+
         //CAMERA ROTATION
-        if input.mouse_button_state(MouseButton::Middle, EventState::Down) {
+        if rotating {
+            let sensitivity = self.settings.rotation_sensitivity;
             //Horizontal rotation, no constraits
+            let camera_yaw_change = -input.mouse.delta_move.x * sensitivity;
+            self.yaw += camera_yaw_change;
             self.transform
-                .orbit(0., -input.mouse.delta_move.x * 0.001, 0., Vector3::zeros());
+                .orbit(0., camera_yaw_change, 0., Vector3::zeros());
 
             //Vertical rotation
-            let camera_tilt_change = input.mouse.delta_move.y * 0.001;
+            let camera_tilt_change = input.mouse.delta_move.y * sensitivity;
             //Vertical rotation constraints
-            if (self.tilt < PI / 2. && camera_tilt_change > 0.)
-                || (self.tilt > PI / 12. && camera_tilt_change < 0.)
+            if (self.tilt < MAX_TILT && camera_tilt_change > 0.)
+                || (self.tilt > MIN_TILT && camera_tilt_change < 0.)
             {
                 self.tilt_camera(camera_tilt_change);
             }
         }
 
         //CAMERA TRANSLATION
-        let mut camera_position_change = Vector2::zeros();
-        //Keyboard inputs, collecting translations
-        if input.key_state(winit::event::VirtualKeyCode::W, EventState::Down) {
-            let direction = (self.transform.y_axis()).xz().normalize() / self.scale;
-            camera_position_change += direction;
-        }
-        if input.key_state(winit::event::VirtualKeyCode::S, EventState::Down) {
-            let direction = (-self.transform.y_axis()).xz().normalize() / self.scale;
-            camera_position_change += direction;
+        //Moving cancels the focus animation
+        if moving || wheel_panning || rotating {
+            self.focus_target = None;
         }
-        if input.key_state(winit::event::VirtualKeyCode::A,EventState::Down) {
-            camera_position_change += self.transform.x_axis().xz();
+
+        //The velocity eases towards the input's, faster when accelerating than when stopping
+        let target_velocity = if moving {
+            direction.normalize() * self.settings.move_speed / self.scale
+        } else {
+            Vector2::zeros()
+        };
+        let rate = if moving {
+            self.settings.acceleration
+        } else {
+            self.settings.damping
+        };
+        self.velocity += (target_velocity - self.velocity) * ease(rate);
+
+        //Applying translation
+        self.translate_camera(self.velocity * delta_time_seconds);
+
+        //A horizontal wheel or touchpad scroll moves sideways by its steps, with no easing
+        if wheel_panning {
+            let right = -self.transform.x_axis().xz().normalize();
+            self.translate_camera(
+                right * horizontal_wheel * self.settings.wheel_pan_step / self.scale,
+            );
         }
-        if input.key_state(winit::event::VirtualKeyCode::D, EventState::Down) {
-            camera_position_change += -self.transform.x_axis().xz();
+
+        //FOCUS ANIMATION
+        if let Some(target) = self.focus_target {
+            match self.ground_ray(Vector2::zeros(), aspect_ratio) {
+                Some(center) => {
+                    let offset = center.origin.xz() - target;
+                    if offset.norm() < FOCUS_THRESHOLD {
+                        self.focus_target = None;
+                    }
+                    self.translate_camera(offset * ease(self.settings.focus_speed));
+                }
+                None => self.focus_target = None,
+            }
         }
 
-        //Camera moving constaints
-        if (self.position.x > 0. && camera_position_change.x > 0.)
-            || (self.position.x < -(MAP_SIZE as f32) && camera_position_change.x < 0.)
-        {
-            camera_position_change.x = 0.;
+        //CAMERA SCALING
+        //The wheel moves the target, the scale eases towards it
+        self.target_scale = (self.target_scale * self.settings.zoom_step.powf(wheel))
+            .clamp(self.settings.min_scale, self.settings.max_scale);
+        if (self.target_scale / self.scale - 1.).abs() > 1e-4 {
+            //Keeping the ground under the cursor in place
+            let cursor = input.get_relative_mouse_position();
+            let ground_before = self.ground_ray(cursor, aspect_ratio);
+
+            //Applying scaling
+            self.scale_camera(
+                (self.target_scale / self.scale).powf(ease(self.settings.zoom_speed)),
+            );
+
+            let ground_after = self.ground_ray(cursor, aspect_ratio);
+            if let (Some(before), Some(after)) = (ground_before, ground_after) {
+                self.translate_camera(after.origin.xz() - before.origin.xz());
+            }
         }
-        if (self.position.y > 0. && camera_position_change.y > 0.)
-            || (self.position.y < -(MAP_SIZE as f32) && camera_position_change.y < 0.)
-        {
-            camera_position_change.y = 0.;
+
+        //CAMERA CONSTRAINTS
+        if let Some(overshoot) = self.bounds_overshoot(aspect_ratio) {
+            //Moving further out is slowed down, the camera springs back the way it came
+            for axis in 0..2 {
+                //The translation moves the view the opposite way of the velocity
+                if -self.velocity[axis] * overshoot[axis] > 0. {
+                    self.velocity[axis] *= 1. - ease(self.settings.edge_resistance);
+                }
+            }
+            self.translate_camera(overshoot * ease(self.settings.bounds_stiffness));
         }
+    }
+    /// Map area under the corners of the screen, as its smallest and largest point.
+    /// Corners above the horizon are left out.
+    pub fn footprint(&self, aspect_ratio: f32) -> (Vector2<f32>, Vector2<f32>) {
+        let center = self.view().focus;
+        [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)]
+            .iter()
+            .filter_map(|&(x, y)| self.ground_ray(Vector2::new(x, y), aspect_ratio))
+            .fold((center, center), |(min, max), ray| {
+                (min.inf(&ray.origin.xz()), max.sup(&ray.origin.xz()))
+            })
+    }
+    /// How far the center of the screen is past where the footprint covers enough of the map.
+    /// Returns *None* inside the bounds, or without bounds.
+    fn bounds_overshoot(&self, aspect_ratio: f32) -> Option<Vector2<f32>> {
+        let bounds = self.bounds?;
+        let center = self.view().focus;
+        let (footprint_min, footprint_max) = self.footprint(aspect_ratio);
 
-        //Applying translation
-        camera_position_change *= delta_time_seconds;
-        self.translate_camera(camera_position_change);
+        //The footprint has to overlap the map by a part of its size, or of the map's when it's bigger
+        let required_overlap = (footprint_max - footprint_min)
+            .inf(&bounds)
+            .scale(self.settings.min_visible_map);
+        let lowest_center = required_overlap - (footprint_max - center);
+        let highest_center = bounds - required_overlap + (center - footprint_min);
 
-        //CAMERA SCALING
-        let scale_factor = 1.2_f32.powi(input.get_mouse_wheel() as i32);
-        //Scaling constaints
-        if (self.scale > 0.02 && scale_factor < 1.) || (self.scale < 0.5 && scale_factor > 1.) {
-        //Applying scaling
-        self.scale_camera(scale_factor);
-        }
+        let overshoot = center - center.sup(&lowest_center).inf(&highest_center);
+        (overshoot != Vector2::zeros()).then_some(overshoot)
     }
 }
@@ -1,9 +1,5 @@
-use nalgebra::{Vector3, Vector4};
-use objects::{
-    getters::Getters,
-    hitbox::ray::{IntersectableWithRay, Ray},
-    GameObject,
-};
+use nalgebra::{Vector2, Vector3};
+use objects::hitbox::ray::{IntersectableWithRay, Ray};
 
 use crate::input::EventState;
 
@@ -12,33 +8,15 @@ use super::App;
 impl<'a> App<'a> {
     #[inline]
     ///Returns with an intersection point of the mouse with the world, if there was one.
-    ///Also returns the clicked object if one was found.
-    pub fn world_mouse_intersection_point(&self) -> Option<(&GameObject, Vector3<f32>)> {
+    ///Also returns the index of the clicked object if one was found.
+    pub fn world_mouse_intersection_point(&self) -> Option<(usize, Vector3<f32>)> {
         //Starting with no intersection point, we modify this later once we find closer and closer intersection points.
         let mut final_intersection_point = None;
         let mut final_intersection_distance = f32::MAX;
-        //Creating a ray going from the camera into the direction of the view.
-        let ray_origin = (self.camera.get_transform().try_inverse().unwrap()
-            * Vector4::new(
-                self.input.get_relative_mouse_position().x * (1920. / 1080.),
-                self.input.get_relative_mouse_position().y,
-                0.,
-                1.,
-            ))
-        .xyz();
-        let ray_direction = self.camera.get_transform().z_axis();
-        let mouse_ray = Ray::new(ray_origin, ray_direction);
-
-        let grass_level_tile = Vector4::new(0., -1., 0., 0.);
-        let (grass_level_intersection, _) = unsafe {
-            mouse_ray
-                .plane_intersection_point(grass_level_tile)
-                .unwrap_unchecked()
-        };
-        let mouse_ray = Ray::new(grass_level_intersection, ray_direction);
+        let mouse_ray = self.mouse_ray()?;
 
         //Iterating over each object, checking intersections with each object
-        for object in &self.gameobjects {
+        for (index, object) in self.gameobjects.content.iter().enumerate() {
             //If object is not clickable, we ignore it, and continue with the next object.
             if object.has_flag(objects::GameObjectFlag::NotClickable) {
                 continue;
@@ -51,7 +29,7 @@ impl<'a> App<'a> {
                 if intersection_distance < final_intersection_distance {
                     //If the intersection point was closer than the previous intersection point
                     //We declare this as the new final intersection point (Min search by distance over all objects)
-                    final_intersection_point = Some((object, intersection_point));
+                    final_intersection_point = Some((index, intersection_point));
                     final_intersection_distance = intersection_distance;
                 }
             }
@@ -64,10 +42,26 @@ impl<'a> App<'a> {
         if map_intersection_distance < final_intersection_distance {
             //If the intersection point was closer than the previous intersection point
             //We declare this as the new final intersection point (Min search by distance over all objects)
-            final_intersection_point = Some((&self.gameobjects[0], map_intersection_point));
+            final_intersection_point = Some((0, map_intersection_point));
         }
 
         //We return the possibly modified value at the end.
         final_intersection_point
     }
+
+    #[inline]
+    ///Returns the ray going from the camera through the mouse, starting at grass level.
+    ///Returns *None* if the mouse is above the horizon.
+    pub fn mouse_ray(&self) -> Option<Ray> {
+        self.screen_ray(self.input.get_relative_mouse_position())
+    }
+
+    ///Returns the ray going from the camera through a point of the screen, starting at grass level.
+    ///The point goes from -1 to 1 on both axes, like the relative mouse position.
+    ///Returns *None* if the ray doesn't reach grass level, only possible with a perspective projection.
+    #[inline]
+    pub fn screen_ray(&self, relative_position: Vector2<f32>) -> Option<Ray> {
+        self.camera
+            .ground_ray(relative_position, self.input.aspect_ratio())
+    }
 }
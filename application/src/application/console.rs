@@ -0,0 +1,131 @@
+use nalgebra::{Vector2, Vector4};
+
+use crate::{
+    bindings::{Action, BINDINGS_PATH},
+    input::{EventState, TextFieldId},
+    ui::{edit_text, Rect, TextEdit, PANEL_COLOR, TEXT_COLOR},
+};
+
+use super::{hud::BAR_HEIGHT, App};
+
+const CONSOLE: TextFieldId = "console";
+//Output lines shown above the command line, older ones are dropped
+const OUTPUT_LINES: usize = 8;
+const LINE_SPACING: f32 = 4.;
+const MARGIN: f32 = 8.;
+const PREEDIT_COLOR: Vector4<f32> = Vector4::new(0.7, 0.7, 0.7, 1.);
+const HELP: &str = "Commands: help, bind <action> = <binding>, <binding>, bindings <action>, clear";
+
+/// Developer console under the resource bar, the keyboard types in it while it's open.
+#[derive(Default)]
+pub struct Console {
+    command_line: String,
+    output: Vec<String>,
+}
+
+impl<'a> App<'a> {
+    /// Draws the console while it's open, it blocks the mouse like the other widgets.
+    pub fn draw_console(&mut self, screen_size: Vector2<f32>) {
+        if !self.input.has_text_focus(CONSOLE) {
+            return;
+        }
+
+        let line_height = self.renderer.text_size("|", 1.).y + LINE_SPACING;
+        let panel = Rect::new(
+            0.,
+            BAR_HEIGHT,
+            screen_size.x,
+            (OUTPUT_LINES + 1) as f32 * line_height + MARGIN * 2.,
+        );
+        self.ui.panel(&mut self.renderer, panel, PANEL_COLOR);
+
+        let mut position = panel.position + Vector2::new(MARGIN, MARGIN);
+        for line in &self.console.output {
+            self.ui
+                .label(&mut self.renderer, line, position, 1., TEXT_COLOR);
+            position.y += line_height;
+        }
+
+        //The IME's composition goes after the typed text until it's committed
+        let command_line = format!("> {}", self.console.command_line);
+        let command_line_position =
+            panel.position + Vector2::new(MARGIN, MARGIN + OUTPUT_LINES as f32 * line_height);
+        self.ui.label(
+            &mut self.renderer,
+            &command_line,
+            command_line_position,
+            1.,
+            TEXT_COLOR,
+        );
+        let preedit_position =
+            command_line_position + Vector2::new(self.renderer.text_size(&command_line, 1.).x, 0.);
+        if !self.input.text.preedit.is_empty() {
+            let preedit = self.input.text.preedit.clone();
+            self.ui.label(
+                &mut self.renderer,
+                &preedit,
+                preedit_position,
+                1.,
+                PREEDIT_COLOR,
+            );
+        }
+        self.input.text.ime_position = preedit_position + Vector2::new(0., line_height);
+    }
+
+    /// Opens the console with its action, then runs the typed commands until Escape closes it.
+    /// Call it after the world's controls, so the keys opening and closing it don't reach them.
+    pub fn console_input(&mut self) {
+        if !self.input.has_text_focus(CONSOLE) {
+            if self
+                .input
+                .action_state(Action::ToggleConsole, EventState::Pressed)
+            {
+                self.input.focus_text(CONSOLE);
+            }
+            return;
+        }
+
+        let typed = self.input.take_text();
+        match edit_text(&mut self.console.command_line, &typed) {
+            TextEdit::Editing => {}
+            TextEdit::Submitted => {
+                let command = std::mem::take(&mut self.console.command_line);
+                self.run_command(&command);
+            }
+            TextEdit::Cancelled => self.input.release_text_focus(),
+        }
+    }
+
+    fn run_command(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        self.console_print(format!("> {command}"));
+
+        let (name, arguments) = command.split_once(' ').unwrap_or((command, ""));
+        let result = match name {
+            "help" => Ok(HELP.to_string()),
+            "bind" => self.input.bindings.set_from_line(arguments).map(|_| {
+                self.input.bindings.save(BINDINGS_PATH);
+                format!("Saved to {BINDINGS_PATH}")
+            }),
+            "bindings" => arguments
+                .trim()
+                .parse()
+                .map(|action| format!("{action} = {}", self.input.bindings.describe(action))),
+            "clear" => {
+                self.console.output.clear();
+                return;
+            }
+            _ => Err(format!("Unknown command `{name}`, try help")),
+        };
+        self.console_print(result.unwrap_or_else(|err| err));
+    }
+
+    fn console_print(&mut self, line: String) {
+        self.console.output.push(line);
+        let overflow = self.console.output.len().saturating_sub(OUTPUT_LINES);
+        self.console.output.drain(..overflow);
+    }
+}
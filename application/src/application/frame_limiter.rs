@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use renderer::{msg, PresentMode};
+
+use super::App;
+
+/// Tried in order until one is supported, FIFO being the fallback.
+pub const PRESENT_MODE_PREFERENCES: [PresentMode; 2] = [PresentMode::Mailbox, PresentMode::Fifo];
+
+//Cycled through at runtime, None is uncapped
+const FRAME_LIMITS: [Option<u32>; 4] = [None, Some(30), Some(60), Some(144)];
+
+//The end of the wait is spun, the sleep of the system isn't precise enough
+const SPIN_DURATION: Duration = Duration::from_millis(1);
+
+/// Caps the frame rate on the CPU by sleeping at the end of the frame.
+#[derive(Default)]
+pub struct FrameLimiter {
+    pub max_fps: Option<u32>,
+}
+
+impl FrameLimiter {
+    /// Waits until the frame started at `frame_start` has taken its share of a second.
+    pub fn wait(&self, frame_start: Instant) {
+        let max_fps = match self.max_fps {
+            Some(max_fps) if max_fps > 0 => max_fps,
+            _ => return,
+        };
+
+        let frame_end = frame_start + Duration::from_secs_f64(1. / max_fps as f64);
+        let now = Instant::now();
+        if frame_end > now + SPIN_DURATION {
+            std::thread::sleep(frame_end - now - SPIN_DURATION);
+        }
+        while Instant::now() < frame_end {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+impl<'a> App<'a> {
+    /// Goes to the next present mode the surface supports, it's applied on the next frame.
+    pub fn cycle_present_mode(&mut self) {
+        let supported = match self.renderer.supported_present_modes() {
+            Ok(supported) => supported,
+            Err(err) => {
+                msg!(warn, err.to_string());
+                return;
+            }
+        };
+
+        let current = self.renderer.present_mode();
+        let index = PresentMode::ALL
+            .iter()
+            .position(|&mode| mode == current)
+            .unwrap_or(0);
+        let next = (1..=PresentMode::ALL.len())
+            .map(|offset| PresentMode::ALL[(index + offset) % PresentMode::ALL.len()])
+            .find(|mode| supported.contains(mode));
+        if let Some(next) = next {
+            self.renderer.set_present_modes(&[next]);
+        }
+    }
+
+    /// Goes to the next frame rate cap.
+    pub fn cycle_frame_limit(&mut self) {
+        let index = FRAME_LIMITS
+            .iter()
+            .position(|&limit| limit == self.frame_limiter.max_fps)
+            .map_or(0, |index| (index + 1) % FRAME_LIMITS.len());
+        self.frame_limiter.max_fps = FRAME_LIMITS[index];
+    }
+}
@@ -3,7 +3,38 @@ use std::f32::consts::PI;
 use nalgebra::Vector3;
 use renderer::Renderer;
 
+/// Stockpile of the player, also used for the costs of structures.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Resources {
+    pub wood: u32,
+    pub stone: u32,
+    pub food: u32,
+}
+
+impl Resources {
+    pub const fn new(wood: u32, stone: u32, food: u32) -> Self {
+        Self { wood, stone, food }
+    }
+
+    #[inline]
+    pub fn covers(&self, cost: &Resources) -> bool {
+        self.wood >= cost.wood && self.stone >= cost.stone && self.food >= cost.food
+    }
+
+    /// Removes the cost, returns *false* and keeps everything if there isn't enough.
+    pub fn spend(&mut self, cost: &Resources) -> bool {
+        if !self.covers(cost) {
+            return false;
+        }
+        self.wood -= cost.wood;
+        self.stone -= cost.stone;
+        self.food -= cost.food;
+        true
+    }
+}
+
 pub struct GameController {
+    pub resources: Resources,
     pub time: f32,
     //Time is in hours, from 0 to 24
     day_length: f32,
@@ -56,6 +87,7 @@ impl GameController {
 impl GameController {
     pub fn init(renderer: &mut Renderer) -> Self {
         let mut game_controller = Self {
+            resources: Resources::new(100, 50, 200),
             time: 11.,
             day_length: 10.,
         };
@@ -0,0 +1,107 @@
+use nalgebra::Vector2;
+use objects::MeshPreset;
+
+use crate::ui::{Rect, PANEL_COLOR, TEXT_COLOR};
+
+use super::{gamecontroller::Resources, App};
+
+pub const BAR_HEIGHT: f32 = 36.;
+const PALETTE_BUTTON_SIZE: Vector2<f32> = Vector2::new(180., 40.);
+const MARGIN: f32 = 8.;
+
+/// A structure the palette can place.
+pub struct PaletteEntry {
+    pub name: &'static str,
+    pub mesh: MeshPreset,
+    pub cost: Resources,
+}
+
+pub const PALETTE: [PaletteEntry; 1] = [PaletteEntry {
+    name: "House",
+    mesh: MeshPreset::House,
+    cost: Resources::new(10, 5, 0),
+}];
+
+/// State of the game's widgets that lasts between frames.
+#[derive(Default)]
+pub struct Hud {
+    //Index in PALETTE of the structure placed by clicking the map
+    pub placing: Option<usize>,
+}
+
+impl<'a> App<'a> {
+    /// Declares the widgets of the frame, call it before the world picking.
+    pub fn draw_hud(&mut self) {
+        self.ui.begin(&self.input);
+
+        let screen_size = self.input.window_size;
+        self.resource_bar(screen_size);
+        self.structure_palette(screen_size);
+        self.draw_minimap(screen_size);
+        self.draw_console(screen_size);
+    }
+
+    /// Top bar with the resources, and the time of day on the right.
+    fn resource_bar(&mut self, screen_size: Vector2<f32>) {
+        let bar = Rect::new(0., 0., screen_size.x, BAR_HEIGHT);
+        self.ui.panel(&mut self.renderer, bar, PANEL_COLOR);
+
+        let resources = self.game_controller.resources;
+        let text = format!(
+            "Wood: {}   Stone: {}   Food: {}",
+            resources.wood, resources.stone, resources.food
+        );
+        let text_position = Vector2::new(
+            MARGIN,
+            (BAR_HEIGHT - self.renderer.text_size(&text, 1.).y) / 2.,
+        );
+        self.ui
+            .label(&mut self.renderer, &text, text_position, 1., TEXT_COLOR);
+
+        let time = self.game_controller.time;
+        let clock = format!("{:02}:{:02}", time as u32 % 24, (time.fract() * 60.) as u32);
+        let clock_size = self.renderer.text_size(&clock, 1.);
+        let clock_position = Vector2::new(
+            screen_size.x - clock_size.x - MARGIN,
+            (BAR_HEIGHT - clock_size.y) / 2.,
+        );
+        self.ui
+            .label(&mut self.renderer, &clock, clock_position, 1., TEXT_COLOR);
+    }
+
+    /// Column of structures in the bottom left corner, clicking the selected one again deselects it.
+    fn structure_palette(&mut self, screen_size: Vector2<f32>) {
+        let panel_height = PALETTE.len() as f32 * (PALETTE_BUTTON_SIZE.y + MARGIN) + MARGIN;
+        let panel = Rect::new(
+            0.,
+            screen_size.y - panel_height,
+            PALETTE_BUTTON_SIZE.x + MARGIN * 2.,
+            panel_height,
+        );
+        self.ui.panel(&mut self.renderer, panel, PANEL_COLOR);
+
+        for (index, entry) in PALETTE.iter().enumerate() {
+            let button = Rect {
+                position: panel.position
+                    + Vector2::new(
+                        MARGIN,
+                        MARGIN + index as f32 * (PALETTE_BUTTON_SIZE.y + MARGIN),
+                    ),
+                size: PALETTE_BUTTON_SIZE,
+            };
+            let label = format!(
+                "{} {}/{}/{}",
+                entry.name, entry.cost.wood, entry.cost.stone, entry.cost.food
+            );
+            let selected = self.hud.placing == Some(index);
+            let affordable = self.game_controller.resources.covers(&entry.cost);
+
+            if self
+                .ui
+                .button(&mut self.renderer, button, &label, selected, affordable)
+            {
+                self.hud.placing = if selected { None } else { Some(index) };
+            }
+        }
+    }
+}
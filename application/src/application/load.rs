@@ -1,8 +1,14 @@
 use objects::{hitbox::Hitbox, mesh::Mesh};
 use renderer::utils::vertex::Vertex;
 
+use crate::map::Map;
+
 use super::App;
 
+pub const FONT_PATH: &str = "resources/fonts/DejaVuSansMono.ttf";
+//Pixels per em, text drawn at scale 1 has this size
+pub const FONT_SIZE: f32 = 24.;
+
 pub const MAX_VERTEX_DISTANCE: f32 = 1.1;
 //True value is 1, add .1 to account for any floating point inaccuracies
 
@@ -15,7 +21,7 @@ impl<'a> App<'a> {
             &mut self.renderer,
             vec![Vertex::default()],
             vec![0, 0, 0],
-            Hitbox::new(vec![], vec![], vec![])
+            Hitbox::new(vec![], vec![], vec![]),
         ));
         //Map
         let map_mesh = self.map.convert_to_mesh(&mut self.renderer);
@@ -24,6 +30,15 @@ impl<'a> App<'a> {
         self.load_object("resources/models/Indicators/MapSelection", meshes);
         self.load_object("resources/models/Debug/Plane", meshes);
         self.load_object("resources/models/Debug/Sphere", meshes);
+        //Water
+        let water_mesh = Map::water_mesh(&mut self.renderer);
+        meshes.push(water_mesh);
+    }
+    pub fn load_font(&mut self) {
+        let font_data = std::fs::read(FONT_PATH).expect("Failed to read the font");
+        self.renderer
+            .load_font(&font_data, FONT_SIZE)
+            .expect("Failed to load the font");
     }
     #[inline]
     fn load_object(&mut self, path: &str, meshes: &mut Vec<Mesh>) {
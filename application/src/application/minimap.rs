@@ -0,0 +1,99 @@
+use nalgebra::{Vector2, Vector4};
+use renderer::{msg, overlay::OverlayTextureId};
+
+use crate::ui::{Rect, PANEL_COLOR};
+
+use super::App;
+
+const MINIMAP_SIZE: f32 = 200.;
+const MARGIN: f32 = 8.;
+const BORDER: f32 = 3.;
+const VIEW_OUTLINE_COLOR: Vector4<f32> = Vector4::new(1., 1., 1., 0.9);
+
+/// Texture of the map with a pixel per tile, drawn in the bottom right corner.
+#[derive(Default)]
+pub struct Minimap {
+    //Created with the first update
+    texture: Option<OverlayTextureId>,
+}
+
+impl<'a> App<'a> {
+    /// Draws the minimap with the outline of the view, holding the mouse on it moves the camera there.
+    pub fn draw_minimap(&mut self, screen_size: Vector2<f32>) {
+        self.update_minimap();
+        let texture = match self.minimap.texture {
+            Some(texture) => texture,
+            None => return,
+        };
+
+        let minimap = Rect::new(
+            screen_size.x - MINIMAP_SIZE - MARGIN,
+            screen_size.y - MINIMAP_SIZE - MARGIN,
+            MINIMAP_SIZE,
+            MINIMAP_SIZE,
+        );
+        let frame = Rect {
+            position: minimap.position - Vector2::new(BORDER, BORDER),
+            size: minimap.size + Vector2::new(BORDER, BORDER) * 2.,
+        };
+        self.ui.panel(&mut self.renderer, frame, PANEL_COLOR);
+
+        let map_size = self.map.size() as f32;
+        if let Some(relative_position) = self.ui.image(&mut self.renderer, minimap, texture) {
+            self.jump_camera_to(relative_position * map_size);
+        }
+
+        //The ground under the corners of the screen, clamped to the minimap when zoomed out.
+        //Corners above the horizon are pushed far away in their direction.
+        let aspect_ratio = self.input.aspect_ratio();
+        let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)].map(|(x, y)| {
+            let corner = Vector2::new(x, y);
+            let ground = match self.screen_ray(corner) {
+                Some(ray) => ray.origin.xz(),
+                None => {
+                    let ray = self.camera.screen_ray(corner, aspect_ratio);
+                    ray.origin.xz() + ray.direction.xz().normalize() * map_size * 2.
+                }
+            };
+            let on_minimap = minimap.position + ground / map_size * MINIMAP_SIZE;
+            on_minimap
+                .sup(&minimap.position)
+                .inf(&(minimap.position + minimap.size))
+        });
+        for (i, &corner) in corners.iter().enumerate() {
+            self.renderer
+                .draw_line(corner, corners[(i + 1) % 4], 2., VIEW_OUTLINE_COLOR);
+        }
+    }
+
+    /// Moves the camera so the center of the screen looks at the point of the map.
+    pub fn jump_camera_to(&mut self, map_position: Vector2<f32>) {
+        //The camera's translation is applied before its rotation, moving it moves the view's ground the other way
+        if let Some(center) = self.screen_ray(Vector2::zeros()) {
+            self.camera
+                .translate_camera(center.origin.xz() - map_position);
+        }
+    }
+
+    /// Regenerates the texture if a tile changed since the last frame.
+    fn update_minimap(&mut self) {
+        if !self.map.take_modified() {
+            return;
+        }
+
+        let pixels = self.map.minimap_pixels();
+        let result = match self.minimap.texture {
+            Some(texture) => self.renderer.update_overlay_texture(texture, &pixels),
+            None => {
+                let size = self.map.size() as u32;
+                self.renderer
+                    .create_overlay_texture(size, size, &pixels)
+                    .map(|texture| self.minimap.texture = Some(texture))
+            }
+        };
+        //The map keeps working without its minimap, it's tried again when a tile changes
+        if let Err(err) = result {
+            msg!(error, format!("Failed to update the minimap: {err}"));
+        }
+    }
+}
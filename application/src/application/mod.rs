@@ -1,78 +1,182 @@
-use std::{f32::consts::PI, mem::size_of, time::Duration};
+use std::{collections::BTreeSet, f32::consts::PI, mem::size_of, time::Duration};
 
-use nalgebra::{Matrix4, Vector2};
+use nalgebra::Vector2;
 use objects::{
     hitbox::Hitbox, mesh::Mesh, GameObject, GameObjectCreateInfo, GameObjectTransform, MeshPreset,
+    SelectionState,
 };
 use renderer::{
     engine::{aligned_array::AlignedArray, aligned_array_implementations, object_vector::ObjVec},
-    utils::MAX_WORLD_OBJECTS,
+    utils::{buffer_data::ObjectUniform, MAX_WORLD_OBJECTS},
     Renderer,
 };
 use winit::window::Window;
 
-use crate::{input::Input, map::Map};
+use crate::{input::Input, map::Map, ui::Ui};
 
-use self::{camera::Camera, gamecontroller::GameController};
+use self::{
+    bookmarks::BOOKMARK_COUNT,
+    camera::{Camera, CameraView},
+    console::Console,
+    frame_limiter::{FrameLimiter, PRESENT_MODE_PREFERENCES},
+    gamecontroller::GameController,
+    hud::Hud,
+    minimap::Minimap,
+};
 
-mod camera;
+pub mod bookmarks;
+pub mod camera;
 pub mod click;
+mod console;
 mod event_handler;
+mod frame_limiter;
 mod gamecontroller;
+mod hud;
 pub mod load;
+mod minimap;
 pub mod run;
+mod save;
+mod selection;
 
 pub struct App<'a> {
     pub input: Input,
     pub renderer: Renderer,
+    pub ui: Ui,
+    hud: Hud,
+    minimap: Minimap,
+    console: Console,
 
     map: Map,
     gameobjects: ObjVec<GameObject<'a>>,
     game_controller: GameController,
+    //Indices of the selected gameobjects
+    pub selection: BTreeSet<usize>,
+    //Where the mouse was pressed for a click or box selection, in pixels
+    selection_drag_start: Option<Vector2<f32>>,
 
-    transform_array: AlignedArray<Matrix4<f32>>,
+    object_uniforms: AlignedArray<ObjectUniform>,
 
     p_meshes_vec: *mut Vec<Mesh>,
 
     pub camera: Camera,
+    bookmarks: [Option<CameraView>; BOOKMARK_COUNT],
+    pub frame_limiter: FrameLimiter,
 
     //It is like minecraft's time, going from 0 to 65535
     pub delta_time: Duration,
 }
 
+/// What an app keeps when it's rebuilt on a new device, see [`App::recover`].
+pub struct AppState {
+    input: Input,
+    hud: Hud,
+    console: Console,
+    map: Map,
+    game_controller: GameController,
+    //Create info, uniform and selection state of every gameobject, in index order
+    objects: Vec<(GameObjectCreateInfo, ObjectUniform, SelectionState)>,
+    selection: BTreeSet<usize>,
+    camera: Camera,
+    bookmarks: [Option<CameraView>; BOOKMARK_COUNT],
+    frame_limiter: FrameLimiter,
+}
+
 impl<'a> App<'a> {
     pub fn init(window: &Window, map_size: usize, p_meshes_vec: &Vec<Mesh>) -> Self {
-        let mut renderer = Renderer::new(window).expect("Failed to setup renderer");
+        let mut renderer =
+            Renderer::new(window, &PRESENT_MODE_PREFERENCES).expect("Failed to setup renderer");
         let map = Map::generate(map_size);
+        let mut camera = Camera::init(
+            Vector2::new(-(map_size as f32) / 2., -(map_size as f32) / 2.),
+            PI / 6.,
+            0.1,
+        );
+        camera.bounds = Some(Vector2::repeat(map.size() as f32));
         Self {
-            input: Input::init(),
+            input: Input::init(Vector2::new(
+                window.inner_size().width as f32,
+                window.inner_size().height as f32,
+            )),
+            ui: Ui::default(),
+            hud: Hud::default(),
+            minimap: Minimap::default(),
+            console: Console::default(),
 
             map,
             gameobjects: ObjVec::with_capacity(MAX_WORLD_OBJECTS),
             game_controller: GameController::init(&mut renderer),
+            selection: BTreeSet::new(),
+            selection_drag_start: None,
 
-            transform_array: AlignedArray::from_dynamic_ub_data(
+            object_uniforms: AlignedArray::from_dynamic_ub_data(
                 &renderer.data.dynamic_uniform_buffer,
             ),
 
             renderer,
             p_meshes_vec: p_meshes_vec as *const _ as *mut _,
 
-            camera: Camera::init(
-                Vector2::new(-(map_size as f32) / 2., -(map_size as f32) / 2.),
-                PI / 6.,
-                0.1,
-            ),
+            camera,
+
+            bookmarks: [None; BOOKMARK_COUNT],
+            frame_limiter: FrameLimiter::default(),
 
             delta_time: Duration::ZERO,
         }
     }
 
+    /// Rebuilds the app on a new renderer with the state of one whose device was lost.
+    /// The meshes are loaded again and the objects are created in the same order, so their indices stay valid.
+    pub fn recover(window: &Window, meshes: &mut Vec<Mesh>, state: AppState) -> Self {
+        let mut app = Self::init(window, state.map.size(), meshes);
+        app.map = state.map;
+        meshes.clear();
+        app.load_meshes(meshes);
+        app.load_font();
+
+        for (create_info, uniform, selection) in state.objects {
+            let index = app.create_obj(&create_info);
+            *app.gameobjects[index].uniform = uniform;
+            app.gameobjects[index].set_selection(selection);
+        }
+
+        app.input = state.input;
+        app.hud = state.hud;
+        app.console = state.console;
+        app.game_controller = state.game_controller;
+        app.game_controller.add_time_elapsed(0., &mut app.renderer);
+        app.selection = state.selection;
+        app.camera = state.camera;
+        app.bookmarks = state.bookmarks;
+        app.frame_limiter = state.frame_limiter;
+        app
+    }
+
+    /// Takes the state that doesn't live on the device, the renderer is dropped with the rest.
+    pub fn into_state(self) -> AppState {
+        AppState {
+            objects: self
+                .gameobjects
+                .content
+                .iter()
+                .map(|object| (object.create_info(), *object.uniform, object.selection()))
+                .collect(),
+            input: self.input,
+            hud: self.hud,
+            console: self.console,
+            map: self.map,
+            game_controller: self.game_controller,
+            selection: self.selection,
+            camera: self.camera,
+            bookmarks: self.bookmarks,
+            frame_limiter: self.frame_limiter,
+        }
+    }
+
     /// # Gameobject creation
     /// returns the index of the created gameobject
     pub fn create_obj(&mut self, create_info: &GameObjectCreateInfo) -> usize {
         let mesh = self.get_mesh(create_info.preset);
-        let obj = GameObject::create(&mut self.transform_array, mesh, create_info)
+        let obj = GameObject::create(&mut self.object_uniforms, mesh, create_info)
             .expect("Failed to create gameObject");
         self.gameobjects.push(obj)
     }
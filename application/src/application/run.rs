@@ -1,51 +1,194 @@
-use crate::input::EventState;
+use crate::{
+    bindings::{Action, Bindings, BINDINGS_PATH},
+    input::EventState,
+};
 
+use nalgebra::{Vector2, Vector3, Vector4};
 use objects::{
-    transformations::Transformations, GameObjectCreateInfo, GameObjectFlag, GameObjectTransform,
-    MeshPreset,
+    getters::Getters, transformations::Transformations, GameObjectCreateInfo, GameObjectFlag,
+    GameObjectTransform, MeshPreset,
 };
-use winit::event::VirtualKeyCode;
+use renderer::material::MaterialPreset;
+
+use crate::map::tile::TileFlag;
 
-use super::App;
+use super::{hud, App};
 
 impl<'a> App<'a> {
     #[inline]
     pub fn main_loop(&mut self) {
-        if let Some((clicked_object, click_position)) = self.world_mouse_intersection_point() {
-            if clicked_object.has_flag(GameObjectFlag::Map) {
-                if self
-                    .input
-                    .mouse_button_state(winit::event::MouseButton::Left, EventState::Pressed)
-                {
-                    self.create_obj(
-                        &GameObjectCreateInfo::default()
-                            .mesh_preset(MeshPreset::House)
-                            .transform(GameObjectTransform::default().position(click_position)),
+        //The widgets go first, so the clicks they take don't reach the world
+        self.draw_hud();
+
+        if self
+            .input
+            .action_state(Action::CancelPlacement, EventState::Pressed)
+        {
+            self.hud.placing = None;
+        }
+
+        let mut hovered_object = None;
+        //The world doesn't take clicks while text is typed, like the keys
+        let world_intersection = if self.ui.wants_mouse() || self.input.text_focus().is_some() {
+            None
+        } else {
+            self.world_mouse_intersection_point()
+        };
+        if let Some((clicked_object, click_position)) = world_intersection {
+            let select_pressed = self.input.action_state(Action::Select, EventState::Pressed);
+
+            if self.gameobjects[clicked_object].has_flag(GameObjectFlag::Map) {
+                if select_pressed {
+                    match self.hud.placing {
+                        Some(index) => self.place_structure(index, click_position),
+                        None => self.start_selection_drag(),
+                    }
+                }
+                self.gameobjects[1]
+                    .uniform
+                    .transform
+                    .set_position(click_position);
+            } else {
+                hovered_object = Some(clicked_object);
+                if select_pressed {
+                    self.start_selection_drag();
+                }
+                //Double clicking an object flies to it
+                if self.input.action_double_clicked(Action::Select) {
+                    self.camera.focus_on(
+                        self.gameobjects[clicked_object]
+                            .transform()
+                            .get_position()
+                            .xz(),
                     );
                 }
-                self.gameobjects[1].transform.set_position(click_position);
-                
             }
         }
+        self.update_selection_drag(hovered_object);
+        self.hover_object(hovered_object);
+
+        if self
+            .input
+            .action_state(Action::ToggleDebugDraw, EventState::Pressed)
+        {
+            self.renderer.toggle_debug_draw();
+        }
+        if self
+            .input
+            .action_state(Action::CyclePresentMode, EventState::Pressed)
+        {
+            self.cycle_present_mode();
+        }
+        if self
+            .input
+            .action_state(Action::CycleFrameLimit, EventState::Pressed)
+        {
+            self.cycle_frame_limit();
+        }
+        self.camera_bookmarks();
+        if self
+            .input
+            .action_state(Action::FocusSelection, EventState::Pressed)
+        {
+            self.focus_selection();
+        }
+        if self
+            .input
+            .action_state(Action::ToggleProjection, EventState::Pressed)
+        {
+            self.camera.toggle_projection();
+        }
+        if self
+            .input
+            .action_state(Action::ReloadBindings, EventState::Pressed)
+        {
+            self.input.bindings = Bindings::load(BINDINGS_PATH);
+        }
+        if self.renderer.debug_draw_enabled() {
+            self.debug_draw(hovered_object);
+        }
 
         if self
             .input
-            .key_state(VirtualKeyCode::Space, EventState::Pressed)
+            .action_state(Action::ToggleWireframe, EventState::Pressed)
         {
-            self.renderer.current_pipeline_index =
-                (self.renderer.current_pipeline_index as i8 - 1).abs() as usize;
+            self.renderer.material_override = match self.renderer.material_override {
+                Some(_) => None,
+                None => Some(MaterialPreset::Debug.id()),
+            };
         }
 
+        self.console_input();
+
         for gameobject in &self.gameobjects {
-            gameobject.render(&self.renderer);
+            gameobject.render(&mut self.renderer);
+        }
+    }
+
+    /// Builds the palette entry if it's affordable, the selection is dropped when it isn't anymore.
+    fn place_structure(&mut self, palette_index: usize, position: Vector3<f32>) {
+        let entry = &hud::PALETTE[palette_index];
+        if self.game_controller.resources.spend(&entry.cost) {
+            let tile = Vector2::new(position.x.max(0.) as usize, position.z.max(0.) as usize);
+            self.map.set_tile_flag_at(&tile, TileFlag::BuildingOnTop);
+            self.create_obj(
+                &GameObjectCreateInfo::default()
+                    .mesh_preset(entry.mesh)
+                    .transform(GameObjectTransform::default().position(position)),
+            );
+        }
+        if !self.game_controller.resources.covers(&entry.cost) {
+            self.hud.placing = None;
+        }
+    }
+
+    /// Shows the frame rate and its settings, the mouse ray, and the hitbox of the hovered object.
+    fn debug_draw(&mut self, hovered_object: Option<usize>) {
+        let frame_time = self.delta_time.as_secs_f32();
+        if frame_time > 0. {
+            self.renderer.draw_text(
+                &format!(
+                    "{:.0} FPS\n{:.2} ms\n{:?} ({})\nLimit: {} ({})",
+                    1. / frame_time,
+                    frame_time * 1000.,
+                    self.renderer.present_mode(),
+                    self.input.bindings.describe(Action::CyclePresentMode),
+                    self.frame_limiter
+                        .max_fps
+                        .map_or("none".to_string(), |max_fps| max_fps.to_string()),
+                    self.input.bindings.describe(Action::CycleFrameLimit),
+                ),
+                Vector2::new(10., 46.),
+                1.,
+                Vector4::new(1., 1., 1., 1.),
+            );
+        }
+
+        if let Some(mouse_ray) = self.mouse_ray() {
+            mouse_ray.debug_draw(&mut self.renderer, 10., Vector3::new(1., 0., 0.));
+        }
+
+        if let Some(index) = hovered_object {
+            self.gameobjects[index].debug_draw_hitbox(&mut self.renderer, Vector3::new(0., 1., 0.));
         }
     }
 
     pub fn setup(&mut self) {
-        self.create_obj(&GameObjectCreateInfo::default().mesh_preset(MeshPreset::Map).flags(&[GameObjectFlag::Map]));
+        self.create_obj(
+            &GameObjectCreateInfo::default()
+                .mesh_preset(MeshPreset::Map)
+                .flags(&[GameObjectFlag::Map]),
+        );
         self.create_obj(
             &GameObjectCreateInfo::default()
                 .mesh_preset(MeshPreset::MapSelection)
+                .material(MaterialPreset::Transparent.id())
+                .flags(&[GameObjectFlag::NotClickable]),
+        );
+        self.create_obj(
+            &GameObjectCreateInfo::default()
+                .mesh_preset(MeshPreset::Water)
+                .material(MaterialPreset::Water.id())
                 .flags(&[GameObjectFlag::NotClickable]),
         );
     }
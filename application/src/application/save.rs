@@ -0,0 +1,98 @@
+use std::io::ErrorKind;
+
+use renderer::msg;
+
+use super::{bookmarks::BOOKMARK_COUNT, camera::CameraView, App};
+
+pub const SAVE_PATH: &str = "save.txt";
+
+/// What is kept between sessions, written as `key = value` lines.
+/// Unknown keys are skipped, so saves from other versions still load.
+#[derive(Default)]
+pub struct SaveGame {
+    pub camera: Option<CameraView>,
+    pub bookmarks: [Option<CameraView>; BOOKMARK_COUNT],
+}
+
+impl SaveGame {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut save = Self::default();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", line_index + 1))?;
+            let with_line = |err: String| format!("line {}: {err}", line_index + 1);
+
+            let mut key_parts = key.split_whitespace();
+            match (key_parts.next(), key_parts.next()) {
+                (Some("camera"), None) => {
+                    save.camera = Some(value.trim().parse().map_err(with_line)?)
+                }
+                (Some("bookmark"), Some(index)) => {
+                    let slot = index
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| save.bookmarks.get_mut(index))
+                        .ok_or_else(|| with_line(format!("no bookmark `{index}`")))?;
+                    *slot = Some(value.trim().parse().map_err(with_line)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(save)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(camera) = &self.camera {
+            text += &format!("camera = {camera}\n");
+        }
+        for (index, bookmark) in self.bookmarks.iter().enumerate() {
+            if let Some(bookmark) = bookmark {
+                text += &format!("bookmark {index} = {bookmark}\n");
+            }
+        }
+        text
+    }
+}
+
+impl<'a> App<'a> {
+    /// Writes the save file, failing only logs it.
+    pub fn save_game(&self) {
+        let save = SaveGame {
+            camera: Some(self.camera.view()),
+            bookmarks: self.bookmarks,
+        };
+        if let Err(err) = std::fs::write(SAVE_PATH, save.to_text()) {
+            msg!(error, format!("Failed to write {SAVE_PATH}: {err}"));
+        }
+    }
+
+    /// Restores the save file if there is one, a broken one is logged and ignored.
+    pub fn load_game(&mut self) {
+        let text = match std::fs::read_to_string(SAVE_PATH) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return,
+            Err(err) => {
+                msg!(warn, format!("Failed to read {SAVE_PATH}: {err}"));
+                return;
+            }
+        };
+        let save = match SaveGame::parse(&text) {
+            Ok(save) => save,
+            Err(err) => {
+                msg!(warn, format!("Failed to load {SAVE_PATH}, {err}"));
+                return;
+            }
+        };
+
+        if let Some(camera) = &save.camera {
+            self.camera.set_view(camera);
+        }
+        self.bookmarks = save.bookmarks;
+    }
+}
@@ -0,0 +1,161 @@
+use nalgebra::{Vector2, Vector3, Vector4};
+use objects::{getters::Getters, GameObjectFlag, SelectionState};
+use winit::event::ModifiersState;
+
+use crate::{bindings::Action, input::EventState, ui::Rect};
+
+use super::App;
+
+const SELECTION_BOX_COLOR: Vector4<f32> = Vector4::new(0.3, 0.6, 1., 0.15);
+const SELECTION_BOX_BORDER_COLOR: Vector4<f32> = Vector4::new(0.4, 0.7, 1., 0.9);
+
+/// How the clicked or boxed objects change the selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionMode {
+    Replace,
+    Add,
+    Toggle,
+}
+
+impl SelectionMode {
+    /// Ctrl toggles, Shift adds, nothing replaces the selection.
+    pub fn from_modifiers(modifiers: ModifiersState) -> Self {
+        if modifiers.ctrl() {
+            SelectionMode::Toggle
+        } else if modifiers.shift() {
+            SelectionMode::Add
+        } else {
+            SelectionMode::Replace
+        }
+    }
+}
+
+impl<'a> App<'a> {
+    /// Marks the object under the cursor as hovered, selected objects keep their state.
+    pub fn hover_object(&mut self, hovered_object: Option<usize>) {
+        for (index, object) in self.gameobjects.content.iter_mut().enumerate() {
+            let selection = if self.selection.contains(&index) {
+                SelectionState::Selected
+            } else if hovered_object == Some(index) {
+                SelectionState::Hovered
+            } else {
+                SelectionState::None
+            };
+            object.set_selection(selection);
+        }
+    }
+
+    /// Changes the selection set with the objects, and the objects' selection state with it.
+    pub fn select_objects(
+        &mut self,
+        objects: impl IntoIterator<Item = usize>,
+        mode: SelectionMode,
+    ) {
+        if mode == SelectionMode::Replace {
+            self.selection.clear();
+        }
+        for index in objects {
+            if mode == SelectionMode::Toggle && self.selection.contains(&index) {
+                self.selection.remove(&index);
+            } else {
+                self.selection.insert(index);
+            }
+        }
+
+        for (index, object) in self.gameobjects.content.iter_mut().enumerate() {
+            if self.selection.contains(&index) {
+                object.set_selection(SelectionState::Selected);
+            } else if object.selection() == SelectionState::Selected {
+                object.set_selection(SelectionState::None);
+            }
+        }
+    }
+
+    /// Starts a click or box selection at the mouse, it's applied when the button is released.
+    #[inline]
+    pub fn start_selection_drag(&mut self) {
+        self.selection_drag_start = Some(self.input.mouse.pos);
+    }
+
+    /// Draws the box while the selection is dragged and selects when it's released,
+    /// the hovered object alone if the mouse barely moved.
+    pub fn update_selection_drag(&mut self, hovered_object: Option<usize>) {
+        let Some(start) = self.selection_drag_start else {
+            return;
+        };
+        let selection_box = Rect::from_corners(start, self.input.mouse.pos);
+        //A dragged button stays a box when the mouse comes back, a key bound selection is measured here
+        let is_box = self.input.action_drag(Action::Select).is_some()
+            || (self.input.mouse.pos - start).norm() >= self.input.mouse.settings.drag_threshold;
+
+        if self.input.action_state(Action::Select, EventState::Up) {
+            let mode = SelectionMode::from_modifiers(self.input.modifier);
+            if is_box {
+                let boxed_objects = self.objects_in_screen_rect(&selection_box);
+                self.select_objects(boxed_objects, mode);
+            } else {
+                self.select_objects(hovered_object, mode);
+            }
+            self.selection_drag_start = None;
+        } else if is_box {
+            self.draw_selection_box(&selection_box);
+        }
+    }
+
+    fn draw_selection_box(&mut self, rect: &Rect) {
+        self.renderer
+            .draw_rect(rect.position, rect.size, SELECTION_BOX_COLOR);
+        let corners = [
+            rect.position,
+            rect.position + Vector2::new(rect.size.x, 0.),
+            rect.position + rect.size,
+            rect.position + Vector2::new(0., rect.size.y),
+        ];
+        for (index, &corner) in corners.iter().enumerate() {
+            self.renderer.draw_line(
+                corner,
+                corners[(index + 1) % corners.len()],
+                1.,
+                SELECTION_BOX_BORDER_COLOR,
+            );
+        }
+    }
+
+    /// The selectable objects whose position is inside the rectangle on the screen.
+    pub fn objects_in_screen_rect(&self, rect: &Rect) -> Vec<usize> {
+        let screen_size = self.input.window_size;
+        let aspect_ratio = self.input.aspect_ratio();
+        self.gameobjects
+            .content
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| {
+                !object.has_flag(GameObjectFlag::Map)
+                    && !object.has_flag(GameObjectFlag::NotClickable)
+            })
+            .filter(|(_, object)| {
+                self.camera
+                    .world_to_screen(object.transform().get_position(), aspect_ratio)
+                    .map(|relative_position| {
+                        (relative_position + Vector2::new(1., 1.)).component_mul(&screen_size) / 2.
+                    })
+                    .is_some_and(|screen_position| rect.contains(screen_position))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Moves the camera to the middle of the selected objects.
+    pub fn focus_selection(&mut self) {
+        if self.selection.is_empty() {
+            return;
+        }
+        let position_sum = self
+            .selection
+            .iter()
+            .map(|&index| self.gameobjects[index].transform().get_position())
+            .sum::<Vector3<f32>>();
+        self.camera
+            .focus_on(position_sum.xz() / self.selection.len() as f32);
+    }
+}
@@ -0,0 +1,429 @@
+use std::{fmt, io::ErrorKind, str::FromStr};
+
+use renderer::msg;
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+use crate::{
+    application::bookmarks::BOOKMARK_COUNT,
+    input::{key_from_name, EventState, Input},
+};
+
+pub const BINDINGS_PATH: &str = "bindings.txt";
+
+/// Something the player does, the keys and buttons doing it are set by the [`Bindings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    PanForward,
+    PanBackward,
+    PanLeft,
+    PanRight,
+    RotateCamera,
+    Select,
+    CancelPlacement,
+    FocusSelection,
+    ToggleProjection,
+    ToggleDebugDraw,
+    ToggleWireframe,
+    CyclePresentMode,
+    CycleFrameLimit,
+    ReloadBindings,
+    ToggleConsole,
+    StoreBookmark(usize),
+    RecallBookmark(usize),
+}
+
+impl Action {
+    const SIMPLE: [Action; 15] = [
+        Action::PanForward,
+        Action::PanBackward,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::RotateCamera,
+        Action::Select,
+        Action::CancelPlacement,
+        Action::FocusSelection,
+        Action::ToggleProjection,
+        Action::ToggleDebugDraw,
+        Action::ToggleWireframe,
+        Action::CyclePresentMode,
+        Action::CycleFrameLimit,
+        Action::ReloadBindings,
+        Action::ToggleConsole,
+    ];
+
+    /// Every action, the bookmark ones once per bookmark.
+    pub fn all() -> impl Iterator<Item = Action> {
+        Self::SIMPLE.into_iter().chain(
+            (0..BOOKMARK_COUNT)
+                .flat_map(|index| [Action::StoreBookmark(index), Action::RecallBookmark(index)]),
+        )
+    }
+
+    /// Name in the bindings file, without the bookmark index.
+    fn name(&self) -> &'static str {
+        match self {
+            Action::PanForward => "pan_forward",
+            Action::PanBackward => "pan_backward",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::RotateCamera => "rotate_camera",
+            Action::Select => "select",
+            Action::CancelPlacement => "cancel_placement",
+            Action::FocusSelection => "focus_selection",
+            Action::ToggleProjection => "toggle_projection",
+            Action::ToggleDebugDraw => "toggle_debug_draw",
+            Action::ToggleWireframe => "toggle_wireframe",
+            Action::CyclePresentMode => "cycle_present_mode",
+            Action::CycleFrameLimit => "cycle_frame_limit",
+            Action::ReloadBindings => "reload_bindings",
+            Action::ToggleConsole => "toggle_console",
+            Action::StoreBookmark(_) => "store_bookmark",
+            Action::RecallBookmark(_) => "recall_bookmark",
+        }
+    }
+}
+
+/// Written as the name, followed by the index for the bookmarks: `store_bookmark 3`.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::StoreBookmark(index) | Action::RecallBookmark(index) => {
+                write!(f, "{} {index}", self.name())
+            }
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = text.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let index = parts.next();
+        if parts.next().is_some() {
+            return Err(format!("expected an action, found `{text}`"));
+        }
+
+        match index {
+            None => Self::SIMPLE
+                .into_iter()
+                .find(|action| action.name() == name),
+            Some(index) => {
+                let index = index
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < BOOKMARK_COUNT)
+                    .ok_or_else(|| format!("no bookmark `{index}`"))?;
+                [Action::StoreBookmark(index), Action::RecallBookmark(index)]
+                    .into_iter()
+                    .find(|action| action.name() == name)
+            }
+        }
+        .ok_or_else(|| format!("unknown action `{text}`"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingInput {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button, with the modifiers that have to be held with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding {
+    pub input: BindingInput,
+    pub modifiers: ModifiersState,
+}
+
+impl Binding {
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self {
+            input: BindingInput::Key(key),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            input: BindingInput::Mouse(button),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn modifiers(mut self, modifiers: ModifiersState) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Returns *true* if the modifiers of the binding are all held, others can be held too.
+    #[inline]
+    fn modifiers_held(&self, input: &Input) -> bool {
+        input.modifier.contains(self.modifiers)
+    }
+
+    #[inline]
+    fn input_state(&self, input: &Input, state: EventState) -> bool {
+        match self.input {
+            //The keyboard types in the text field with the focus instead
+            BindingInput::Key(key) => input.text_focus().is_none() && input.key_state(key, state),
+            //The UI is still clicked while text is typed, the world checks the focus itself
+            BindingInput::Mouse(button) => input.mouse_button_state(button, state),
+        }
+    }
+}
+
+const MODIFIER_NAMES: [(ModifiersState, &str); 4] = [
+    (ModifiersState::CTRL, "Ctrl"),
+    (ModifiersState::SHIFT, "Shift"),
+    (ModifiersState::ALT, "Alt"),
+    (ModifiersState::LOGO, "Logo"),
+];
+
+const MOUSE_BUTTON_NAMES: [(MouseButton, &str); 3] = [
+    (MouseButton::Left, "MouseLeft"),
+    (MouseButton::Right, "MouseRight"),
+    (MouseButton::Middle, "MouseMiddle"),
+];
+
+/// Written as the modifiers and the key joined by `+`: `Ctrl+Shift+Key1`, `MouseMiddle`, `Mouse4`.
+/// Keys are named like winit's [`VirtualKeyCode`] variants, the other mouse buttons by their number.
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match self.input {
+            BindingInput::Key(key) => write!(f, "{key:?}"),
+            BindingInput::Mouse(MouseButton::Other(number)) => write!(f, "Mouse{number}"),
+            BindingInput::Mouse(button) => {
+                let (_, name) = MOUSE_BUTTON_NAMES
+                    .iter()
+                    .find(|(named_button, _)| *named_button == button)
+                    .ok_or(fmt::Error)?;
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let input_name = parts.pop().unwrap_or_default();
+
+        let mut modifiers = ModifiersState::empty();
+        for part in parts {
+            let (modifier, _) = MODIFIER_NAMES
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(part))
+                .ok_or_else(|| format!("unknown modifier `{part}`"))?;
+            modifiers |= *modifier;
+        }
+
+        let input = if let Some((button, _)) = MOUSE_BUTTON_NAMES
+            .iter()
+            .find(|(_, name)| *name == input_name)
+        {
+            BindingInput::Mouse(*button)
+        } else if let Some(number) = input_name
+            .strip_prefix("Mouse")
+            .and_then(|number| number.parse().ok())
+        {
+            BindingInput::Mouse(MouseButton::Other(number))
+        } else {
+            key_from_name(input_name)
+                .map(BindingInput::Key)
+                .ok_or_else(|| format!("unknown key `{input_name}`"))?
+        };
+
+        Ok(Self { input, modifiers })
+    }
+}
+
+/// Maps the [`Action`]s to keys and mouse buttons, read from a `action = binding, binding` file.
+/// Actions missing from the file keep their default bindings, an empty list unbinds them.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Self { bindings: vec![] };
+        bindings
+            .bind(Action::PanForward, Binding::key(VirtualKeyCode::W))
+            .bind(Action::PanBackward, Binding::key(VirtualKeyCode::S))
+            .bind(Action::PanLeft, Binding::key(VirtualKeyCode::A))
+            .bind(Action::PanRight, Binding::key(VirtualKeyCode::D))
+            .bind(Action::RotateCamera, Binding::mouse(MouseButton::Middle))
+            .bind(Action::Select, Binding::mouse(MouseButton::Left))
+            .bind(
+                Action::CancelPlacement,
+                Binding::key(VirtualKeyCode::Escape),
+            )
+            .bind(Action::FocusSelection, Binding::key(VirtualKeyCode::F))
+            .bind(Action::ToggleProjection, Binding::key(VirtualKeyCode::F7))
+            .bind(Action::ToggleDebugDraw, Binding::key(VirtualKeyCode::F3))
+            .bind(Action::ToggleWireframe, Binding::key(VirtualKeyCode::Space))
+            .bind(Action::CyclePresentMode, Binding::key(VirtualKeyCode::F5))
+            .bind(Action::CycleFrameLimit, Binding::key(VirtualKeyCode::F6))
+            .bind(Action::ReloadBindings, Binding::key(VirtualKeyCode::F9))
+            .bind(Action::ToggleConsole, Binding::key(VirtualKeyCode::Grave));
+
+        const NUMBER_KEYS: [VirtualKeyCode; BOOKMARK_COUNT] = [
+            VirtualKeyCode::Key0,
+            VirtualKeyCode::Key1,
+            VirtualKeyCode::Key2,
+            VirtualKeyCode::Key3,
+            VirtualKeyCode::Key4,
+            VirtualKeyCode::Key5,
+            VirtualKeyCode::Key6,
+            VirtualKeyCode::Key7,
+            VirtualKeyCode::Key8,
+            VirtualKeyCode::Key9,
+        ];
+        for (index, key) in NUMBER_KEYS.into_iter().enumerate() {
+            bindings
+                .bind(
+                    Action::StoreBookmark(index),
+                    Binding::key(key).modifiers(ModifiersState::CTRL),
+                )
+                .bind(Action::RecallBookmark(index), Binding::key(key));
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// Adds a binding to the action, the ones it already has are kept.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> &mut Self {
+        if !self.bindings.contains(&(action, binding)) {
+            self.bindings.push((action, binding));
+        }
+        self
+    }
+
+    /// Removes every binding of the action.
+    pub fn unbind(&mut self, action: Action) -> &mut Self {
+        self.bindings
+            .retain(|(bound_action, _)| *bound_action != action);
+        self
+    }
+
+    pub fn bindings_of(&self, action: Action) -> impl Iterator<Item = &Binding> {
+        self.bindings
+            .iter()
+            .filter(move |(bound_action, _)| *bound_action == action)
+            .map(|(_, binding)| binding)
+    }
+
+    /// The mouse buttons bound to the action, whatever the modifiers of their bindings.
+    pub fn mouse_buttons(&self, action: Action) -> impl Iterator<Item = MouseButton> + '_ {
+        self.bindings_of(action)
+            .filter_map(|binding| match binding.input {
+                BindingInput::Mouse(button) => Some(button),
+                BindingInput::Key(_) => None,
+            })
+    }
+
+    /// The bindings of the action as written in the file, to show them to the player.
+    pub fn describe(&self, action: Action) -> String {
+        let bindings: Vec<String> = self
+            .bindings_of(action)
+            .map(|binding| binding.to_string())
+            .collect();
+        bindings.join(", ")
+    }
+
+    /// Same meaning as [`Input::key_state`], true if any binding of the action is in the state.
+    /// A binding is shadowed while the modifiers of a more specific one on the same input are held,
+    /// so `1` doesn't recall a bookmark when `Ctrl+1` stores it.
+    pub fn state(&self, input: &Input, action: Action, state: EventState) -> bool {
+        self.bindings_of(action).any(|binding| {
+            binding.modifiers_held(input)
+                && binding.input_state(input, state)
+                && !self.bindings.iter().any(|(_, other)| {
+                    other.input == binding.input
+                        && other.modifiers != binding.modifiers
+                        && other.modifiers.contains(binding.modifiers)
+                        && other.modifiers_held(input)
+                })
+        })
+    }
+
+    /// Replaces the bindings of an action with the ones of a `action = binding, binding` line.
+    /// Nothing changes if the line is broken.
+    pub fn set_from_line(&mut self, line: &str) -> Result<(), String> {
+        let (action, value) = line.split_once('=').ok_or("expected `action = binding`")?;
+        let action = action.trim().parse::<Action>()?;
+        let bindings = value
+            .split(',')
+            .map(str::trim)
+            .filter(|binding| !binding.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Binding>, _>>()?;
+
+        self.unbind(action);
+        for binding in bindings {
+            self.bind(action, binding);
+        }
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            bindings
+                .set_from_line(line)
+                .map_err(|err| format!("line {}: {err}", line_index + 1))?;
+        }
+        Ok(bindings)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text =
+            String::from("# action = binding, binding (modifiers: Ctrl+, Shift+, Alt+, Logo+)\n");
+        for action in Action::all() {
+            text += &format!("{action} = {}\n", self.describe(action));
+        }
+        text
+    }
+
+    /// Reads the bindings file, writing the defaults to it when there's none so they can be edited.
+    /// A broken file is logged and the defaults are used.
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).unwrap_or_else(|err| {
+                msg!(warn, format!("Failed to load {path}, {err}"));
+                Self::default()
+            }),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let bindings = Self::default();
+                bindings.save(path);
+                bindings
+            }
+            Err(err) => {
+                msg!(warn, format!("Failed to read {path}: {err}"));
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the bindings file, failing only logs it.
+    pub fn save(&self, path: &str) {
+        if let Err(err) = std::fs::write(path, self.to_text()) {
+            msg!(error, format!("Failed to write {path}: {err}"));
+        }
+    }
+}
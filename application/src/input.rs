@@ -1,13 +1,40 @@
+use std::{collections::HashMap, time::Duration};
+
 use nalgebra::Vector2;
+use renderer::msg;
 use winit::event::{ElementState, Event, ModifiersState, MouseButton, VirtualKeyCode};
 
-use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::{
+    bindings::{Action, Bindings},
+    recording::{InputEvent, Player, Recorder, Recording},
+};
+
+//Number of winit's virtual key codes
+pub const KEY_COUNT: usize = 163;
+
+/// The key named like its [`VirtualKeyCode`] variant, as the bindings and recording files write them.
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    //The key codes have no gaps, every index below the count is a variant
+    (0..KEY_COUNT as u32)
+        .map(|index| unsafe { std::mem::transmute::<u32, VirtualKeyCode>(index) })
+        .find(|key| format!("{key:?}") == name)
+}
 
 pub struct Input {
-    pub keys: [EventState; 163],
+    pub keys: [EventState; KEY_COUNT],
     pub modifier: ModifiersState,
     pub mouse: Mouse,
+    //Size of the window's client area in pixels, the mouse position is relative to it
+    pub window_size: Vector2<f32>,
+    pub bindings: Bindings,
+    pub text: TextInput,
+    //Number of frames begun since the start
+    pub frame: u64,
+    //Sum of the delta times the frames began with, the time the events are handled at
+    pub time: Duration,
     last_modified_keys: Vec<u8>,
+    recorder: Option<Recorder>,
+    player: Option<Player>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -19,21 +46,103 @@ pub enum EventState {
 }
 
 impl Input {
-    pub fn init() -> Self {
-        let keys = [EventState::Released; 163];
+    pub fn init(window_size: Vector2<f32>) -> Self {
+        let keys = [EventState::Released; KEY_COUNT];
         Self {
             keys,
+            window_size,
+            bindings: Bindings::default(),
+            text: TextInput::default(),
+            frame: 0,
+            time: Duration::ZERO,
+            recorder: None,
+            player: None,
             modifier: Default::default(),
             last_modified_keys: Vec::default(),
             mouse: Mouse {
-                buttons: [EventState::Released; 3],
+                buttons: HashMap::new(),
                 pos: Default::default(),
                 delta_move: Default::default(),
-                wheel: 0.,
+                wheel: Vector2::zeros(),
+                in_window: false,
+                settings: MouseSettings::default(),
+                presses: HashMap::new(),
+                last_click: None,
+                double_clicks: vec![],
             },
         }
     }
 
+    /// Records the event if a recording is running, then handles it.
+    #[inline]
+    pub fn handle_event(&mut self, event: InputEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(event.clone());
+        }
+        event.apply(self);
+    }
+
+    /// Records the events handled from now on, the frames are closed by [`Input::begin_frame`].
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder::new(self));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
+    /// Advances the input's time and frame and closes the recorded frame, called once per frame before the events are used.
+    /// While a recording is played, its next frame's events are handled and its delta time replaces the
+    /// measured one, so the frames can be driven without a window.
+    /// Returns the delta time the frame lasts.
+    pub fn begin_frame(&mut self, delta_time: Duration) -> Duration {
+        let delta_time = self.play_frame().unwrap_or(delta_time);
+        self.time += delta_time;
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_frame(self.frame, delta_time);
+        }
+        //Counted here and not in refresh, a frame that isn't refreshed still has its number
+        self.frame += 1;
+        delta_time
+    }
+
+    /// Replays the recording from a released state, the window's events should be ignored meanwhile.
+    pub fn start_playback(&mut self, recording: Recording) {
+        self.keys = [EventState::Up; KEY_COUNT];
+        self.mouse.buttons.clear();
+        self.mouse.presses.clear();
+        self.mouse.last_click = None;
+        self.mouse.double_clicks.clear();
+        self.modifier = ModifiersState::empty();
+        self.mouse.wheel = Vector2::zeros();
+        self.last_modified_keys.clear();
+        self.player = Some(Player::new(recording));
+    }
+
+    #[inline]
+    pub fn is_playing(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// Handles the events of the next recorded frame and returns its delta time.
+    /// Returns *None* when nothing is played, the playback stops after the last frame.
+    fn play_frame(&mut self) -> Option<Duration> {
+        let frame = self.player.as_mut()?.next_frame().cloned();
+        match frame {
+            Some(frame) => {
+                for event in frame.events {
+                    self.handle_event(event);
+                }
+                Some(frame.delta_time)
+            }
+            None => {
+                self.player = None;
+                msg!(info, "Finished playing the recording");
+                None
+            }
+        }
+    }
+
     #[inline]
     pub fn set_modif(&mut self, modif: ModifiersState) {
         self.modifier = modif
@@ -54,36 +163,139 @@ impl Input {
         }
     }
 
+    /// Characters only reach the text field with the focus, control ones included.
+    #[inline]
+    pub fn handle_character(&mut self, character: char) {
+        if self.text.focus.is_some() {
+            self.text.typed.push(character);
+        }
+    }
+
+    #[inline]
+    pub fn handle_ime_preedit(&mut self, text: String) {
+        if self.text.focus.is_some() {
+            self.text.preedit = text;
+        }
+    }
+
+    #[inline]
+    pub fn handle_ime_commit(&mut self, text: &str) {
+        if self.text.focus.is_some() {
+            self.text.typed.push_str(text);
+        }
+        self.text.preedit.clear();
+    }
+
+    /// Gives the typed text to the field until the focus is released. Meanwhile the keys stop reaching
+    /// the bindings, and the mouse bindings only reach the UI, the camera and the selection ignore them.
+    pub fn focus_text(&mut self, field: TextFieldId) {
+        if self.text.focus != Some(field) {
+            self.text.typed.clear();
+            self.text.preedit.clear();
+        }
+        self.text.focus = Some(field);
+    }
+
+    pub fn release_text_focus(&mut self) {
+        self.text.focus = None;
+        self.text.typed.clear();
+        self.text.preedit.clear();
+    }
+
+    #[inline]
+    pub fn text_focus(&self) -> Option<TextFieldId> {
+        self.text.focus
+    }
+
+    #[inline]
+    pub fn has_text_focus(&self, field: TextFieldId) -> bool {
+        self.text.focus == Some(field)
+    }
+
+    /// The characters typed since the last call, for the field with the focus.
+    #[inline]
+    pub fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.text.typed)
+    }
+
+    #[inline]
+    pub fn handle_resize(&mut self, width: u32, height: u32) {
+        self.window_size = Vector2::new(width as f32, height as f32);
+    }
+
     #[inline]
     pub fn handle_mouse_move(&mut self, x: f64, y: f64) {
         self.mouse.delta_move.x = x as f32 - self.mouse.pos.x;
         self.mouse.delta_move.y = y as f32 - self.mouse.pos.y;
         self.mouse.pos.x = x as f32;
         self.mouse.pos.y = y as f32;
+        self.mouse.in_window = true;
+
+        let (position, threshold) = (self.mouse.pos, self.mouse.settings.drag_threshold);
+        for press in self.mouse.presses.values_mut() {
+            press.dragging |= (position - press.position).norm() >= threshold;
+        }
     }
 
     #[inline]
+    pub fn handle_mouse_leave(&mut self) {
+        self.mouse.in_window = false;
+    }
+
     pub fn handle_mouse_press(&mut self, button: MouseButton, state: ElementState) {
         match state {
             ElementState::Pressed => {
-                self.mouse.buttons[Self::get_mouse_button_index(button)] = EventState::Pressed;
+                self.mouse.buttons.insert(button, EventState::Pressed);
+                self.mouse.presses.insert(
+                    button,
+                    ButtonPress {
+                        position: self.mouse.pos,
+                        dragging: false,
+                    },
+                );
+
+                //The second press close enough in time and space to the first is a double click
+                let settings = self.mouse.settings;
+                let is_double_click =
+                    self.mouse
+                        .last_click
+                        .is_some_and(|(last_button, last_time, last_position)| {
+                            last_button == button
+                                && self.time - last_time <= settings.double_click_interval
+                                && (self.mouse.pos - last_position).norm() < settings.drag_threshold
+                        });
+                if is_double_click {
+                    self.mouse.double_clicks.push(button);
+                    self.mouse.last_click = None;
+                } else {
+                    self.mouse.last_click = Some((button, self.time, self.mouse.pos));
+                }
             }
             ElementState::Released => {
-                self.mouse.buttons[Self::get_mouse_button_index(button)] = EventState::Released;
+                self.mouse.buttons.insert(button, EventState::Released);
             }
         }
     }
 
+    /// Adds wheel steps, on both axes.
+    #[inline]
+    pub fn handle_mouse_wheel(&mut self, scroll_x: f32, scroll_y: f32) {
+        self.mouse.wheel += Vector2::new(scroll_x, scroll_y);
+    }
+
+    /// Adds a touchpad's or precise wheel's scroll in pixels, counted in wheel steps.
     #[inline]
-    pub fn handle_mouse_wheel(&mut self, scroll_y: f32) {
-        self.mouse.wheel += scroll_y;
+    pub fn handle_mouse_pixel_wheel(&mut self, scroll_x: f64, scroll_y: f64) {
+        self.mouse.wheel += Vector2::new(scroll_x as f32, scroll_y as f32)
+            / self.mouse.settings.pixels_per_wheel_step;
     }
 
     #[inline]
     pub fn refresh(&mut self) {
         self.mouse.delta_move.x = 0.;
         self.mouse.delta_move.y = 0.;
-        self.mouse.wheel = 0.;
+        self.mouse.wheel = Vector2::zeros();
+        self.mouse.double_clicks.clear();
 
         self.last_modified_keys
             .iter()
@@ -95,11 +307,16 @@ impl Input {
 
         self.last_modified_keys.clear();
 
-        self.mouse.buttons.iter_mut().for_each(|mut b| match b {
+        self.mouse.buttons.values_mut().for_each(|b| match b {
             EventState::Pressed => *b = EventState::Down,
             EventState::Released => *b = EventState::Up,
             _ => (),
         });
+        //The presses last until the frame after the release, so the drags can be ended
+        let buttons = &self.mouse.buttons;
+        self.mouse
+            .presses
+            .retain(|button, _| buttons.get(button) == Some(&EventState::Down));
     }
 
     #[inline]
@@ -134,31 +351,136 @@ impl Input {
         }
     }
 
+    /// Same meaning as [`Input::key_state`], for the keys and buttons bound to the action.
+    #[inline]
+    pub fn action_state(&self, action: Action, state: EventState) -> bool {
+        self.bindings.state(self, action, state)
+    }
+
+    /// Returns *true* the frame the button is pressed the second time of a double click.
+    #[inline]
+    pub fn double_clicked(&self, button: MouseButton) -> bool {
+        self.mouse.double_clicks.contains(&button)
+    }
+
+    /// Where the button was pressed, while it's held after the mouse moved past the drag threshold
+    /// and the frame it's released.
+    #[inline]
+    pub fn drag(&self, button: MouseButton) -> Option<Vector2<f32>> {
+        self.mouse
+            .presses
+            .get(&button)
+            .filter(|press| press.dragging)
+            .map(|press| press.position)
+    }
+
+    /// Same as [`Input::double_clicked`], for the mouse buttons bound to the action.
+    #[inline]
+    pub fn action_double_clicked(&self, action: Action) -> bool {
+        self.bindings
+            .mouse_buttons(action)
+            .any(|button| self.double_clicked(button))
+    }
+
+    /// Same as [`Input::drag`], for the mouse buttons bound to the action.
+    #[inline]
+    pub fn action_drag(&self, action: Action) -> Option<Vector2<f32>> {
+        self.bindings
+            .mouse_buttons(action)
+            .find_map(|button| self.drag(button))
+    }
+
+    /// Vertical wheel steps this frame, positive upwards.
     #[inline]
     pub fn get_mouse_wheel(&self) -> f32 {
-        self.mouse.wheel
+        self.mouse.wheel.y
+    }
+
+    /// Horizontal wheel steps this frame, positive to the right.
+    #[inline]
+    pub fn get_horizontal_mouse_wheel(&self) -> f32 {
+        self.mouse.wheel.x
+    }
+
+    /// Width of the window over its height, what the game frames the view with.
+    /// Played recordings set the size, the replay doesn't depend on the window it's shown in.
+    #[inline]
+    pub fn aspect_ratio(&self) -> f32 {
+        self.window_size.x / self.window_size.y.max(1.)
     }
 
+    /// Mouse position from -1 to 1 on both axes, from the top left corner of the window.
     #[inline]
     pub fn get_relative_mouse_position(&self) -> Vector2<f32> {
-        Vector2::new(
-            (self.mouse.pos.x / WINDOW_WIDTH as f32) * 2. - 1.,
-            (self.mouse.pos.y / WINDOW_HEIGHT as f32) * 2. - 1.,
-        )
+        self.mouse
+            .pos
+            .component_div(&self.window_size.sup(&Vector2::new(1., 1.)))
+            * 2.
+            - Vector2::new(1., 1.)
     }
 
+    //Buttons never pressed aren't stored
     fn get_mouse_button_state(&self, button: MouseButton) -> EventState {
-        unsafe { self.mouse.buttons[*(&button as *const _ as *const u8) as usize] }
+        self.mouse
+            .buttons
+            .get(&button)
+            .copied()
+            .unwrap_or(EventState::Up)
     }
+}
+
+/// Names the text field that has the focus, like `"console"`.
+pub type TextFieldId = &'static str;
+
+/// Text typed for the text field with the focus, see [`Input::focus_text`].
+#[derive(Default)]
+pub struct TextInput {
+    focus: Option<TextFieldId>,
+    typed: String,
+    //Text the IME is composing, shown in the field until it's committed
+    pub preedit: String,
+    //Where the IME puts its candidates, under the field's text in pixels
+    pub ime_position: Vector2<f32>,
+}
 
-    pub fn get_mouse_button_index(button: MouseButton) -> usize {
-        unsafe { *(&button as *const _ as *const u8) as usize }
+/// Tuning of the mouse's clicks, drags and wheel.
+#[derive(Clone, Copy, Debug)]
+pub struct MouseSettings {
+    //Longest time between the presses of a double click
+    pub double_click_interval: Duration,
+    //Pixels the mouse moves while a button is held before it's a drag, the presses of a double click are closer
+    pub drag_threshold: f32,
+    //Pixels of a touchpad's scroll counted as one wheel step
+    pub pixels_per_wheel_step: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            double_click_interval: Duration::from_millis(400),
+            drag_threshold: 4.,
+            pixels_per_wheel_step: 40.,
+        }
     }
 }
 
+struct ButtonPress {
+    position: Vector2<f32>,
+    //The mouse moved past the drag threshold since the press
+    dragging: bool,
+}
+
 pub struct Mouse {
-    pub buttons: [EventState; 3],
+    pub buttons: HashMap<MouseButton, EventState>,
     pub pos: Vector2<f32>,
     pub delta_move: Vector2<f32>,
-    pub wheel: f32,
+    //Wheel steps this frame, x is the horizontal wheel
+    pub wheel: Vector2<f32>,
+    //The position is the last one inside the window when it's outside
+    pub in_window: bool,
+    pub settings: MouseSettings,
+    presses: HashMap<MouseButton, ButtonPress>,
+    //Button, time and position of the press a double click can follow
+    last_click: Option<(MouseButton, Duration, Vector2<f32>)>,
+    double_clicks: Vec<MouseButton>,
 }
@@ -1,24 +1,75 @@
 mod application;
+mod bindings;
 mod input;
 mod map;
+mod recording;
+mod ui;
 
 use std::time::Instant;
 
 use application::App;
+use bindings::{Bindings, BINDINGS_PATH};
 use objects::{hitbox::Hitbox, mesh::Mesh};
+use recording::{InputEvent, Recording};
 use winit::{
-    dpi::PhysicalSize,
-    event::{Event, KeyboardInput, MouseScrollDelta, WindowEvent},
-    event_loop::ControlFlow,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
     platform::run_return::EventLoopExtRunReturn,
-    window::Fullscreen,
+    window::{Fullscreen, Window},
 };
 
 use macros::load_consts;
 
 load_consts!("application/src/constants.const");
 
-use renderer::msg;
+use renderer::{error::RendererError, msg, Renderer};
+
+/// Out of date swapchains are rebuilt on the next frame, anything else stops the event loop.
+/// A lost device is flagged, the app is rebuilt on a new one once the loop stopped.
+fn handle_renderer_error(
+    renderer: &mut Renderer,
+    err: RendererError,
+    control_flow: &mut ControlFlow,
+    device_lost: &mut bool,
+) {
+    match err {
+        RendererError::SwapchainOutOfDate => renderer.rebuild_swapchain = true,
+        RendererError::DeviceLost { .. } => {
+            msg!(warn, format!("{err}, recreating the renderer"));
+            *device_lost = true;
+            *control_flow = ControlFlow::Exit;
+        }
+        err => {
+            msg!(error, err.to_string());
+            *control_flow = ControlFlow::Exit;
+        }
+    }
+}
+
+/// Command line options, `--record <file>` records the session's input to the file when the game
+/// exits, `--play <file>` replays a recorded session instead of the window's input.
+#[derive(Default)]
+struct Options {
+    record: Option<String>,
+    play: Option<String>,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = args.next(),
+                "--play" => options.play = args.next(),
+                _ => msg!(warn, format!("Unknown argument `{arg}`")),
+            }
+        }
+        options
+    }
+}
+
 fn main() {
     let mut loggers: Vec<Box<dyn simplelog::SharedLogger>> = vec![simplelog::TermLogger::new(
         simplelog::LevelFilter::Info,
@@ -36,74 +87,108 @@ fn main() {
 
     simplelog::CombinedLogger::init(loggers).unwrap();
 
+    let options = Options::from_args();
+
     let mut event_loop = winit::event_loop::EventLoop::new();
     let window = winit::window::WindowBuilder::new()
         .with_title("HAHA")
         .with_inner_size(PhysicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT))
         .with_fullscreen(Some(Fullscreen::Borderless(None)))
-        .with_resizable(false)
+        .with_resizable(true)
         .build(&event_loop)
         .unwrap();
 
     let mut meshes: Vec<Mesh> = vec![];
     let mut app = App::init(&window, MAP_SIZE, &meshes);
     app.load_meshes(&mut meshes);
+    app.load_font();
     app.setup();
+    app.load_game();
+    app.input.bindings = Bindings::load(BINDINGS_PATH);
+    if options.record.is_some() {
+        app.input.start_recording();
+    }
+    if let Some(path) = &options.play {
+        match Recording::load(path) {
+            Ok(recording) => app.input.start_playback(recording),
+            Err(err) => msg!(error, err),
+        }
+    }
 
-    app.renderer.data.push_const.wh_ratio = app.renderer.base.surface_extent.width as f32
-        / app.renderer.base.surface_extent.height as f32;
-    app.renderer.data.push_const.min_z = -200.;
-    app.renderer.data.push_const.max_z = 200.;
+    //The event loop only stops early when the device is lost, the app then continues on a new one
+    while run(&mut event_loop, &window, &mut app, &options) {
+        app = App::recover(&window, &mut meshes, app.into_state());
+    }
+}
 
+/// Runs the event loop until the game exits, returns *true* if it stopped because the device was lost.
+fn run(event_loop: &mut EventLoop<()>, window: &Window, app: &mut App, options: &Options) -> bool {
     let mut start_time = Instant::now();
-    event_loop.run_return(move |event, _, control_flow| match event {
-        Event::WindowEvent { event, .. } => match event {
-            WindowEvent::CloseRequested => {
-                *control_flow = winit::event_loop::ControlFlow::Exit;
-            }
-            WindowEvent::Resized(..) => {
-                app.renderer.rebuild_swapchain = true;
-            }
-            WindowEvent::CursorMoved { position, .. } => {
-                app.input.handle_mouse_move(position.x, position.y);
-            }
-            WindowEvent::MouseInput { state, button, .. } => {
-                app.input.handle_mouse_press(button, state)
+    let mut ime_allowed = false;
+    let mut ime_position = app.input.text.ime_position;
+    let mut requested_size = window.inner_size();
+    let mut device_lost = false;
+    event_loop.run_return(|event, _, control_flow| match event {
+        Event::WindowEvent { event, .. } => {
+            //The recording being played stands for the window's input
+            if let Some(input_event) = InputEvent::from_window_event(&event) {
+                if !app.input.is_playing() {
+                    app.input.handle_event(input_event);
+                }
             }
-            WindowEvent::MouseWheel {
-                delta: MouseScrollDelta::LineDelta(_, scroll_y),
-                ..
-            } => app.input.handle_mouse_wheel(scroll_y),
-
-            WindowEvent::KeyboardInput {
-                input: keyboard_input,
-                ..
-            } => match keyboard_input {
-                KeyboardInput {
-                    state,
-                    virtual_keycode,
-                    ..
-                } => {
-                    app.input.handle_key_press(virtual_keycode, state);
+
+            match event {
+                WindowEvent::CloseRequested => {
+                    app.save_game();
+                    *control_flow = winit::event_loop::ControlFlow::Exit;
+                }
+                WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                    app.renderer.rebuild_swapchain = true;
                 }
-            },
-            WindowEvent::ModifiersChanged(modifier) => app.input.set_modif(modifier),
-            _ => {}
-        },
+                _ => {}
+            }
+        }
         Event::MainEventsCleared => {
-            app.delta_time = start_time.elapsed();
+            //A minimized window has no surface to draw on
+            let window_size = window.inner_size();
+            if window_size.width == 0 || window_size.height == 0 {
+                return;
+            }
+
+            let elapsed = start_time.elapsed();
 
             start_time = Instant::now();
 
+            app.delta_time = app.input.begin_frame(elapsed);
+
+            //The game reads the played window size, the window is asked for it so the picture isn't stretched
+            if app.input.is_playing() {
+                let played_size = PhysicalSize::new(
+                    app.input.window_size.x as u32,
+                    app.input.window_size.y as u32,
+                );
+                if played_size != requested_size {
+                    requested_size = played_size;
+                    window.set_fullscreen(None);
+                    window.set_inner_size(played_size);
+                }
+            }
+
             if app.renderer.rebuild_swapchain {
                 app.renderer.rebuild_swapchain = false;
-                if let Err(msg) = app.renderer.resize(&window) {
-                    msg!(error, msg);
+                if let Err(err) = app.renderer.resize(window) {
+                    handle_renderer_error(&mut app.renderer, err, control_flow, &mut device_lost);
+                    app.input.refresh();
                     return;
                 }
             }
 
-            app.renderer.prepare_renderer().unwrap();
+            //The frame has begun, its events are used up even if it isn't drawn
+            if let Err(err) = app.renderer.prepare_renderer() {
+                handle_renderer_error(&mut app.renderer, err, control_flow, &mut device_lost);
+                app.input.refresh();
+                return;
+            }
 
             app.renderer.data.dynamic_uniform_buffer.update(
                 &app.renderer.base.device,
@@ -111,18 +196,41 @@ fn main() {
             );
 
             app.renderer.data.world_view.view = *app.camera.get_transform();
-            app.camera
-                .camera_move(&app.input, app.delta_time.as_secs_f32());
+            app.renderer.data.world_view.projection =
+                app.camera.projection_matrix(app.input.aspect_ratio());
+            app.camera.camera_move(
+                &app.input,
+                app.input.aspect_ratio(),
+                app.delta_time.as_secs_f32(),
+            );
             app.main_loop();
 
-            if let Err(msg) = app.renderer.flush() {
-                msg!(error, msg);
-                *control_flow = ControlFlow::Exit;
-                return;
+            //The IME only composes for the text field with the focus, its candidates go under the field
+            let text_focused = app.input.text_focus().is_some();
+            if text_focused != ime_allowed {
+                ime_allowed = text_focused;
+                window.set_ime_allowed(text_focused);
+            }
+            if text_focused && app.input.text.ime_position != ime_position {
+                ime_position = app.input.text.ime_position;
+                window.set_ime_position(PhysicalPosition::new(ime_position.x, ime_position.y));
+            }
+
+            //The input is refreshed even if presenting failed, or the pressed keys would fire again
+            if let Err(err) = app.renderer.flush() {
+                handle_renderer_error(&mut app.renderer, err, control_flow, &mut device_lost);
             }
 
             app.input.refresh();
+            app.frame_limiter.wait(start_time);
+        }
+        //Any exit ends up here, closing the window or a renderer error, the recording goes on with a new device
+        Event::LoopDestroyed if !device_lost => {
+            if let (Some(recording), Some(path)) = (app.input.stop_recording(), &options.record) {
+                recording.save(path);
+            }
         }
         _ => {}
     });
+    device_lost
 }
@@ -1,9 +1,9 @@
 use std::ops::Range;
 
+use crate::MAP_SIZE;
 use nalgebra::Vector3;
-use objects::{mesh::Mesh, hitbox::Hitbox};
+use objects::{hitbox::Hitbox, mesh::Mesh};
 use renderer::Renderer;
-use crate::MAP_SIZE;
 
 use super::Map;
 
@@ -78,24 +78,33 @@ impl Map {
             }
             y += 1;
         }
-        let (mut water_vertices, water_quad) = Mesh::quad(
+        Mesh::new(
+            renderer,
+            vertices,
+            indicies,
+            // Hitbox::new(vertex_positions, vec![], hitbox_quads),
+            //We currently use a specialized function for intersections with the map, so no hitbox is needed.
+            Hitbox::new(vec![], vec![], vec![]),
+        )
+    }
+    /// The water plane under the tiles, drawn with its own material.
+    pub fn water_mesh(renderer: &mut Renderer) -> Mesh {
+        //The water material gives the color
+        let (vertices, water_quad) = Mesh::quad(
             [
                 Vector3::new(0., 0.2, 0.),
                 Vector3::new(0., 0.2, MAP_SIZE as f32),
                 Vector3::new(MAP_SIZE as f32, 0.2, MAP_SIZE as f32),
                 Vector3::new(MAP_SIZE as f32, 0.2, 0.),
             ],
-            Vector3::new(39. / 255., 144. / 255., 176. / 255.),
-            tile_index * 20,
+            Vector3::new(1., 1., 1.),
+            0,
         );
-        vertices.append(&mut water_vertices);
-        indicies.append(&mut water_quad.triangulated_indicies());
         Mesh::new(
             renderer,
             vertices,
-            indicies,
-            // Hitbox::new(vertex_positions, vec![], hitbox_quads),
-            //We currently use a specialized function for intersections with the map, so no hitbox is needed.
+            water_quad.triangulated_indicies(),
+            //Clicks on the water are handled by the map.
             Hitbox::new(vec![], vec![], vec![]),
         )
     }
@@ -0,0 +1,33 @@
+use super::{tile::TileFlag, Map};
+
+const LAND_COLOR: [u8; 4] = [148, 186, 101, 255];
+const WATER_COLOR: [u8; 4] = [39, 144, 176, 255];
+const STRUCTURE_COLOR: [u8; 4] = [150, 90, 50, 255];
+const UNIT_COLOR: [u8; 4] = [220, 50, 50, 255];
+
+impl Map {
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.matrix.len()
+    }
+
+    ///Returns one RGBA pixel per tile, row by row, the same layout as the tile matrix.
+    pub fn minimap_pixels(&self) -> Vec<u8> {
+        self.matrix
+            .iter()
+            .flatten()
+            .flat_map(|tile| {
+                //Units are drawn over structures, structures over the ground
+                if tile.flag_active(TileFlag::TroopOnTop) {
+                    UNIT_COLOR
+                } else if tile.flag_active(TileFlag::BuildingOnTop) {
+                    STRUCTURE_COLOR
+                } else if tile.is_solid() {
+                    LAND_COLOR
+                } else {
+                    WATER_COLOR
+                }
+            })
+            .collect()
+    }
+}
@@ -11,15 +11,17 @@ use self::{heightmap::HeightMap, structure::Structure, tile::Tile};
 pub mod heightmap;
 pub mod maphitbox;
 pub mod mapmesh;
+pub mod minimap;
 pub mod structure;
 pub mod tile;
 
 pub struct Map {
     matrix: Vec<Vec<Tile>>,
+    //Set when a tile changes, until taken by take_modified
+    modified: bool,
 }
 
 impl Map {
-
     pub fn generate(size: usize) -> Self {
         let heightmap = HeightMap::perlin_noise(size, 30., 0.65, 4);
         let mut tile_matrix = vec::from_elem(vec::from_elem(Tile::none(), size), size);
@@ -32,6 +34,13 @@ impl Map {
         }
         Self {
             matrix: tile_matrix,
+            modified: true,
         }
     }
+
+    /// Returns *true* once after any tile changed, for the views of the map to update.
+    #[inline]
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
 }
@@ -10,7 +10,7 @@ pub enum StructureType {
     // Barracks,
     // Mine,
     // Forestry,
-    // 
+    //
 }
 
 pub enum StructureFlag {
@@ -34,7 +34,5 @@ impl NoneValue for Structure {
 }
 
 impl Map {
-    pub fn build_structure() {
-        
-    }
-}
\ No newline at end of file
+    pub fn build_structure() {}
+}
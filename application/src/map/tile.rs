@@ -58,11 +58,22 @@ impl Map {
     pub unsafe fn get_tile_at_unchecked(&self, coordinates: &Vector2<usize>) -> &Tile {
         debug_assert!(
             coordinates.x <= MAP_SIZE || coordinates.y <= MAP_SIZE,
-            "Cannot get tile at invalid coordinates! {}", coordinates
+            "Cannot get tile at invalid coordinates! {}",
+            coordinates
         );
         return &self.matrix[coordinates.y][coordinates.x];
     }
     #[inline]
+    ///Sets the flag of the tile at the coordinates, returns false if they are out of bounds.
+    pub fn set_tile_flag_at(&mut self, coordinates: &Vector2<usize>, flag: TileFlag) -> bool {
+        if coordinates.x >= self.size() || coordinates.y >= self.size() {
+            return false;
+        }
+        self.matrix[coordinates.y][coordinates.x].set_flag(flag);
+        self.modified = true;
+        true
+    }
+    #[inline]
     ///Checks wether tile is solid at the coordinates
     pub fn is_tile_solid_at(&self, coordinates: &Vector2<usize>) -> bool {
         if coordinates.x >= MAP_SIZE || coordinates.y >= MAP_SIZE {
@@ -0,0 +1,537 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use renderer::msg;
+use winit::event::{
+    ElementState, Ime, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+
+use crate::input::{key_from_name, Input};
+
+/// One input from the window, what [`Input`] records and plays back instead of winit's events.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    Key {
+        key: VirtualKeyCode,
+        state: ElementState,
+    },
+    Modifiers(ModifiersState),
+    MouseMove {
+        x: f64,
+        y: f64,
+    },
+    MouseLeave,
+    MouseButton {
+        button: MouseButton,
+        state: ElementState,
+    },
+    Wheel {
+        x: f32,
+        y: f32,
+    },
+    PixelWheel {
+        x: f64,
+        y: f64,
+    },
+    Resize {
+        width: u32,
+        height: u32,
+    },
+    Character(char),
+    ImePreedit(String),
+    ImeCommit(String),
+}
+
+impl InputEvent {
+    /// The input the window event carries, if it carries one.
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => Some(InputEvent::Key {
+                key: *key,
+                state: *state,
+            }),
+            WindowEvent::ModifiersChanged(modifiers) => Some(InputEvent::Modifiers(*modifiers)),
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::MouseMove {
+                x: position.x,
+                y: position.y,
+            }),
+            WindowEvent::CursorLeft { .. } => Some(InputEvent::MouseLeave),
+            WindowEvent::MouseInput { state, button, .. } => Some(InputEvent::MouseButton {
+                button: *button,
+                state: *state,
+            }),
+            WindowEvent::MouseWheel { delta, .. } => Some(match delta {
+                MouseScrollDelta::LineDelta(x, y) => InputEvent::Wheel { x: *x, y: *y },
+                MouseScrollDelta::PixelDelta(position) => InputEvent::PixelWheel {
+                    x: position.x,
+                    y: position.y,
+                },
+            }),
+            WindowEvent::Resized(size) => Some(InputEvent::Resize {
+                width: size.width,
+                height: size.height,
+            }),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => Some(InputEvent::Resize {
+                width: new_inner_size.width,
+                height: new_inner_size.height,
+            }),
+            WindowEvent::ReceivedCharacter(character) => Some(InputEvent::Character(*character)),
+            WindowEvent::Ime(Ime::Preedit(text, _)) => Some(InputEvent::ImePreedit(text.clone())),
+            WindowEvent::Ime(Ime::Commit(text)) => Some(InputEvent::ImeCommit(text.clone())),
+            _ => None,
+        }
+    }
+
+    /// Gives the event to the input, through the same handlers as the window's events.
+    pub fn apply(self, input: &mut Input) {
+        match self {
+            InputEvent::Key { key, state } => input.handle_key_press(Some(key), state),
+            InputEvent::Modifiers(modifiers) => input.set_modif(modifiers),
+            InputEvent::MouseMove { x, y } => input.handle_mouse_move(x, y),
+            InputEvent::MouseLeave => input.handle_mouse_leave(),
+            InputEvent::MouseButton { button, state } => input.handle_mouse_press(button, state),
+            InputEvent::Wheel { x, y } => input.handle_mouse_wheel(x, y),
+            InputEvent::PixelWheel { x, y } => input.handle_mouse_pixel_wheel(x, y),
+            InputEvent::Resize { width, height } => input.handle_resize(width, height),
+            InputEvent::Character(character) => input.handle_character(character),
+            InputEvent::ImePreedit(text) => input.handle_ime_preedit(text),
+            InputEvent::ImeCommit(text) => input.handle_ime_commit(&text),
+        }
+    }
+}
+
+fn state_name(state: ElementState) -> &'static str {
+    match state {
+        ElementState::Pressed => "pressed",
+        ElementState::Released => "released",
+    }
+}
+
+fn parse_state(text: &str) -> Result<ElementState, String> {
+    match text {
+        "pressed" => Ok(ElementState::Pressed),
+        "released" => Ok(ElementState::Released),
+        _ => Err(format!("expected `pressed` or `released`, found `{text}`")),
+    }
+}
+
+//Text is written as its characters' code points, so any character fits on the line
+fn code_points(text: &str) -> String {
+    text.chars()
+        .map(|character| (character as u32).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_character(code: Option<&str>) -> Result<char, String> {
+    let code = parse_value(code)?;
+    char::from_u32(code).ok_or_else(|| format!("invalid character `{code}`"))
+}
+
+fn parse_code_points<'a>(codes: impl Iterator<Item = &'a str>) -> Result<String, String> {
+    codes.map(|code| parse_character(Some(code))).collect()
+}
+
+fn parse_value<T: FromStr>(text: Option<&str>) -> Result<T, String> {
+    let text = text.ok_or("missing value")?;
+    text.parse().map_err(|_| format!("invalid value `{text}`"))
+}
+
+/// One line, the event's name and its values separated by spaces: `key W pressed`, `mouse_move 12.5 40`.
+/// Mouse buttons other than left, right and middle are written as their number, text as its code points.
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputEvent::Key { key, state } => write!(f, "key {key:?} {}", state_name(*state)),
+            InputEvent::Modifiers(modifiers) => write!(f, "modifiers {}", modifiers.bits()),
+            InputEvent::MouseMove { x, y } => write!(f, "mouse_move {x} {y}"),
+            InputEvent::MouseLeave => write!(f, "mouse_leave"),
+            InputEvent::MouseButton { button, state } => {
+                match button {
+                    MouseButton::Other(number) => write!(f, "mouse_button {number}")?,
+                    button => write!(f, "mouse_button {button:?}")?,
+                }
+                write!(f, " {}", state_name(*state))
+            }
+            InputEvent::Wheel { x, y } => write!(f, "wheel {x} {y}"),
+            InputEvent::PixelWheel { x, y } => write!(f, "pixel_wheel {x} {y}"),
+            InputEvent::Resize { width, height } => write!(f, "resize {width} {height}"),
+            InputEvent::Character(character) => write!(f, "character {}", *character as u32),
+            InputEvent::ImePreedit(text) => write!(f, "ime_preedit {}", code_points(text)),
+            InputEvent::ImeCommit(text) => write!(f, "ime_commit {}", code_points(text)),
+        }
+    }
+}
+
+impl FromStr for InputEvent {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = text.split_whitespace();
+        let event = match parts.next().unwrap_or_default() {
+            "key" => {
+                let name = parts.next().unwrap_or_default();
+                InputEvent::Key {
+                    key: key_from_name(name).ok_or_else(|| format!("unknown key `{name}`"))?,
+                    state: parse_state(parts.next().unwrap_or_default())?,
+                }
+            }
+            "modifiers" => {
+                let bits = parse_value(parts.next())?;
+                InputEvent::Modifiers(ModifiersState::from_bits_truncate(bits))
+            }
+            "mouse_move" => InputEvent::MouseMove {
+                x: parse_value(parts.next())?,
+                y: parse_value(parts.next())?,
+            },
+            "mouse_leave" => InputEvent::MouseLeave,
+            "mouse_button" => InputEvent::MouseButton {
+                button: match parts.next().unwrap_or_default() {
+                    "Left" => MouseButton::Left,
+                    "Right" => MouseButton::Right,
+                    "Middle" => MouseButton::Middle,
+                    number => MouseButton::Other(parse_value(Some(number))?),
+                },
+                state: parse_state(parts.next().unwrap_or_default())?,
+            },
+            "wheel" => {
+                //The first recordings only had the vertical steps
+                let first = parse_value(parts.next())?;
+                match parts.next() {
+                    Some(y) => InputEvent::Wheel {
+                        x: first,
+                        y: parse_value(Some(y))?,
+                    },
+                    None => InputEvent::Wheel { x: 0., y: first },
+                }
+            }
+            "pixel_wheel" => InputEvent::PixelWheel {
+                x: parse_value(parts.next())?,
+                y: parse_value(parts.next())?,
+            },
+            "resize" => InputEvent::Resize {
+                width: parse_value(parts.next())?,
+                height: parse_value(parts.next())?,
+            },
+            "character" => InputEvent::Character(parse_character(parts.next())?),
+            "ime_preedit" => InputEvent::ImePreedit(parse_code_points(parts.by_ref())?),
+            "ime_commit" => InputEvent::ImeCommit(parse_code_points(parts.by_ref())?),
+            name => return Err(format!("unknown event `{name}`")),
+        };
+        match parts.next() {
+            Some(extra) => Err(format!("unexpected `{extra}`")),
+            None => Ok(event),
+        }
+    }
+}
+
+/// The events received before a frame, and how long the frame took.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    //Counted from the start of the recording
+    pub frame: u64,
+    pub delta_time: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+/// Every frame of a session, written as a `frame <number> <nanoseconds>` line per frame
+/// followed by a line per event.
+/// Frames without events are kept, their delta times are needed to replay the session,
+/// so the frame numbers have to follow each other.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut recording = Self::default();
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let with_line = |err: String| format!("line {}: {err}", line_index + 1);
+
+            if let Some(frame) = line.strip_prefix("frame ") {
+                let mut parts = frame.split_whitespace();
+                let frame = parse_value(parts.next()).map_err(with_line)?;
+                let expected_frame = recording.frames.len() as u64;
+                if frame != expected_frame {
+                    return Err(with_line(format!(
+                        "expected frame {expected_frame}, found frame {frame}"
+                    )));
+                }
+                recording.frames.push(RecordedFrame {
+                    frame,
+                    delta_time: Duration::from_nanos(parse_value(parts.next()).map_err(with_line)?),
+                    events: vec![],
+                });
+            } else {
+                let event = line.parse().map_err(with_line)?;
+                recording
+                    .frames
+                    .last_mut()
+                    .ok_or_else(|| with_line("event before the first frame".to_string()))?
+                    .events
+                    .push(event);
+            }
+        }
+        Ok(recording)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for frame in &self.frames {
+            text += &format!("frame {} {}\n", frame.frame, frame.delta_time.as_nanos());
+            for event in &frame.events {
+                text += &format!("{event}\n");
+            }
+        }
+        text
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| format!("Failed to read {path}: {err}"))?;
+        Self::parse(&text).map_err(|err| format!("Failed to load {path}, {err}"))
+    }
+
+    /// Writes the recording file, failing only logs it.
+    pub fn save(&self, path: &str) {
+        if let Err(err) = std::fs::write(path, self.to_text()) {
+            msg!(error, format!("Failed to write {path}: {err}"));
+        }
+    }
+}
+
+/// Collects the events of the frame being recorded.
+pub struct Recorder {
+    recording: Recording,
+    pending_events: Vec<InputEvent>,
+    //Input's frame the recording started at
+    first_frame: u64,
+}
+
+impl Recorder {
+    /// The recording starts with the state the input is in, so playing it back doesn't depend on it.
+    pub fn new(input: &Input) -> Self {
+        let mut pending_events = vec![
+            InputEvent::Resize {
+                width: input.window_size.x as u32,
+                height: input.window_size.y as u32,
+            },
+            InputEvent::Modifiers(input.modifier),
+            InputEvent::MouseMove {
+                x: input.mouse.pos.x as f64,
+                y: input.mouse.pos.y as f64,
+            },
+        ];
+        if !input.mouse.in_window {
+            pending_events.push(InputEvent::MouseLeave);
+        }
+        Self {
+            recording: Recording::default(),
+            pending_events,
+            first_frame: input.frame,
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, event: InputEvent) {
+        self.pending_events.push(event);
+    }
+
+    /// Closes the frame with the events received since the last one.
+    pub fn end_frame(&mut self, frame: u64, delta_time: Duration) {
+        self.recording.frames.push(RecordedFrame {
+            frame: frame - self.first_frame,
+            delta_time,
+            events: std::mem::take(&mut self.pending_events),
+        });
+    }
+
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+/// Hands out the frames of a recording in order.
+pub struct Player {
+    recording: Recording,
+    next_frame: usize,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+        }
+    }
+
+    pub fn next_frame(&mut self) -> Option<&RecordedFrame> {
+        let frame = self.recording.frames.get(self.next_frame)?;
+        self.next_frame += 1;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::application::camera::{Camera, CameraView};
+
+    const FRAME_TIME: Duration = Duration::from_millis(16);
+
+    //A frame the way the event loop runs it, without the window and the renderer
+    fn run_frame(input: &mut Input, camera: &mut Camera, measured_time: Duration) -> CameraView {
+        let delta_time = input.begin_frame(measured_time);
+        camera.camera_move(input, input.aspect_ratio(), delta_time.as_secs_f32());
+        input.refresh();
+        camera.view()
+    }
+
+    //Pans, zooms to the cursor and rotates, with empty frames in between
+    fn session() -> Vec<Vec<InputEvent>> {
+        let mut frames = vec![vec![]; 40];
+        frames[0].push(InputEvent::MouseMove { x: 400., y: 300. });
+        frames[1].push(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Pressed,
+        });
+        frames[10].push(InputEvent::Key {
+            key: VirtualKeyCode::W,
+            state: ElementState::Released,
+        });
+        frames[12].push(InputEvent::Wheel { x: 0., y: 2. });
+        frames[20].push(InputEvent::MouseButton {
+            button: MouseButton::Middle,
+            state: ElementState::Pressed,
+        });
+        frames[21].push(InputEvent::MouseMove { x: 460., y: 330. });
+        frames[22].push(InputEvent::MouseButton {
+            button: MouseButton::Middle,
+            state: ElementState::Released,
+        });
+        frames[25].push(InputEvent::Resize {
+            width: 1024,
+            height: 768,
+        });
+        frames[26].push(InputEvent::MouseMove { x: 1022., y: 700. });
+        frames
+    }
+
+    fn new_camera() -> Camera {
+        Camera::init(Vector2::new(-10., -10.), PI / 6., 0.1)
+    }
+
+    #[test]
+    fn replays_a_recorded_session() {
+        let mut input = Input::init(Vector2::new(800., 600.));
+        let mut camera = new_camera();
+        input.start_recording();
+        let views: Vec<CameraView> = session()
+            .into_iter()
+            .map(|events| {
+                for event in events {
+                    input.handle_event(event);
+                }
+                run_frame(&mut input, &mut camera, FRAME_TIME)
+            })
+            .collect();
+        assert_ne!(views.first(), views.last());
+        let recording = input.stop_recording().unwrap();
+        assert_eq!(
+            Recording::parse(&recording.to_text()),
+            Ok(recording.clone())
+        );
+
+        //The replay starts in another state, the recording brings the input back to the recorded one
+        let mut input = Input::init(Vector2::new(1920., 1080.));
+        input.handle_event(InputEvent::MouseMove { x: 5., y: 5. });
+        let mut camera = new_camera();
+        input.start_playback(recording);
+        let replayed_views: Vec<CameraView> = views
+            .iter()
+            .map(|_| run_frame(&mut input, &mut camera, Duration::from_secs(1)))
+            .collect();
+        assert_eq!(replayed_views, views);
+        assert_eq!(input.time, FRAME_TIME * views.len() as u32);
+
+        assert!(input.is_playing());
+        assert_eq!(input.begin_frame(FRAME_TIME), FRAME_TIME);
+        assert!(!input.is_playing());
+    }
+
+    #[test]
+    fn numbers_frames_that_are_not_refreshed() {
+        let mut input = Input::init(Vector2::new(800., 600.));
+        input.start_recording();
+        input.begin_frame(FRAME_TIME);
+        input.refresh();
+        //A frame the renderer failed to prepare
+        input.handle_event(InputEvent::Wheel { x: 0., y: 1. });
+        input.begin_frame(FRAME_TIME);
+        input.begin_frame(FRAME_TIME);
+        input.refresh();
+
+        let recording = input.stop_recording().unwrap();
+        assert_eq!(recording.frames.len(), 3);
+        assert_eq!(
+            Recording::parse(&recording.to_text()),
+            Ok(recording.clone())
+        );
+    }
+
+    #[test]
+    fn keeps_the_camera_still_while_text_is_typed() {
+        let mut input = Input::init(Vector2::new(800., 600.));
+        let mut camera = new_camera();
+        let view = camera.view();
+        input.focus_text("console");
+        let frames = [
+            InputEvent::MouseMove { x: 400., y: 300. },
+            InputEvent::MouseButton {
+                button: MouseButton::Middle,
+                state: ElementState::Pressed,
+            },
+            InputEvent::MouseMove { x: 460., y: 330. },
+            InputEvent::Wheel { x: 1., y: 2. },
+        ];
+        for event in frames {
+            input.handle_event(event);
+            assert_eq!(run_frame(&mut input, &mut camera, FRAME_TIME), view);
+        }
+    }
+
+    #[test]
+    fn reads_vertical_only_wheel_events() {
+        assert_eq!("wheel 1.5".parse(), Ok(InputEvent::Wheel { x: 0., y: 1.5 }));
+        assert_eq!(
+            "wheel -2 1.5".parse(),
+            Ok(InputEvent::Wheel { x: -2., y: 1.5 })
+        );
+    }
+
+    #[test]
+    fn rejects_missing_frames() {
+        let text = "frame 0 16000000\nkey W pressed\nframe 2 16000000\n";
+        assert_eq!(
+            Recording::parse(text),
+            Err("line 3: expected frame 1, found frame 2".to_string())
+        );
+    }
+}
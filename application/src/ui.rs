@@ -0,0 +1,201 @@
+use nalgebra::{Vector2, Vector4};
+use renderer::{overlay::OverlayTextureId, Renderer};
+
+use crate::{
+    bindings::Action,
+    input::{EventState, Input},
+};
+
+pub const PANEL_COLOR: Vector4<f32> = Vector4::new(0.08, 0.08, 0.1, 0.8);
+pub const BUTTON_COLOR: Vector4<f32> = Vector4::new(0.2, 0.2, 0.25, 0.9);
+pub const BUTTON_HOVER_COLOR: Vector4<f32> = Vector4::new(0.3, 0.3, 0.38, 0.9);
+pub const BUTTON_SELECTED_COLOR: Vector4<f32> = Vector4::new(0.55, 0.45, 0.15, 0.9);
+pub const BUTTON_DISABLED_COLOR: Vector4<f32> = Vector4::new(0.15, 0.15, 0.15, 0.9);
+pub const TEXT_COLOR: Vector4<f32> = Vector4::new(1., 1., 1., 1.);
+pub const DISABLED_TEXT_COLOR: Vector4<f32> = Vector4::new(0.5, 0.5, 0.5, 1.);
+
+/// What the typed characters did to a text field, see [`edit_text`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEdit {
+    Editing,
+    Submitted,
+    Cancelled,
+}
+
+/// Types the characters in the text, Backspace erases, Enter submits and Escape cancels.
+/// The characters after a submit or a cancel are dropped.
+pub fn edit_text(text: &mut String, typed: &str) -> TextEdit {
+    for character in typed.chars() {
+        match character {
+            '\r' | '\n' => return TextEdit::Submitted,
+            '\u{1b}' => return TextEdit::Cancelled,
+            //Some platforms send delete for Backspace
+            '\u{8}' | '\u{7f}' => {
+                text.pop();
+            }
+            character if !character.is_control() => text.push(character),
+            _ => {}
+        }
+    }
+    TextEdit::Editing
+}
+
+/// Screen rectangle in pixels, from the top left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+}
+
+impl Rect {
+    #[inline]
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            position: Vector2::new(x, y),
+            size: Vector2::new(width, height),
+        }
+    }
+
+    /// The rectangle between two opposite corners, in any order.
+    #[inline]
+    pub fn from_corners(corner: Vector2<f32>, opposite_corner: Vector2<f32>) -> Self {
+        let position = corner.inf(&opposite_corner);
+        Self {
+            position,
+            size: corner.sup(&opposite_corner) - position,
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        point.x >= self.position.x
+            && point.y >= self.position.y
+            && point.x < self.position.x + self.size.x
+            && point.y < self.position.y + self.size.y
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector2<f32> {
+        self.position + self.size / 2.
+    }
+}
+
+/// Immediate mode widgets, drawn by the renderer's overlay pass.
+/// Widgets are declared every frame between [`Ui::begin`] and the world picking,
+/// the ones under the mouse take the clicks, see [`Ui::wants_mouse`].
+#[derive(Default)]
+pub struct Ui {
+    mouse_position: Vector2<f32>,
+    clicked: bool,
+    held: bool,
+    //Any widget was under the mouse this frame
+    mouse_over: bool,
+}
+
+impl Ui {
+    /// Reads the mouse for the widgets of this frame, they're clicked with the select binding.
+    pub fn begin(&mut self, input: &Input) {
+        self.mouse_position = input.mouse.pos;
+        self.clicked = input.action_state(Action::Select, EventState::Pressed);
+        self.held = input.action_state(Action::Select, EventState::Down);
+        self.mouse_over = false;
+    }
+
+    /// Returns *true* if the mouse is over a widget, the world shouldn't react to it.
+    #[inline]
+    pub fn wants_mouse(&self) -> bool {
+        self.mouse_over
+    }
+
+    /// Returns *true* if the mouse is over the rectangle, and blocks it from the world.
+    #[inline]
+    fn hover(&mut self, rect: &Rect) -> bool {
+        let hovered = rect.contains(self.mouse_position);
+        self.mouse_over |= hovered;
+        hovered
+    }
+
+    pub fn panel(&mut self, renderer: &mut Renderer, rect: Rect, color: Vector4<f32>) {
+        self.hover(&rect);
+        renderer.draw_rect(rect.position, rect.size, color);
+    }
+
+    /// Labels don't block the mouse.
+    #[inline]
+    pub fn label(
+        &mut self,
+        renderer: &mut Renderer,
+        text: &str,
+        position: Vector2<f32>,
+        scale: f32,
+        color: Vector4<f32>,
+    ) {
+        renderer.draw_text(text, position, scale, color);
+    }
+
+    /// Draws the text centered in the rectangle.
+    pub fn centered_label(
+        &mut self,
+        renderer: &mut Renderer,
+        text: &str,
+        rect: Rect,
+        scale: f32,
+        color: Vector4<f32>,
+    ) {
+        let position = rect.center() - renderer.text_size(text, scale) / 2.;
+        self.label(renderer, text, position, scale, color);
+    }
+
+    /// Returns *true* the frame the button is clicked.
+    /// A selected button stays highlighted, a disabled one can't be clicked.
+    pub fn button(
+        &mut self,
+        renderer: &mut Renderer,
+        rect: Rect,
+        text: &str,
+        selected: bool,
+        enabled: bool,
+    ) -> bool {
+        let hovered = self.hover(&rect);
+
+        let color = if !enabled {
+            BUTTON_DISABLED_COLOR
+        } else if selected {
+            BUTTON_SELECTED_COLOR
+        } else if hovered {
+            BUTTON_HOVER_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        renderer.draw_rect(rect.position, rect.size, color);
+
+        let text_color = if enabled {
+            TEXT_COLOR
+        } else {
+            DISABLED_TEXT_COLOR
+        };
+        self.centered_label(renderer, text, rect, 1., text_color);
+
+        enabled && hovered && self.clicked
+    }
+
+    /// Draws the texture stretched over the rectangle.
+    /// Returns where it's held with the left button, from 0 to 1 on both axes from the top left corner.
+    pub fn image(
+        &mut self,
+        renderer: &mut Renderer,
+        rect: Rect,
+        texture: OverlayTextureId,
+    ) -> Option<Vector2<f32>> {
+        let hovered = self.hover(&rect);
+        renderer.draw_image(
+            texture,
+            rect.position,
+            rect.size,
+            Vector4::new(1., 1., 1., 1.),
+        );
+
+        (hovered && self.held)
+            .then(|| (self.mouse_position - rect.position).component_div(&rect.size))
+    }
+}
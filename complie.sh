@@ -1,2 +1,5 @@
 glslc renderer/.shaders/vertex.vert -o renderer/.compiled_shaders/vert.spv
 glslc renderer/.shaders/fragment.frag -o renderer/.compiled_shaders/frag.spv
+glslc renderer/.shaders/debug.vert -o renderer/.compiled_shaders/debug_vert.spv
+glslc renderer/.shaders/overlay.vert -o renderer/.compiled_shaders/overlay_vert.spv
+glslc renderer/.shaders/overlay.frag -o renderer/.compiled_shaders/overlay_frag.spv
@@ -3,7 +3,8 @@ use std::{
     io::{BufRead, BufReader},
 };
 
-use nalgebra::{Vector2, Vector3, Vector4};
+use nalgebra::{Matrix4, Vector2, Vector3, Vector4};
+use renderer::Renderer;
 
 use crate::{
     getters::Getters,
@@ -89,6 +90,35 @@ impl Hitbox {
         }
         Hitbox::new(vertices, triangles, quads)
     }
+
+    ///Draws the faces of the hitbox and their normals with debug lines.
+    ///The transform is the one of the object the hitbox belongs to.
+    pub fn debug_draw(&self, renderer: &mut Renderer, transform: &Matrix4<f32>, color: Vector3<f32>) {
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|v| transform.transform_point(&(*v).into()).coords)
+            .collect::<Vec<_>>();
+
+        let faces = self
+            .quads
+            .iter()
+            .map(|quad| (quad.get_vertex_positions(&vertices), quad.normal))
+            .chain(
+                self.triangles
+                    .iter()
+                    .map(|triangle| (triangle.get_vertex_positions(&vertices), triangle.normal)),
+            );
+
+        for (corners, normal) in faces {
+            renderer.debug_polygon(&corners, color);
+
+            let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+            let normal = transform.transform_vector(&normal).normalize() * 0.25;
+            renderer.debug_arrow(center, center + normal, Vector3::new(0., 0., 1.));
+        }
+    }
+
     // pub fn into_mesh(&self, renderer: &mut Renderer, color: Vector3<f32>) -> Mesh {
     //     //Collecting vertices
     //     let mut vertex_buffer = Vec::with_capacity(self.vertices.len());
@@ -110,12 +140,12 @@ impl IntersectableWithRay for GameObject<'_> {
     fn intersection_point(&self, ray: &Ray) -> Option<(Vector3<f32>, f32)> {
         //Intead of transforming the vertices with the model transform, we only tranform the ray
         //The relative positions of the vertices and the ray will be the same this way.
-        if (self.transform.get_position().x - ray.origin.x).abs() > 4.
-            || (self.transform.get_position().x - ray.origin.x).abs() > 4.
+        if (self.transform().get_position().x - ray.origin.x).abs() > 4.
+            || (self.transform().get_position().x - ray.origin.x).abs() > 4.
         {
             return None;
         }
-        let ray = self.transform.try_inverse().unwrap() * ray;
+        let ray = self.transform().try_inverse().unwrap() * ray;
         let untransformed_intersection_point = ray.hitbox_intersection_point(&self.mesh.hitbox);
 
         if let None = untransformed_intersection_point {
@@ -124,7 +154,7 @@ impl IntersectableWithRay for GameObject<'_> {
         let (mut intersection_point, t) =
             unsafe { untransformed_intersection_point.unwrap_unchecked() };
 
-        intersection_point = (*self.transform
+        intersection_point = (*self.transform()
             * Vector4::new(
                 intersection_point.x,
                 intersection_point.y,
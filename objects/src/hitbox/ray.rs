@@ -2,6 +2,8 @@ use std::ops::{Mul, MulAssign};
 
 use nalgebra::{Matrix4, Vector3, Vector4};
 
+use renderer::Renderer;
+
 use crate::mesh::primitives::Polygon;
 
 use super::Hitbox;
@@ -23,6 +25,16 @@ impl Ray {
         Self { origin, direction }
     }
 
+    #[inline]
+    ///Draws the ray as a debug arrow, with the specified length.
+    pub fn debug_draw(&self, renderer: &mut Renderer, length: f32, color: Vector3<f32>) {
+        renderer.debug_arrow(
+            self.origin,
+            self.origin + self.direction.normalize() * length,
+            color,
+        );
+    }
+
     #[inline]
     ///Returns the closest collision point of the hitbox and the ray.
     ///Returns *None* if no intersection point was found.
@@ -1,9 +1,12 @@
 use ash::vk;
 use flags::{Flag, Flags};
+use getters::Getters;
 use mesh::Mesh;
-use nalgebra::{Matrix4, Vector3};
+use nalgebra::{Matrix4, Vector3, Vector4};
 use renderer::{
     engine::aligned_array::{AlignedArray, NoneValue},
+    material::{MaterialId, MaterialPreset},
+    utils::buffer_data::ObjectUniform,
     Renderer,
 };
 use transformations::Transformations;
@@ -23,45 +26,122 @@ pub enum MeshPreset {
     MapSelection,
     Plane,
     Sphere,
+    Water,
 }
 
 pub struct GameObject<'a> {
-    pub transform: &'a mut Matrix4<f32>,
+    //Lives in the dynamic uniform buffer, changes are seen by the next frame
+    pub uniform: &'a mut ObjectUniform,
     transform_index: usize,
     mesh: &'a Mesh,
+    preset: MeshPreset,
+    pub material: MaterialId,
+    selection: SelectionState,
     flags: Flags<{ GameObjectFlag::SIZE }>,
 }
 
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionState {
+    #[default]
+    None,
+    Hovered,
+    Selected,
+}
+
 impl<'a> GameObject<'a> {
     pub fn create(
-        transform_buf: &mut AlignedArray<Matrix4<f32>>,
+        uniform_buf: &mut AlignedArray<ObjectUniform>,
         mesh: &'a Mesh,
         create_info: &GameObjectCreateInfo,
     ) -> Result<Self, ObjectCreationError> {
-        let transform_index = transform_buf
-            .push(Matrix4::identity())
+        let transform_index = uniform_buf
+            .push(ObjectUniform::default())
             .map_err(|_| ObjectCreationError::NotEnoughSpace)?;
         let transform = *Matrix4::new_translation(&create_info.transform.position)
             .scale_object(create_info.transform.scale);
-        let transform_ptr = unsafe { &mut *(transform_buf.get_data_pointer(transform_index)) };
-        *transform_ptr = transform;
+        let uniform_ptr = unsafe { &mut *(uniform_buf.get_data_pointer(transform_index)) };
+        uniform_ptr.transform = transform;
+        uniform_ptr.tint = create_info.tint;
         Ok(Self {
             flags: create_info.flags,
-            transform: transform_ptr,
+            uniform: uniform_ptr,
             transform_index,
             mesh,
+            preset: create_info.preset,
+            material: create_info.material,
+            selection: SelectionState::None,
         })
     }
 
     #[inline]
-    pub fn render(&self, renderer: &Renderer) {
-        renderer.stage_mesh(self.renderable_form())
+    pub fn render(&self, renderer: &mut Renderer) {
+        renderer.stage_mesh(
+            self.renderable_form(),
+            self.material,
+            self.uniform.transform.get_position(),
+        );
+
+        //The mask keeps the outline from covering the object itself
+        let outline = match self.selection {
+            SelectionState::None => return,
+            SelectionState::Hovered => MaterialPreset::HoverOutline,
+            SelectionState::Selected => MaterialPreset::SelectedOutline,
+        };
+        for material in [MaterialPreset::SelectionMask, outline] {
+            renderer.stage_mesh(
+                self.renderable_form(),
+                material.id(),
+                self.uniform.transform.get_position(),
+            );
+        }
+    }
+
+    #[inline]
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.uniform.transform
+    }
+
+    #[inline]
+    pub fn selection(&self) -> SelectionState {
+        self.selection
+    }
+
+    /// Sets the selection state, and the matching flags the shaders read.
+    pub fn set_selection(&mut self, selection: SelectionState) {
+        self.selection = selection;
+        self.uniform.set_flag(
+            ObjectUniform::HIGHLIGHTED,
+            selection == SelectionState::Hovered,
+        );
+        self.uniform.set_flag(
+            ObjectUniform::SELECTED,
+            selection == SelectionState::Selected,
+        );
+    }
+
+    #[inline]
+    pub fn debug_draw_hitbox(&self, renderer: &mut Renderer, color: Vector3<f32>) {
+        self.mesh
+            .hitbox
+            .debug_draw(renderer, &self.uniform.transform, color);
     }
 
     pub fn get_mesh(&self) -> &'a Mesh {
         self.mesh
     }
 
+    /// What the object was created with, to create it again on a new renderer.
+    /// The transform is left at its default, the uniform holds the current one.
+    pub fn create_info(&self) -> GameObjectCreateInfo {
+        GameObjectCreateInfo::new(
+            GameObjectTransform::default(),
+            self.preset,
+            self.material,
+            self.uniform.tint,
+            self.flags,
+        )
+    }
+
     #[inline]
     pub fn renderable_form(&self) -> (vk::Buffer, vk::Buffer, u32, usize) {
         self.get_mesh().into_tuple(self.transform_index)
@@ -117,22 +197,39 @@ impl<'a> NoneValue for GameObject<'a> {
     }
 }
 
-#[derive(Default)]
 pub struct GameObjectCreateInfo {
     pub transform: GameObjectTransform,
     pub preset: MeshPreset,
+    pub material: MaterialId,
+    pub tint: Vector4<f32>,
     pub flags: Flags<{ GameObjectFlag::SIZE }>,
 }
 
+impl Default for GameObjectCreateInfo {
+    fn default() -> Self {
+        Self {
+            transform: GameObjectTransform::default(),
+            preset: MeshPreset::default(),
+            material: MaterialPreset::Opaque.id(),
+            tint: Vector4::new(1., 1., 1., 1.),
+            flags: Flags::default(),
+        }
+    }
+}
+
 impl GameObjectCreateInfo {
     pub fn new(
         transform: GameObjectTransform,
         preset: MeshPreset,
+        material: MaterialId,
+        tint: Vector4<f32>,
         flags: Flags<{ GameObjectFlag::SIZE }>,
     ) -> Self {
         Self {
             transform,
             preset,
+            material,
+            tint,
             flags,
         }
     }
@@ -146,6 +243,16 @@ impl GameObjectCreateInfo {
         self.preset = preset;
         self
     }
+    pub fn material(mut self, material: MaterialId) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn tint(mut self, tint: Vector4<f32>) -> Self {
+        self.tint = tint;
+        self
+    }
+
     pub fn flags(mut self, active_flags: &[GameObjectFlag]) -> Self {
         let mut flags = Flags::default();
         for flag in active_flags {
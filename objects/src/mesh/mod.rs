@@ -25,23 +25,23 @@ impl Mesh {
     pub fn new(renderer: &mut Renderer, vertices: Vec<Vertex>, indicies: Vec<u32>, hitbox: Hitbox) -> Self {
         let vertex_buffer = Buffer::device_local(
             &renderer.base.device,
+            &mut renderer.base.allocator,
+            &mut renderer.data.upload_queue,
+            renderer.base.queue,
             vertices.as_ptr() as _,
             size_of::<Vertex>() as u64 * vertices.len() as u64,
-            renderer.base.physical_device_memory_properties,
             vk::BufferUsageFlags::VERTEX_BUFFER,
-            renderer.base.queue,
-            renderer.data.command_pool,
         )
         .unwrap();
 
         let index_buffer = Buffer::device_local(
             &renderer.base.device,
+            &mut renderer.base.allocator,
+            &mut renderer.data.upload_queue,
+            renderer.base.queue,
             indicies.as_ptr() as _,
             size_of::<u32>() as u64 * indicies.len() as u64,
-            renderer.base.physical_device_memory_properties,
             vk::BufferUsageFlags::INDEX_BUFFER,
-            renderer.base.queue,
-            renderer.data.command_pool,
         )
         .unwrap();
 
@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec3 color;
+// Not named view, naga's GLSL frontend can't look up a field named like its block
+layout(binding = 0) uniform _view {
+    mat4 view;
+    mat4 projection;
+} world_view;
+
+layout(location = 0) out vec4 fragColor;
+
+// The lines are already in world space, they have no model transform
+void main()
+{
+    gl_Position = world_view.projection * world_view.view * vec4(pos, 1.);
+
+    fragColor = vec4(color, 1.);
+}
\ No newline at end of file
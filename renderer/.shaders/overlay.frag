@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 fragUv;
+layout(location = 1) in vec4 fragColor;
+
+layout(binding = 0) uniform texture2D overlay_texture;
+layout(binding = 1) uniform sampler overlay_sampler;
+
+layout(location = 0) out vec4 outColor;
+
+// The font atlas is white, its alpha is the coverage of the glyphs
+void main() {
+	outColor = fragColor * texture(sampler2D(overlay_texture, overlay_sampler), fragUv);
+}
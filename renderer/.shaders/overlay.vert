@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 fragUv;
+layout(location = 1) out vec4 fragColor;
+
+// The positions are already in normalized device coordinates
+void main()
+{
+    gl_Position = vec4(pos, 0., 1.);
+    fragUv = uv;
+    fragColor = color;
+}
@@ -1,34 +1,42 @@
 #version 450
 
 layout(push_constant) uniform _push_const {
-    float wh_ratio;
-    float min_z;
-    float max_z;
     vec3 sun_direction;
     vec3 sun_color;
+    float lighting;
+    vec4 tint;
+    float outline_width;
 } push_const;
 
 layout(location = 0) in vec3 pos;
 layout(location = 1) in vec3 color;
 layout(location = 2) in vec3 normal;
+// Not named view, naga's GLSL frontend can't look up a field named like its block
 layout(binding = 0) uniform _view {
     mat4 view;
-} view;
+    mat4 projection;
+} world_view;
 
 layout(binding = 1) uniform _model {
     mat4 transform;
+    vec4 tint;
+    vec4 parameters;
+    uint flags;
 } model;
 
-layout(location = 0) out vec3 fragColor;
+const uint HIGHLIGHTED = 1u;
+const uint SELECTED = 2u;
+
+layout(location = 0) out vec4 fragColor;
 
 void main()
 {   
-    mat4 model_view = view.view * model.transform;
+    mat4 model_view = world_view.view * model.transform;
 
     // Position calculation
-    vec4 new_pos = model_view * vec4(pos, 1.);
-    float depth_z = (new_pos.z - push_const.min_z) / (push_const.max_z - push_const.min_z);
-    gl_Position = vec4(new_pos.x * push_const.wh_ratio, new_pos.y, depth_z, 1.);
+    // Outlines are the mesh inflated along its normals
+    vec4 new_pos = model_view * vec4(pos + normal * push_const.outline_width, 1.);
+    gl_Position = world_view.projection * new_pos;
 
     // Color/light calculation
     vec4 sun_direction = vec4(push_const.sun_direction, 1.0);
@@ -36,7 +44,20 @@ void main()
     // vec4 sun_color = vec4(1.0, 0.0, 0.0, 1.0);
     vec3 sun_final_color = push_const.sun_color * dot(normalize(model.transform * vec4(normal, 0.0)), sun_direction);
 
-    fragColor = color + sun_final_color;
+    vec3 highlight = vec3(0.);
+    if ((model.flags & SELECTED) != 0u) {
+        highlight = vec3(0.2);
+    } else if ((model.flags & HIGHLIGHTED) != 0u) {
+        highlight = vec3(0.1);
+    }
+    vec4 object_color = vec4(color + sun_final_color * push_const.lighting + highlight, 1.) * model.tint;
+
+    // Outlines are a plain color
+    if (push_const.outline_width > 0.) {
+        fragColor = push_const.tint;
+    } else {
+        fragColor = object_color * push_const.tint;
+    }
     // fragColor = color;
     // fragColor = vec3(model.transform * vec4(normal, 0.0));
 }
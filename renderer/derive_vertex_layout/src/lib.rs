@@ -0,0 +1,231 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Data, Fields, GenericArgument, Ident, PathArguments, Type};
+
+/// Implements `VertexLayout`, one attribute per field, with locations in declaration order.
+/// The format is inferred from the field type:
+/// `f32`, `i32`, `u32`, arrays of them with up to 4 elements, nalgebra's `Vector2/3/4`,
+/// and `[u8; 4]` which is read as a normalized color.
+/// Other types need a `#[vertex(format = R16G16_SFLOAT)]` attribute with the `vk::Format` name.
+/// The struct has to be `#[repr(C)]`, Rust's default layout can reorder the fields.
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn vertex_layout_derive(input: TokenStream) -> TokenStream {
+    let parsed_input = syn::parse(input).unwrap();
+    match impl_vertex_layout(&parsed_input) {
+        Ok(gen) => gen.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn impl_vertex_layout(p_input: &syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &p_input.ident;
+    let fields = match &p_input.data {
+        Data::Struct(d_struct) => match &d_struct.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "VertexLayout needs a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(name, "Struct only derivative macro")),
+    };
+    if !is_repr_c(p_input)? {
+        return Err(syn::Error::new_spanned(
+            name,
+            "VertexLayout needs #[repr(C)], the shaders read the fields in declaration order",
+        ));
+    }
+
+    let mut attributes = Vec::with_capacity(fields.len());
+    for (location, field) in fields.iter().enumerate() {
+        let field_name = field.ident.as_ref().unwrap();
+        let location = location as u32;
+
+        let format = match format_override(field)? {
+            Some(format) => format,
+            None => infer_format(&field.ty).ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &field.ty,
+                    "can't infer the vertex format of this type, add #[vertex(format = ...)]",
+                )
+            })?,
+        };
+
+        attributes.push(quote! {
+            ash::vk::VertexInputAttributeDescription {
+                location: #location,
+                binding,
+                format: ash::vk::Format::#format,
+                offset: std::mem::offset_of!(#name, #field_name) as u32,
+            }
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = p_input.generics.split_for_impl();
+
+    let gen = quote! {
+        impl #impl_generics VertexLayout for #name #ty_generics #where_clause {
+            fn binding_description(binding: u32) -> ash::vk::VertexInputBindingDescription {
+                ash::vk::VertexInputBindingDescription {
+                    binding,
+                    stride: std::mem::size_of::<Self>() as u32,
+                    input_rate: ash::vk::VertexInputRate::VERTEX,
+                }
+            }
+
+            fn attribute_descriptions(binding: u32) -> Vec<ash::vk::VertexInputAttributeDescription> {
+                vec![#(#attributes),*]
+            }
+        }
+    };
+    Ok(gen)
+}
+
+fn is_repr_c(p_input: &syn::DeriveInput) -> syn::Result<bool> {
+    let mut repr_c = false;
+    for attr in p_input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+            }
+            //Skips the arguments of the other representations, like align(16)
+            if meta.input.peek(syn::token::Paren) {
+                meta.input.parse::<TokenStream2>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(repr_c)
+}
+
+fn format_override(field: &syn::Field) -> syn::Result<Option<Ident>> {
+    let mut format = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("vertex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("format") {
+                format = Some(meta.value()?.parse::<Ident>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported vertex attribute, expected `format`"))
+            }
+        })?;
+    }
+    Ok(format)
+}
+
+fn infer_format(ty: &Type) -> Option<Ident> {
+    let (scalar, count) = match ty {
+        Type::Array(array) => {
+            let count = match &array.len {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }) => len.base10_parse::<usize>().ok()?,
+                _ => return None,
+            };
+            (type_name(&array.elem)?, count)
+        }
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let count = match segment.ident.to_string().as_str() {
+                "Vector2" => 2,
+                "Vector3" => 3,
+                "Vector4" => 4,
+                scalar => return scalar_format(scalar, 1),
+            };
+            let scalar = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => match args.args.first()? {
+                    GenericArgument::Type(ty) => type_name(ty)?,
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            (scalar, count)
+        }
+        _ => return None,
+    };
+
+    scalar_format(&scalar, count)
+}
+
+fn scalar_format(scalar: &str, count: usize) -> Option<Ident> {
+    if !(1..=4).contains(&count) {
+        return None;
+    }
+
+    let format = match scalar {
+        "f32" | "i32" | "u32" => {
+            let suffix = match scalar {
+                "f32" => "SFLOAT",
+                "i32" => "SINT",
+                _ => "UINT",
+            };
+            let components = ["R32", "G32", "B32", "A32"][..count].concat();
+            format!("{}_{}", components, suffix)
+        }
+        //Bytes are almost always colors, so they are normalized.
+        "u8" if count == 4 => String::from("R8G8B8A8_UNORM"),
+        _ => return None,
+    };
+
+    Some(Ident::new(&format, Span::call_site()))
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => Some(path.path.segments.last()?.ident.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn needs_repr_c() {
+        let default_layout: syn::DeriveInput = parse_quote! {
+            struct Vertex {
+                pos: [f32; 3],
+            }
+        };
+        assert!(impl_vertex_layout(&default_layout).is_err());
+
+        let repr_c: syn::DeriveInput = parse_quote! {
+            #[repr(C, align(16))]
+            struct Vertex {
+                pos: [f32; 3],
+            }
+        };
+        assert!(impl_vertex_layout(&repr_c).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        let input: syn::DeriveInput = parse_quote! {
+            #[repr(C)]
+            struct Vertex {
+                pos: [f64; 3],
+            }
+        };
+        assert!(impl_vertex_layout(&input).is_err());
+    }
+
+    #[test]
+    fn infers_formats() {
+        let format = |ty: Type| infer_format(&ty).map(|format| format.to_string());
+        assert_eq!(format(parse_quote!(f32)).unwrap(), "R32_SFLOAT");
+        assert_eq!(format(parse_quote!([u32; 2])).unwrap(), "R32G32_UINT");
+        assert_eq!(
+            format(parse_quote!(nalgebra::Vector3<i32>)).unwrap(),
+            "R32G32B32_SINT"
+        );
+        assert_eq!(format(parse_quote!([u8; 4])).unwrap(), "R8G8B8A8_UNORM");
+        assert_eq!(format(parse_quote!([f32; 5])), None);
+        assert_eq!(format(parse_quote!([u8; 3])), None);
+    }
+}
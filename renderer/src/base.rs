@@ -1,7 +1,9 @@
 use ash::extensions::{ext, khr};
 use ash::vk;
 
-use crate::resources::image::Image;
+use crate::error::RendererError;
+use crate::resources::{allocator::MemoryAllocator, image::Image};
+use crate::{renderer_error, vk_error};
 use crate::setup::{
     create_debug_call_back, create_instance, create_logical_device, create_surface,
     create_swapchain, get_depth_format, get_physical_device, get_present_mode, get_queue_family,
@@ -25,6 +27,8 @@ pub struct RenderBase {
     pub depth_format: vk::Format,
     pub surface_format: vk::SurfaceFormatKHR,
     pub present_mode: vk::PresentModeKHR,
+    //In order of preference, the first supported one is picked when the swapchain is built
+    pub present_mode_preferences: Vec<vk::PresentModeKHR>,
     pub queue_family: u32,
     pub device: ash::Device,
     pub queue: vk::Queue,
@@ -33,12 +37,18 @@ pub struct RenderBase {
     pub swapchain: vk::SwapchainKHR,
     pub swapchain_images: Vec<vk::Image>,
     pub swapchain_image_views: Vec<vk::ImageView>,
+    pub allocator: MemoryAllocator,
 }
 
 impl RenderBase {
-    pub fn new(window: &winit::window::Window) -> Result<Self, String> {
-        let entry = unsafe { ash::Entry::load().map_err(|msg| format!("{}", msg))? };
-        let instance_extensions = get_required_instance_extensions(window).unwrap();
+    pub fn new(
+        window: &winit::window::Window,
+        present_mode_preferences: Vec<vk::PresentModeKHR>,
+    ) -> Result<Self, RendererError> {
+        let entry = unsafe {
+            ash::Entry::load().map_err(|err| renderer_error!("load_vulkan", "{}", err))?
+        };
+        let instance_extensions = get_required_instance_extensions(window)?;
         let device_extensions = vec![ash::extensions::khr::Swapchain::name()];
 
         let instance = create_instance(&entry, &instance_extensions);
@@ -56,7 +66,12 @@ impl RenderBase {
         let physical_device_properties =
             unsafe { instance.get_physical_device_properties(physical_device) };
         let surface_format = get_surface_format(physical_device, &surface_loader, surface)?;
-        let present_mode = get_present_mode(physical_device, &surface_loader, surface)?;
+        let present_mode = get_present_mode(
+            physical_device,
+            &surface_loader,
+            surface,
+            &present_mode_preferences,
+        )?;
 
         let queue_family = get_queue_family(&instance, physical_device, &surface_loader, surface)?;
 
@@ -84,6 +99,7 @@ impl RenderBase {
 
         let memory_props =
             unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let allocator = MemoryAllocator::new(memory_props, physical_device_properties);
 
         Ok(RenderBase {
             entry,
@@ -101,6 +117,7 @@ impl RenderBase {
             physical_device_memory_properties: memory_props,
             surface_format,
             present_mode,
+            present_mode_preferences,
             queue_family,
             queue,
             surface_capabilities: resize_data.surface_capabilities,
@@ -111,11 +128,19 @@ impl RenderBase {
             swapchain_loader,
             device,
             depth_format,
+            allocator,
         })
     }
 
     #[inline]
-    pub fn resize(&mut self, window: &winit::window::Window) -> Result<(), String> {
+    pub fn resize(&mut self, window: &winit::window::Window) -> Result<(), RendererError> {
+        self.present_mode = get_present_mode(
+            self.physical_device,
+            &self.surface_loader,
+            self.surface,
+            &self.present_mode_preferences,
+        )?;
+
         let resize_data = resize_internal(
             window,
             &self.device,
@@ -138,7 +163,8 @@ impl RenderBase {
         Ok(())
     }
 
-    pub fn clean_up(&self) {
+    pub fn clean_up(&mut self) {
+        self.allocator.clean_up(&self.device);
         unsafe {
             self.swapchain_loader
                 .destroy_swapchain(self.swapchain, None);
@@ -173,9 +199,11 @@ fn resize_internal(
     surface_format: &vk::SurfaceFormatKHR,
     present_mode: vk::PresentModeKHR,
     old_swapchain_image_views: &Vec<vk::ImageView>,
-) -> Result<ResizeResult, String> {
+) -> Result<ResizeResult, RendererError> {
     unsafe {
-        device.device_wait_idle().unwrap();
+        device
+            .device_wait_idle()
+            .map_err(vk_error!("device_wait_idle"))?;
     }
 
     let surface_capabilities = get_surface_capabilities(surface_loader, physical_device, surface)?;
@@ -210,9 +238,8 @@ fn resize_internal(
                 surface_format.format,
                 vk::ImageAspectFlags::COLOR,
             )
-            .unwrap()
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     Ok(ResizeResult {
         surface_capabilities,
@@ -1,21 +1,26 @@
-use nalgebra::Matrix4;
-
 use crate::{
-    create_shader,
+    debug_draw::DebugLines,
+    include_shader,
+    error::RendererError,
+    material::{Material, MaterialPreset},
+    overlay::Overlay,
     resources::{
         self,
+        allocator::MemoryAllocator,
         buffer::{Buffer, DynamicUniformBuffer, UniformBuffer},
         desriptors::{
             create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets,
             update_descriptor_sets,
         },
         image::Image,
+        upload::UploadQueue,
     },
     setup,
     utils::{
-        buffer_data::{BufferObject, PushConst, WorldView},
+        buffer_data::{BufferObject, ObjectUniform, PushConst, WorldView},
         MAX_WORLD_OBJECTS,
     },
+    vk_error,
 };
 use ash::vk;
 
@@ -24,7 +29,11 @@ use super::{base::RenderBase, utils::MAX_FRAME_DRAWS};
 pub struct RenderData {
     pub pipeline_layout: vk::PipelineLayout,
     pub render_pass: vk::RenderPass,
-    pub pipelines: [vk::Pipeline; 2],
+    //Kept alive, so materials can be created after the startup
+    pub shader_modules: (vk::ShaderModule, vk::ShaderModule),
+    pub materials: Vec<Material>,
+    pub debug_lines: DebugLines,
+    pub overlay: Overlay,
     pub viewport: vk::Viewport,
     pub scissor: vk::Rect2D,
     pub framebuffers: Vec<vk::Framebuffer>,
@@ -45,55 +54,65 @@ pub struct RenderData {
     //Buffers
     pub uniform_buffer: UniformBuffer,
     pub dynamic_uniform_buffer: DynamicUniformBuffer,
+    pub upload_queue: UploadQueue,
+}
+
+pub fn descriptor_set_layout_bindings() -> [vk::DescriptorSetLayoutBinding; 2] {
+    [
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            ..Default::default()
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            ..Default::default()
+        },
+    ]
 }
 
 impl RenderData {
-    pub fn new(base: &mut RenderBase) -> Result<Self, String> {
-        let vertex_shader_module = create_shader!("../.compiled_shaders/vert.spv", base.device);
-        let fragment_shader_module = create_shader!("../.compiled_shaders/frag.spv", base.device);
+    pub fn new(base: &mut RenderBase) -> Result<Self, RendererError> {
+        let layout_bindings = descriptor_set_layout_bindings();
+
+        #[cfg(not(feature = "shader-hot-reload"))]
+        let (vertex_code, fragment_code, debug_vertex_code) = (
+            include_shader!("../.compiled_shaders/vert.spv"),
+            include_shader!("../.compiled_shaders/frag.spv"),
+            include_shader!("../.compiled_shaders/debug_vert.spv"),
+        );
+        //Falling back to the precompiled shaders, so a broken source doesn't stop the startup.
+        #[cfg(feature = "shader-hot-reload")]
+        let (vertex_code, fragment_code, debug_vertex_code) =
+            crate::shaders::compile_shaders().unwrap_or_else(|err| {
+                crate::msg!(warn, err.to_string());
+                (
+                    include_shader!("../.compiled_shaders/vert.spv"),
+                    include_shader!("../.compiled_shaders/frag.spv"),
+                    include_shader!("../.compiled_shaders/debug_vert.spv"),
+                )
+            });
 
-        let layout_bindings = [
-            vk::DescriptorSetLayoutBinding {
-                binding: 0,
-                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
-                descriptor_count: 1,
-                stage_flags: vk::ShaderStageFlags::VERTEX,
-                ..Default::default()
-            },
-            vk::DescriptorSetLayoutBinding {
-                binding: 1,
-                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
-                descriptor_count: 1,
-                stage_flags: vk::ShaderStageFlags::VERTEX,
-                ..Default::default()
-            },
-        ];
+        setup::validate_shaders(&vertex_code, &fragment_code, &layout_bindings)?;
+        setup::validate_shaders(&debug_vertex_code, &fragment_code, &layout_bindings)?;
+        let vertex_shader_module = setup::create_shader_module(&base.device, &vertex_code)?;
+        let fragment_shader_module = setup::create_shader_module(&base.device, &fragment_code)?;
 
         let descriptor_set_layout = create_descriptor_set_layout(&base.device, &layout_bindings)?;
-        let pipeline_layout = setup::create_pipeline_layout(&base.device, descriptor_set_layout)?;
+        let pipeline_layout = setup::create_pipeline_layout(
+            &base.device,
+            descriptor_set_layout,
+            &[setup::push_constant_range()],
+        )?;
 
         let render_pass =
             setup::create_render_pass(&base.device, base.surface_format.format, base.depth_format)?;
 
-        let pipelines = [
-            setup::create_pipelines(
-                &base.device,
-                vertex_shader_module,
-                fragment_shader_module,
-                pipeline_layout,
-                render_pass,
-                vk::PolygonMode::FILL,
-            )?,
-            setup::create_pipelines(
-                &base.device,
-                vertex_shader_module,
-                fragment_shader_module,
-                pipeline_layout,
-                render_pass,
-                vk::PolygonMode::LINE,
-            )?,
-        ];
-
         let viewport = vk::Viewport {
             x: 0.0,
             y: 0.0,
@@ -113,13 +132,13 @@ impl RenderData {
 
         let depth_img = Image::new(
             &base.device,
+            &mut base.allocator,
             base.surface_extent.into(),
             base.depth_format,
             vk::ImageTiling::OPTIMAL,
             vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            vk::ImageAspectFlags::DEPTH,
+            setup::depth_aspect_mask(base.depth_format),
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
-            base.physical_device_memory_properties,
         )?;
 
         let framebuffers = resources::create_framebuffers(
@@ -148,7 +167,7 @@ impl RenderData {
                     .build();
                 base.device
                     .allocate_command_buffers(&cb_info)
-                    .map_err(|err| format!("{}", err))?
+                    .map_err(vk_error!("allocate_command_buffers"))?
             }
         };
 
@@ -163,24 +182,26 @@ impl RenderData {
             },
         ];
 
-        let descriptor_pool = create_descriptor_pool(&base.device, &pool_sizes)?;
+        let descriptor_pool = create_descriptor_pool(&base.device, &pool_sizes, MAX_FRAME_DRAWS as u32)?;
 
         let world_view = WorldView::new();
         let uniform_buffer = Buffer::uniform_buffer::<WorldView>(
             &base.device,
+            &mut base.allocator,
             world_view.as_void_ptr(),
-            base.physical_device_memory_properties,
             0,
         )?;
 
-        let dynamic_uniform_buffer = Buffer::dynamic_uniform_buffer::<Matrix4<f32>>(
+        let dynamic_uniform_buffer = Buffer::dynamic_uniform_buffer::<ObjectUniform>(
             &base.device,
-            base.physical_device_memory_properties,
+            &mut base.allocator,
             base.physical_device_properties,
             MAX_WORLD_OBJECTS,
             1,
         )?;
 
+        let upload_queue = UploadQueue::new(&base.device, &mut base.allocator, base.queue_family)?;
+
         let descriptor_sets =
             create_descriptor_sets(&base.device, descriptor_pool, descriptor_set_layout)?;
 
@@ -216,17 +237,40 @@ impl RenderData {
         update_descriptor_sets(&base.device, &descriptor_sets, &mut write_desc_sets);
         uniform_buffer.update(&base.device, world_view.as_void_ptr(), &descriptor_sets);
 
-        unsafe {
-            base.device
-                .destroy_shader_module(vertex_shader_module, None);
-            base.device
-                .destroy_shader_module(fragment_shader_module, None);
-        }
+        let shader_modules = (vertex_shader_module, fragment_shader_module);
+        let materials = MaterialPreset::ALL
+            .iter()
+            .map(|preset| {
+                Material::new(
+                    &base.device,
+                    shader_modules,
+                    pipeline_layout,
+                    render_pass,
+                    descriptor_sets.clone(),
+                    preset.create_info(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let debug_lines = DebugLines::new(
+            &base.device,
+            &mut base.allocator,
+            &debug_vertex_code,
+            fragment_shader_module,
+            pipeline_layout,
+            render_pass,
+            descriptor_sets.clone(),
+        )?;
+
+        let overlay = Overlay::new(&base.device, &mut base.allocator, render_pass)?;
 
         Ok(Self {
             pipeline_layout,
             render_pass,
-            pipelines,
+            shader_modules,
+            materials,
+            debug_lines,
+            overlay,
             viewport,
             scissor,
             framebuffers,
@@ -245,29 +289,30 @@ impl RenderData {
             //Buffers
             uniform_buffer,
             dynamic_uniform_buffer,
+            upload_queue,
             push_const: PushConst::default(),
         })
     }
 
     #[inline]
-    pub fn resize(&mut self, base: &RenderBase) -> Result<(), String> {
+    pub fn resize(&mut self, base: &mut RenderBase) -> Result<(), RendererError> {
         unsafe {
             for &framebuffer in &self.framebuffers {
                 base.device.destroy_framebuffer(framebuffer, None);
             }
         }
 
-        self.depth_img.free(&base.device);
+        self.depth_img.free(&base.device, &mut base.allocator);
 
         self.depth_img = Image::new(
             &base.device,
+            &mut base.allocator,
             base.surface_extent.into(),
             base.depth_format,
             vk::ImageTiling::OPTIMAL,
             vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            vk::ImageAspectFlags::DEPTH,
+            setup::depth_aspect_mask(base.depth_format),
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
-            base.physical_device_memory_properties,
         )?;
 
         self.framebuffers = resources::create_framebuffers(
@@ -281,11 +326,14 @@ impl RenderData {
         Ok(())
     }
 
-    pub fn clean_up(&self, device: &ash::Device) {
+    pub fn clean_up(&mut self, device: &ash::Device, allocator: &mut MemoryAllocator) {
+        self.upload_queue.clean_up(device, allocator);
+        self.debug_lines.clean_up(device, allocator);
+        self.overlay.clean_up(device, allocator);
         unsafe {
-            self.dynamic_uniform_buffer.free(device);
-            self.uniform_buffer.free(device);
-            self.depth_img.free(device);
+            self.dynamic_uniform_buffer.free(device, allocator);
+            self.uniform_buffer.free(device, allocator);
+            self.depth_img.free(device, allocator);
 
             device.destroy_descriptor_pool(self.descriptor_pool, None);
             device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
@@ -294,9 +342,11 @@ impl RenderData {
 
             device.destroy_render_pass(self.render_pass, None);
 
-            self.pipelines.iter().for_each(|p| {
-                device.destroy_pipeline(*p, None);
-            });
+            self.materials
+                .iter()
+                .for_each(|material| material.destroy(device));
+            device.destroy_shader_module(self.shader_modules.0, None);
+            device.destroy_shader_module(self.shader_modules.1, None);
 
             for &framebuffer in &self.framebuffers {
                 device.destroy_framebuffer(framebuffer, None);
@@ -0,0 +1,242 @@
+use std::{f32::consts::TAU, mem::size_of, ptr::copy_nonoverlapping};
+
+use ash::vk;
+use nalgebra::{Matrix4, Vector3};
+
+use crate::{
+    error::RendererError,
+    material::{Material, MaterialCreateInfo, RenderQueue},
+    resources::{allocator::MemoryAllocator, buffer::Buffer},
+    setup,
+    utils::{vertex::Vertex, MAX_FRAME_DRAWS},
+    Renderer,
+};
+
+/// Lines past this count are dropped for the frame.
+pub const MAX_DEBUG_VERTICES: usize = 32768;
+
+const SPHERE_SEGMENTS: usize = 24;
+
+/// Immediate mode lines in world space. They are collected during the frame,
+/// recorded after every other draw and cleared.
+pub struct DebugLines {
+    pub enabled: bool,
+    //Every two vertices make a line
+    vertices: Vec<Vertex>,
+    //One per frame in flight, host visible and mapped
+    vertex_buffers: Vec<Buffer>,
+    pub(crate) material: Material,
+}
+
+impl DebugLines {
+    pub fn new(
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        vertex_code: &[u32],
+        fragment_shader_module: vk::ShaderModule,
+        pipeline_layout: vk::PipelineLayout,
+        render_pass: vk::RenderPass,
+        descriptor_sets: Vec<vk::DescriptorSet>,
+    ) -> Result<Self, RendererError> {
+        let vertex_shader_module = setup::create_shader_module(device, vertex_code)?;
+        let material = Material::new(
+            device,
+            (vertex_shader_module, fragment_shader_module),
+            pipeline_layout,
+            render_pass,
+            descriptor_sets,
+            MaterialCreateInfo::default()
+                .render_queue(RenderQueue::Overlay)
+                .topology(vk::PrimitiveTopology::LINE_LIST),
+        );
+        unsafe { device.destroy_shader_module(vertex_shader_module, None) };
+        let material = material?;
+
+        let vertex_buffers = (0..MAX_FRAME_DRAWS)
+            .map(|_| {
+                Buffer::new(
+                    device,
+                    allocator,
+                    (MAX_DEBUG_VERTICES * size_of::<Vertex>()) as u64,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            enabled: false,
+            vertices: Vec::with_capacity(MAX_DEBUG_VERTICES),
+            vertex_buffers,
+            material,
+        })
+    }
+
+    #[inline]
+    pub fn line(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>) {
+        if !self.enabled || self.vertices.len() + 2 > MAX_DEBUG_VERTICES {
+            return;
+        }
+
+        //The normal is not read by the debug shader
+        self.vertices.push(Vertex::new(start, color, Vector3::y()));
+        self.vertices.push(Vertex::new(end, color, Vector3::y()));
+    }
+
+    /// Records the lines of the frame into the command buffer, then clears them.
+    pub fn record(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pipeline_layout: vk::PipelineLayout,
+        frame_index: usize,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = &self.vertex_buffers[frame_index];
+        unsafe {
+            //The fence of this frame was waited for, the GPU doesn't read this buffer anymore
+            copy_nonoverlapping(
+                self.vertices.as_ptr(),
+                vertex_buffer.allocation.mapped_ptr as *mut Vertex,
+                self.vertices.len(),
+            );
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.material.pipeline,
+            );
+
+            //Only the view is read, the dynamic offset doesn't matter
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                0,
+                &[self.material.descriptor_sets[frame_index]],
+                &[0],
+            );
+
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.buf], &[0]);
+            device.cmd_draw(command_buffer, self.vertices.len() as u32, 1, 0, 0);
+        }
+
+        self.vertices.clear();
+    }
+
+    pub fn clean_up(&mut self, device: &ash::Device, allocator: &mut MemoryAllocator) {
+        self.material.destroy(device);
+        self.vertex_buffers
+            .iter()
+            .for_each(|buffer| buffer.free(device, allocator));
+    }
+}
+
+impl Renderer {
+    #[inline]
+    pub fn toggle_debug_draw(&mut self) {
+        self.data.debug_lines.enabled = !self.data.debug_lines.enabled;
+    }
+
+    #[inline]
+    pub fn debug_draw_enabled(&self) -> bool {
+        self.data.debug_lines.enabled
+    }
+
+    #[inline]
+    pub fn debug_line(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>) {
+        self.data.debug_lines.line(start, end, color);
+    }
+
+    /// Draws the outline of a polygon, the last corner is connected to the first.
+    pub fn debug_polygon(&mut self, corners: &[Vector3<f32>], color: Vector3<f32>) {
+        for (i, &corner) in corners.iter().enumerate() {
+            self.debug_line(corner, corners[(i + 1) % corners.len()], color);
+        }
+    }
+
+    /// Draws an axis aligned box.
+    pub fn debug_box(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: Vector3<f32>) {
+        self.debug_transformed_box(&Matrix4::identity(), min, max, color);
+    }
+
+    /// Draws a box given in the local space of the transform.
+    pub fn debug_transformed_box(
+        &mut self,
+        transform: &Matrix4<f32>,
+        min: Vector3<f32>,
+        max: Vector3<f32>,
+        color: Vector3<f32>,
+    ) {
+        //Corner i takes its x from bit 0, its y from bit 1 and its z from bit 2
+        let corners = (0..8)
+            .map(|i: usize| {
+                let corner = Vector3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                );
+                transform.transform_point(&corner.into()).coords
+            })
+            .collect::<Vec<_>>();
+
+        //The edges connect the corners that differ in a single bit
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.debug_line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+    }
+
+    /// Draws a circle around each axis.
+    pub fn debug_sphere(&mut self, center: Vector3<f32>, radius: f32, color: Vector3<f32>) {
+        let axes = [
+            (Vector3::x(), Vector3::y()),
+            (Vector3::y(), Vector3::z()),
+            (Vector3::z(), Vector3::x()),
+        ];
+
+        for (a, b) in axes {
+            let point = |segment: usize| {
+                let angle = segment as f32 / SPHERE_SEGMENTS as f32 * TAU;
+                center + (a * angle.cos() + b * angle.sin()) * radius
+            };
+
+            for segment in 0..SPHERE_SEGMENTS {
+                self.debug_line(point(segment), point(segment + 1), color);
+            }
+        }
+    }
+
+    /// Draws a line with a head at the end, the head is a quarter of the length.
+    pub fn debug_arrow(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>) {
+        self.debug_line(start, end, color);
+
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = direction / length;
+
+        //Any vector that isn't parallel to the direction works for the sides of the head
+        let helper = if direction.y.abs() < 0.9 {
+            Vector3::y()
+        } else {
+            Vector3::x()
+        };
+        let side = direction.cross(&helper).normalize();
+        let up = direction.cross(&side);
+
+        let head_length = length * 0.25;
+        let head_base = end - direction * head_length;
+        for offset in [side, -side, up, -up] {
+            self.debug_line(end, head_base + offset * head_length * 0.5, color);
+        }
+    }
+}
@@ -1,21 +1,20 @@
-use std::mem::size_of_val;
+use std::mem::{size_of, size_of_val};
 
 use ash::vk;
 use nalgebra::Vector3;
 
-use crate::Renderer;
+use crate::{
+    error::RendererError,
+    material::{DrawCommand, MaterialId, MaterialParameters, RenderQueue},
+    utils::buffer_data::{BufferObject, PushConst},
+    vk_error, Renderer,
+};
 
 impl Renderer {
     #[inline]
     pub fn start_record(&self) {
         let current_command_buffer = self.data.command_buffers[self.current_frame_index];
         unsafe {
-            self.base.device.cmd_bind_pipeline(
-                current_command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.data.pipelines[self.current_pipeline_index],
-            );
-
             self.base
                 .device
                 .cmd_set_viewport(current_command_buffer, 0, &[self.data.viewport]);
@@ -37,8 +36,114 @@ impl Renderer {
         }
     }
 
+    /// Records the staged draws queue by queue.
+    /// Opaque and overlay draws are grouped by material, so each pipeline is bound once,
+    /// transparent draws go back to front, so they blend over what is behind them.
+    pub fn record_draws(&mut self) {
+        let mut draw_commands = std::mem::take(&mut self.draw_commands);
+        let view = self.data.world_view.view;
+        let render_queue =
+            |draw: &DrawCommand| self.data.materials[draw.material].create_info.render_queue;
+        //The view space z grows with the distance from the camera
+        let view_depth = |draw: &DrawCommand| (view * draw.position.push(1.)).z;
+
+        draw_commands.sort_by(|a, b| {
+            render_queue(a)
+                .cmp(&render_queue(b))
+                .then_with(|| match render_queue(a) {
+                    RenderQueue::Transparent => view_depth(b).total_cmp(&view_depth(a)),
+                    _ => a.material.cmp(&b.material),
+                })
+        });
+
+        let mut bound_material = None;
+        for draw in &draw_commands {
+            if bound_material != Some(draw.material) {
+                self.bind_material(draw.material);
+                bound_material = Some(draw.material);
+            }
+            self.record_draw(draw);
+        }
+
+        draw_commands.clear();
+        self.draw_commands = draw_commands;
+
+        //Debug lines go over everything else
+        self.data.debug_lines.record(
+            &self.base.device,
+            self.data.command_buffers[self.current_frame_index],
+            self.data.pipeline_layout,
+            self.current_frame_index,
+        );
+
+        //The overlay is screen space, it's drawn last
+        self.data.overlay.record(
+            &self.base.device,
+            self.data.command_buffers[self.current_frame_index],
+            self.current_frame_index,
+        );
+    }
+
+    #[inline]
+    fn bind_material(&self, material: MaterialId) {
+        let current_command_buffer = self.data.command_buffers[self.current_frame_index];
+        let material = &self.data.materials[material];
+        unsafe {
+            self.base.device.cmd_bind_pipeline(
+                current_command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                material.pipeline,
+            );
+
+            self.base.device.cmd_push_constants(
+                current_command_buffer,
+                self.data.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                std::mem::offset_of!(PushConst, lighting) as u32,
+                std::slice::from_raw_parts(
+                    material.create_info.parameters.as_void_ptr() as *const u8,
+                    size_of::<MaterialParameters>(),
+                ),
+            );
+        }
+    }
+
     #[inline]
-    pub fn end_record(&self) {
+    fn record_draw(&self, draw: &DrawCommand) {
+        let current_command_buffer = self.data.command_buffers[self.current_frame_index];
+        let material = &self.data.materials[draw.material];
+        unsafe {
+            self.base.device.cmd_bind_descriptor_sets(
+                current_command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.data.pipeline_layout,
+                0,
+                &[material.descriptor_sets[self.current_frame_index]],
+                &[draw.transform_index as u32 * self.data.dynamic_uniform_buffer.alignment as u32],
+            );
+
+            self.base.device.cmd_bind_vertex_buffers(
+                current_command_buffer,
+                0,
+                &[draw.vertex_buffer],
+                &[0],
+            );
+
+            self.base.device.cmd_bind_index_buffer(
+                current_command_buffer,
+                draw.index_buffer,
+                0,
+                vk::IndexType::UINT32,
+            );
+
+            self.base
+                .device
+                .cmd_draw_indexed(current_command_buffer, draw.index_count, 1, 0, 0, 0);
+        }
+    }
+
+    #[inline]
+    pub fn end_record(&self) -> Result<(), RendererError> {
         let current_command_buffer = self.data.command_buffers[self.current_frame_index];
         unsafe {
             self.base.device.cmd_end_render_pass(current_command_buffer);
@@ -46,7 +151,7 @@ impl Renderer {
             self.base
                 .device
                 .end_command_buffer(current_command_buffer)
-                .unwrap();
+                .map_err(vk_error!("end_command_buffer"))
         }
     }
 
@@ -92,7 +197,7 @@ impl Renderer {
     }
 
     #[inline]
-    pub fn begin_command_buffer(&self) {
+    pub fn begin_command_buffer(&self) -> Result<(), RendererError> {
         let begin_info = vk::CommandBufferBeginInfo::builder()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
             .build();
@@ -104,34 +209,34 @@ impl Renderer {
                     self.data.command_buffers[self.current_frame_index],
                     &begin_info,
                 )
-                .map_err(|_| String::from("failed to begin command buffer"))
-                .unwrap();
+                .map_err(vk_error!("begin_command_buffer"))
         }
     }
 
     #[inline]
-    pub fn get_img_index(&self) -> Result<Option<u32>, String> {
-        let (index, is_suboptimal) = match unsafe {
-            self.base.swapchain_loader.acquire_next_image(
-                self.base.swapchain,
-                u64::MAX,
-                self.data.img_available_semaphores[self.current_frame_index],
-                vk::Fence::null(),
-            )
-        } {
-            Ok((index, is_suboptimal)) => (index, is_suboptimal),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(None),
-            Err(message) => return Err(format!("{}", message)),
+    /// Returns [`RendererError::SwapchainOutOfDate`] if the swapchain has to be rebuilt.
+    pub fn get_img_index(&self) -> Result<u32, RendererError> {
+        let (index, is_suboptimal) = unsafe {
+            self.base
+                .swapchain_loader
+                .acquire_next_image(
+                    self.base.swapchain,
+                    u64::MAX,
+                    self.data.img_available_semaphores[self.current_frame_index],
+                    vk::Fence::null(),
+                )
+                .map_err(vk_error!("acquire_next_image"))?
         };
 
         if is_suboptimal {
-            return Ok(None);
+            return Err(RendererError::SwapchainOutOfDate);
         }
 
-        Ok(Some(index))
+        Ok(index)
     }
     #[inline]
-    pub fn present(&self) -> Result<bool, String> {
+    /// Returns [`RendererError::SwapchainOutOfDate`] if the swapchain has to be rebuilt.
+    pub fn present(&self) -> Result<(), RendererError> {
         let semaphores = [self.data.render_finished_semaphores[self.current_frame_index]];
         let swapchains = [self.base.swapchain];
         let indices = [self.image_index as u32];
@@ -141,21 +246,18 @@ impl Renderer {
             .image_indices(&indices)
             .build();
 
-        unsafe {
-            if let Err(err) = self
-                .base
+        let is_suboptimal = unsafe {
+            self.base
                 .swapchain_loader
                 .queue_present(self.base.queue, &present_info)
-            {
-                if err == vk::Result::SUBOPTIMAL_KHR || err == vk::Result::ERROR_OUT_OF_DATE_KHR {
-                    return Ok(false);
-                } else {
-                    return Err(String::from("failed to present"));
-                }
-            }
+                .map_err(vk_error!("queue_present"))?
+        };
+
+        if is_suboptimal {
+            return Err(RendererError::SwapchainOutOfDate);
         }
 
-        Ok(true)
+        Ok(())
     }
 
     #[inline]
@@ -182,7 +284,7 @@ impl Renderer {
     }
 
     #[inline]
-    pub fn submit(&self) -> Result<(), String> {
+    pub fn submit(&self) -> Result<(), RendererError> {
         let fence = self.data.fences[self.current_frame_index as usize];
 
         let wait_semaphores = [self.data.img_available_semaphores[self.current_frame_index]];
@@ -200,32 +302,34 @@ impl Renderer {
             self.base
                 .device
                 .queue_submit(self.base.queue, &[submit_info], fence)
-                .map_err(|_| String::from("failed to submit graphics command buffer"))?
+                .map_err(vk_error!(
+                    "queue_submit",
+                    "frame {}",
+                    self.current_frame_index
+                ))?
         }
 
         Ok(())
     }
     #[inline]
-    pub fn wait_resource_available(&self) -> Result<(), String> {
+    pub fn wait_resource_available(&self) -> Result<(), RendererError> {
         let fence = self.data.fences[self.current_frame_index as usize];
 
         unsafe {
             self.base
                 .device
                 .wait_for_fences(&[fence], true, u64::MAX)
-                .map_err(|_| {
-                    format!(
-                        "failed to wait for resource fence {}",
-                        self.current_frame_index
-                    )
-                })?;
-
-            self.base.device.reset_fences(&[fence]).map_err(|_| {
-                format!(
-                    "failed to reset resource fence {}",
+                .map_err(vk_error!(
+                    "wait_for_fences",
+                    "frame {}",
                     self.current_frame_index
-                )
-            })?;
+                ))?;
+
+            self.base.device.reset_fences(&[fence]).map_err(vk_error!(
+                "reset_fences",
+                "frame {}",
+                self.current_frame_index
+            ))?;
         }
 
         Ok(())
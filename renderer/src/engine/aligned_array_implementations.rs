@@ -1,5 +1,7 @@
 use nalgebra::Matrix4;
 
+use crate::utils::buffer_data::ObjectUniform;
+
 use super::aligned_array::NoneValue;
 
 impl NoneValue for Matrix4<f32> {
@@ -11,3 +13,13 @@ impl NoneValue for Matrix4<f32> {
         *self = Matrix4::zeros();
     }
 }
+
+impl NoneValue for ObjectUniform {
+    fn is_none(&self) -> bool {
+        self.transform.is_none()
+    }
+
+    fn set_to_none(&mut self) {
+        self.transform.set_to_none();
+    }
+}
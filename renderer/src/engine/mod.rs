@@ -10,7 +10,7 @@ use num::{traits::AsPrimitive, Num};
 #[macro_export]
 macro_rules! offset_of {
     ($type:ty, $field:ident) => {{
-        unsafe { &(*(0 as usize as *const $type)).$field as *const _ as usize }
+        std::mem::offset_of!($type, $field)
     }};
 }
 
@@ -0,0 +1,126 @@
+use std::fmt::{self, Display};
+
+use ash::vk;
+
+#[derive(Debug, Clone)]
+pub enum RendererError {
+    /// A Vulkan call failed with an error that can't be recovered from.
+    Vulkan {
+        result: vk::Result,
+        operation: &'static str,
+        context: String,
+    },
+    /// The logical device was lost, every object created from it has to be recreated.
+    DeviceLost {
+        operation: &'static str,
+        context: String,
+    },
+    /// The swapchain doesn't match the surface anymore, rebuilding it and skipping the frame fixes it.
+    SwapchainOutOfDate,
+    /// Failures that don't come from a Vulkan call, like a missing memory type or an unsupported device.
+    Other {
+        operation: &'static str,
+        context: String,
+    },
+}
+
+impl RendererError {
+    /// Sorts the result into the matching variant, so device loss and out of date swapchains
+    /// can be handled separately from the rest.
+    pub fn vulkan(result: vk::Result, operation: &'static str, context: String) -> Self {
+        match result {
+            vk::Result::ERROR_DEVICE_LOST => Self::DeviceLost { operation, context },
+            vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::SUBOPTIMAL_KHR => {
+                Self::SwapchainOutOfDate
+            }
+            _ => Self::Vulkan {
+                result,
+                operation,
+                context,
+            },
+        }
+    }
+
+    #[inline]
+    pub fn other(operation: &'static str, context: String) -> Self {
+        Self::Other { operation, context }
+    }
+
+    /// Returns the Vulkan result the error was created from, if there is one.
+    pub fn result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Vulkan { result, .. } => Some(*result),
+            Self::DeviceLost { .. } => Some(vk::Result::ERROR_DEVICE_LOST),
+            Self::SwapchainOutOfDate => Some(vk::Result::ERROR_OUT_OF_DATE_KHR),
+            Self::Other { .. } => None,
+        }
+    }
+
+    /// Returns *true* if the renderer can go on, after rebuilding the swapchain when it's out of date
+    /// or recreating the renderer when the device was lost.
+    #[inline]
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, Self::SwapchainOutOfDate | Self::DeviceLost { .. })
+    }
+}
+
+impl Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan {
+                result,
+                operation,
+                context,
+            } => write!(f, "{operation} failed with {result}: {context}"),
+            Self::DeviceLost { operation, context } => {
+                write!(f, "device lost during {operation}: {context}")
+            }
+            Self::SwapchainOutOfDate => write!(f, "swapchain is out of date"),
+            Self::Other { operation, context } => write!(f, "{operation} failed: {context}"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Vulkan { result, .. } => Some(result),
+            _ => None,
+        }
+    }
+}
+
+/// Creates a closure turning a `vk::Result` into a [`RendererError`](crate::error::RendererError),
+/// meant for `map_err`. The context defaults to the location of the call.
+#[macro_export]
+macro_rules! vk_error {
+    ($operation:expr) => {{
+        |result| {
+            $crate::error::RendererError::vulkan(
+                result,
+                $operation,
+                format!("at {}:{}", file!(), line!()),
+            )
+        }
+    }};
+    ($operation:expr, $($context:tt)+) => {{
+        |result| {
+            $crate::error::RendererError::vulkan(
+                result,
+                $operation,
+                format!("{}\tat {}:{}", format!($($context)+), file!(), line!()),
+            )
+        }
+    }};
+}
+
+/// Creates a [`RendererError::Other`](crate::error::RendererError) for failures that don't come from Vulkan.
+#[macro_export]
+macro_rules! renderer_error {
+    ($operation:expr, $($context:tt)+) => {{
+        $crate::error::RendererError::other(
+            $operation,
+            format!("{}\tat {}:{}", format!($($context)+), file!(), line!()),
+        )
+    }};
+}
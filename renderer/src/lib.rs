@@ -1,44 +1,93 @@
 mod base;
 mod data;
+pub mod debug_draw;
 mod draw_setup;
 pub mod engine;
+pub mod error;
+pub mod material;
+pub mod overlay;
+pub mod reflection;
 pub mod resources;
 mod setup;
+#[cfg(feature = "shader-hot-reload")]
+pub mod shaders;
 pub mod utils;
 
 use ash::vk;
-use resources::buffer::Buffer;
+use material::{DrawCommand, MaterialId, StencilMode};
+use nalgebra::Vector3;
+use resources::{buffer::Buffer, upload::UploadId};
 use utils::{buffer_data::BufferObject, MAX_WORLD_OBJECTS};
 use winit::window::Window;
 
-use crate::{base::RenderBase, data::RenderData, utils::MAX_FRAME_DRAWS};
+use crate::{
+    base::RenderBase, data::RenderData, error::RendererError, utils::MAX_FRAME_DRAWS,
+};
+
+/// How the swapchain hands frames to the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresentMode {
+    //Vsync, always supported
+    Fifo,
+    //Vsync without blocking, the latest frame replaces the queued one
+    Mailbox,
+    //No vsync, may tear
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 3] = [
+        PresentMode::Fifo,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+}
+
+impl From<PresentMode> for vk::PresentModeKHR {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => vk::PresentModeKHR::FIFO,
+            PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+            PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        }
+    }
+}
 
 pub struct Renderer {
     pub data: RenderData,
     pub base: RenderBase,
 
     meshes_buffers: Vec<[Buffer; 2]>,
+    draw_commands: Vec<DrawCommand>,
 
     pub current_frame_index: usize,
-    pub current_pipeline_index: usize,
+    //Draws everything with this material instead of its own, for debugging
+    pub material_override: Option<MaterialId>,
     pub rebuild_swapchain: bool,
 
     pub image_index: usize,
+
+    #[cfg(feature = "shader-hot-reload")]
+    shader_watcher: shaders::ShaderWatcher,
 }
 
 impl Renderer {
-    pub fn new(window: &Window) -> Result<Self, String> {
-        let mut base = RenderBase::new(window)?;
+    /// The present mode is the first supported one of the preferences, FIFO if none are.
+    pub fn new(window: &Window, present_modes: &[PresentMode]) -> Result<Self, RendererError> {
+        let mut base = RenderBase::new(window, present_modes.iter().map(|&m| m.into()).collect())?;
         let data = RenderData::new(&mut base)?;
 
         Ok(Self {
             base,
             data,
             current_frame_index: 0,
-            current_pipeline_index: 0,
+            material_override: None,
             rebuild_swapchain: true,
             image_index: 0,
             meshes_buffers: Vec::with_capacity(MAX_WORLD_OBJECTS),
+            draw_commands: Vec::with_capacity(MAX_WORLD_OBJECTS),
+            #[cfg(feature = "shader-hot-reload")]
+            shader_watcher: shaders::ShaderWatcher::default(),
         })
     }
 
@@ -46,47 +95,42 @@ impl Renderer {
         self.meshes_buffers.push(mesh)
     }
 
+    /// Queues the mesh for this frame, the draws are sorted by material and recorded by [`Renderer::flush`].
+    /// The position is the world position of the object, transparent draws are sorted by it.
     #[inline]
-    pub fn stage_mesh(&self, mesh: (vk::Buffer, vk::Buffer, u32, usize)) {
-        let current_command_buffer = self.data.command_buffers[self.current_frame_index];
-        unsafe {
-            self.base.device.cmd_bind_descriptor_sets(
-                current_command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.data.pipeline_layout,
-                0,
-                &[self.data.descriptor_sets[self.current_frame_index]],
-                &[mesh.3 as u32 * self.data.dynamic_uniform_buffer.alignment as u32],
-            );
-
-            self.base
-                .device
-                .cmd_bind_vertex_buffers(current_command_buffer, 0, &[mesh.0], &[0]);
-
-            self.base.device.cmd_bind_index_buffer(
-                current_command_buffer,
-                mesh.1,
-                0,
-                vk::IndexType::UINT32,
-            );
-
-            self.base
-                .device
-                .cmd_draw_indexed(current_command_buffer, mesh.2, 1, 0, 0, 0);
+    pub fn stage_mesh(
+        &mut self,
+        mesh: (vk::Buffer, vk::Buffer, u32, usize),
+        material: MaterialId,
+        position: Vector3<f32>,
+    ) {
+        let material = self.material_override.unwrap_or(material);
+        //The outlines would cover the whole object without a stencil
+        if self.data.materials[material].create_info.stencil_mode != StencilMode::Disabled
+            && !setup::has_stencil(self.base.depth_format)
+        {
+            return;
         }
+
+        self.draw_commands.push(DrawCommand {
+            material,
+            vertex_buffer: mesh.0,
+            index_buffer: mesh.1,
+            index_count: mesh.2,
+            transform_index: mesh.3,
+            position,
+        });
     }
 
     #[inline]
-    pub fn prepare_renderer(&mut self) -> Result<(), String> {
-        self.image_index = match self.get_img_index()? {
-            Some(index) => index as usize,
-            None => {
-                self.rebuild_swapchain = true;
-                return Ok(());
-            }
-        };
+    pub fn prepare_renderer(&mut self) -> Result<(), RendererError> {
+        #[cfg(feature = "shader-hot-reload")]
+        self.reload_shaders()?;
+
+        self.image_index = self.get_img_index()? as usize;
 
         self.wait_resource_available()?;
+        self.data.upload_queue.poll(&self.base.device)?;
 
         let current_command_buffer = self.data.command_buffers[self.current_frame_index];
         self.data.uniform_buffer.update(
@@ -102,43 +146,88 @@ impl Renderer {
                     current_command_buffer,
                     vk::CommandBufferResetFlags::default(),
                 )
-                .unwrap();
+                .map_err(vk_error!("reset_command_buffer"))?;
         }
 
-        self.begin_command_buffer();
+        self.begin_command_buffer()?;
         self.begin_render_pass();
         self.start_record();
         Ok(())
     }
 
     #[inline]
-    pub fn flush(&mut self) -> Result<(), String> {
-        self.end_record();
+    pub fn flush(&mut self) -> Result<(), RendererError> {
+        self.record_draws();
+        self.end_record()?;
+        self.data.overlay.upload_textures(
+            &self.base.device,
+            &mut self.data.upload_queue,
+            self.base.queue,
+            self.current_frame_index,
+        )?;
+        //Uploads go first, so meshes loaded during the frame can be drawn by it.
+        self.flush_uploads()?;
         self.submit()?;
 
-        if !self.present()? {
-            self.rebuild_swapchain = true;
-            return Ok(());
-        }
+        self.present()?;
 
         self.current_frame_index = (self.current_frame_index + 1) % MAX_FRAME_DRAWS;
 
         Ok(())
     }
 
+    /// Submits the uploads recorded since the last frame without waiting for them.
+    #[inline]
+    pub fn flush_uploads(&mut self) -> Result<Option<UploadId>, RendererError> {
+        self.data
+            .upload_queue
+            .submit(&self.base.device, self.base.queue)
+    }
+
     #[inline]
-    pub fn resize(&mut self, window: &Window) -> Result<(), String> {
+    pub fn upload_complete(&self, id: UploadId) -> bool {
+        self.data.upload_queue.is_complete(id)
+    }
+
+    /// Applied when the swapchain is rebuilt, which is requested here.
+    pub fn set_present_modes(&mut self, present_modes: &[PresentMode]) {
+        self.base.present_mode_preferences = present_modes.iter().map(|&m| m.into()).collect();
+        self.rebuild_swapchain = true;
+    }
+
+    /// The modes the surface can present with, FIFO is always one of them.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, RendererError> {
+        let modes = setup::get_supported_present_modes(
+            self.base.physical_device,
+            &self.base.surface_loader,
+            self.base.surface,
+        )?;
+        Ok(PresentMode::ALL
+            .into_iter()
+            .filter(|&mode| modes.contains(&mode.into()))
+            .collect())
+    }
+
+    /// The mode the swapchain was built with.
+    pub fn present_mode(&self) -> PresentMode {
+        match self.base.present_mode {
+            vk::PresentModeKHR::MAILBOX => PresentMode::Mailbox,
+            vk::PresentModeKHR::IMMEDIATE => PresentMode::Immediate,
+            _ => PresentMode::Fifo,
+        }
+    }
+
+    #[inline]
+    pub fn resize(&mut self, window: &Window) -> Result<(), RendererError> {
         unsafe {
             let _ = self.base.device.device_wait_idle();
         }
 
+        self.base.resize(window)?;
+        self.data.resize(&mut self.base)?;
+        //The viewport and scissor follow the new extent
         self.set_scissor();
         self.set_viewport();
-        self.base.resize(window)?;
-        self.data.resize(&self.base)?;
-
-        self.data.push_const.wh_ratio =
-            self.base.surface_extent.height as f32 / self.base.surface_extent.width as f32;
 
         Ok(())
     }
@@ -153,17 +242,17 @@ impl Renderer {
 impl Drop for Renderer {
     fn drop(&mut self) {
         unsafe {
-            self.base.device.device_wait_idle().unwrap();
+            let _ = self.base.device.device_wait_idle();
         }
 
         if self.meshes_buffers.len() != 0 {
             self.meshes_buffers.iter().for_each(|b| {
-                b[0].free(&self.base.device);
-                b[1].free(&self.base.device)
+                b[0].free(&self.base.device, &mut self.base.allocator);
+                b[1].free(&self.base.device, &mut self.base.allocator)
             });
         }
 
-        self.data.clean_up(&self.base.device);
+        self.data.clean_up(&self.base.device, &mut self.base.allocator);
         self.base.clean_up();
     }
 }
@@ -0,0 +1,330 @@
+use ash::vk;
+use nalgebra::{Vector3, Vector4};
+
+use crate::{
+    error::RendererError,
+    setup,
+    utils::{buffer_data::BufferObject, vertex::Vertex},
+    Renderer,
+};
+
+/// Index of a material in [`RenderData::materials`](crate::data::RenderData).
+pub type MaterialId = usize;
+
+/// Materials created by the renderer at startup, the value is their [`MaterialId`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaterialPreset {
+    #[default]
+    Opaque = 0,
+    Transparent,
+    Water,
+    Unlit,
+    Debug,
+    SelectionMask,
+    HoverOutline,
+    SelectedOutline,
+}
+
+impl MaterialPreset {
+    pub const ALL: [MaterialPreset; 8] = [
+        MaterialPreset::Opaque,
+        MaterialPreset::Transparent,
+        MaterialPreset::Water,
+        MaterialPreset::Unlit,
+        MaterialPreset::Debug,
+        MaterialPreset::SelectionMask,
+        MaterialPreset::HoverOutline,
+        MaterialPreset::SelectedOutline,
+    ];
+
+    #[inline]
+    pub fn id(self) -> MaterialId {
+        self as MaterialId
+    }
+
+    pub fn create_info(self) -> MaterialCreateInfo {
+        match self {
+            MaterialPreset::Opaque => MaterialCreateInfo::default(),
+            MaterialPreset::Transparent => MaterialCreateInfo::default()
+                .render_queue(RenderQueue::Transparent)
+                .blend_mode(BlendMode::Alpha)
+                .depth_write(false)
+                .parameters(MaterialParameters::default().tint(Vector4::new(1., 1., 1., 0.5))),
+            MaterialPreset::Water => MaterialCreateInfo::default()
+                .render_queue(RenderQueue::Transparent)
+                .blend_mode(BlendMode::Alpha)
+                .depth_write(false)
+                .parameters(
+                    MaterialParameters::default()
+                        .tint(Vector4::new(0.3, 0.6, 0.9, 0.7))
+                        .lighting(0.5),
+                ),
+            MaterialPreset::Unlit => {
+                MaterialCreateInfo::default().parameters(MaterialParameters::default().lighting(0.))
+            }
+            MaterialPreset::Debug => MaterialCreateInfo::default()
+                .render_queue(RenderQueue::Overlay)
+                .polygon_mode(vk::PolygonMode::LINE)
+                .parameters(MaterialParameters::default().lighting(0.)),
+            //The mask has to be recorded before the outlines, it has the lower id
+            MaterialPreset::SelectionMask => MaterialCreateInfo::default()
+                .render_queue(RenderQueue::Highlight)
+                .stencil_mode(StencilMode::Mask)
+                //The mesh is already in the depth buffer, it would fail a LESS test against itself
+                .depth_test(false)
+                .depth_write(false),
+            MaterialPreset::HoverOutline => MaterialPreset::outline(Vector4::new(1., 1., 1., 1.)),
+            MaterialPreset::SelectedOutline => {
+                MaterialPreset::outline(Vector4::new(1., 0.8, 0.2, 1.))
+            }
+        }
+    }
+
+    fn outline(color: Vector4<f32>) -> MaterialCreateInfo {
+        MaterialCreateInfo::default()
+            .render_queue(RenderQueue::Highlight)
+            .stencil_mode(StencilMode::Outline)
+            //Objects in front of the outlined one still cover its outline
+            .depth_write(false)
+            .parameters(
+                MaterialParameters::default()
+                    .lighting(0.)
+                    //The shader draws outlines with the tint only
+                    .tint(color)
+                    .outline_width(0.05),
+            )
+    }
+}
+
+/// Draws are recorded queue by queue, in this order.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderQueue {
+    #[default]
+    Opaque,
+    Transparent,
+    //Selection outlines, drawn over the scene
+    Highlight,
+    Overlay,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    Alpha,
+    Additive,
+}
+
+/// Selection outlines are made with the stencil, needs a depth format with a stencil component.
+/// Without one, draws with a stencil mode are skipped.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StencilMode {
+    #[default]
+    Disabled,
+    //Writes 1 to the stencil without writing colors
+    Mask,
+    //Draws only where the stencil isn't 1
+    Outline,
+}
+
+/// Values the shaders read per material, pushed right after the frame's push constants.
+/// The layout has to match the fields from `lighting` to `outline_width` of [`PushConst`](crate::utils::buffer_data::PushConst).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct MaterialParameters {
+    //How much the sun affects the color, 0 is unlit
+    pub lighting: f32,
+    pub tint: Vector4<f32>,
+    //Moves the vertices along their normals, in model space
+    pub outline_width: f32,
+}
+
+impl Default for MaterialParameters {
+    fn default() -> Self {
+        Self {
+            lighting: 1.,
+            tint: Vector4::new(1., 1., 1., 1.),
+            outline_width: 0.,
+        }
+    }
+}
+
+impl MaterialParameters {
+    #[inline]
+    pub fn lighting(mut self, lighting: f32) -> Self {
+        self.lighting = lighting;
+        self
+    }
+
+    #[inline]
+    pub fn tint(mut self, tint: Vector4<f32>) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    #[inline]
+    pub fn outline_width(mut self, outline_width: f32) -> Self {
+        self.outline_width = outline_width;
+        self
+    }
+}
+
+impl BufferObject for MaterialParameters {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MaterialCreateInfo {
+    pub render_queue: RenderQueue,
+    pub topology: vk::PrimitiveTopology,
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub blend_mode: BlendMode,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub stencil_mode: StencilMode,
+    pub parameters: MaterialParameters,
+}
+
+impl Default for MaterialCreateInfo {
+    fn default() -> Self {
+        Self {
+            render_queue: RenderQueue::Opaque,
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::NONE,
+            blend_mode: BlendMode::Opaque,
+            depth_test: true,
+            depth_write: true,
+            stencil_mode: StencilMode::Disabled,
+            parameters: MaterialParameters::default(),
+        }
+    }
+}
+
+impl MaterialCreateInfo {
+    pub fn render_queue(mut self, render_queue: RenderQueue) -> Self {
+        self.render_queue = render_queue;
+        self
+    }
+
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub fn stencil_mode(mut self, stencil_mode: StencilMode) -> Self {
+        self.stencil_mode = stencil_mode;
+        self
+    }
+
+    pub fn parameters(mut self, parameters: MaterialParameters) -> Self {
+        self.parameters = parameters;
+        self
+    }
+}
+
+/// A pipeline, the descriptor sets bound with it and the parameters pushed with it.
+pub struct Material {
+    pub pipeline: vk::Pipeline,
+    //One per frame in flight
+    pub descriptor_sets: Vec<vk::DescriptorSet>,
+    pub create_info: MaterialCreateInfo,
+}
+
+impl Material {
+    pub fn new(
+        device: &ash::Device,
+        shader_modules: (vk::ShaderModule, vk::ShaderModule),
+        pipeline_layout: vk::PipelineLayout,
+        render_pass: vk::RenderPass,
+        descriptor_sets: Vec<vk::DescriptorSet>,
+        create_info: MaterialCreateInfo,
+    ) -> Result<Self, RendererError> {
+        let pipeline = setup::create_pipelines::<Vertex>(
+            device,
+            shader_modules.0,
+            shader_modules.1,
+            pipeline_layout,
+            render_pass,
+            &create_info,
+        )?;
+
+        Ok(Self {
+            pipeline,
+            descriptor_sets,
+            create_info,
+        })
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline(self.pipeline, None) }
+    }
+}
+
+/// A mesh waiting to be recorded at the end of the frame.
+#[derive(Clone, Copy, Debug)]
+pub struct DrawCommand {
+    pub material: MaterialId,
+    pub vertex_buffer: vk::Buffer,
+    pub index_buffer: vk::Buffer,
+    pub index_count: u32,
+    pub transform_index: usize,
+    //World position, used to sort the transparent draws
+    pub position: Vector3<f32>,
+}
+
+impl Renderer {
+    /// Creates a material with the renderer's shaders, bound to the default descriptor sets.
+    pub fn create_material(
+        &mut self,
+        create_info: MaterialCreateInfo,
+    ) -> Result<MaterialId, RendererError> {
+        let material = Material::new(
+            &self.base.device,
+            self.data.shader_modules,
+            self.data.pipeline_layout,
+            self.data.render_pass,
+            self.data.descriptor_sets.clone(),
+            create_info,
+        )?;
+
+        self.data.materials.push(material);
+        Ok(self.data.materials.len() - 1)
+    }
+
+    #[inline]
+    pub fn material(&self, id: MaterialId) -> &Material {
+        &self.data.materials[id]
+    }
+
+    /// Changes the parameters pushed with the material, the pipeline is kept.
+    #[inline]
+    pub fn set_material_parameters(&mut self, id: MaterialId, parameters: MaterialParameters) {
+        self.data.materials[id].create_info.parameters = parameters;
+    }
+}
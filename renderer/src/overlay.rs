@@ -0,0 +1,989 @@
+use std::{collections::HashMap, mem::size_of, ptr::copy_nonoverlapping};
+
+use ash::vk;
+use nalgebra::{Vector2, Vector4};
+
+use crate::{
+    error::RendererError,
+    include_shader,
+    material::{BlendMode, MaterialCreateInfo, RenderQueue},
+    reflection::PipelineInterface,
+    renderer_error,
+    resources::{
+        allocator::MemoryAllocator,
+        buffer::Buffer,
+        desriptors::{
+            create_descriptor_pool, create_descriptor_set_layout, create_descriptor_sets,
+            update_descriptor_sets,
+        },
+        image::Image,
+        texture::TextureData,
+        upload::UploadQueue,
+    },
+    setup,
+    utils::{vertex::VertexLayout, MAX_FRAME_DRAWS},
+    vk_error, Renderer,
+};
+
+/// Quads past this count are dropped for the frame.
+pub const MAX_OVERLAY_QUADS: usize = 4096;
+pub const MAX_OVERLAY_TEXTURES: usize = 16;
+
+//Every glyph, rectangle, line and image is a quad made of two triangles
+const VERTICES_PER_QUAD: usize = 6;
+
+//Printable ASCII, the rest is drawn with the fallback
+const FIRST_CHARACTER: u8 = b' ';
+const LAST_CHARACTER: u8 = b'~';
+const FALLBACK_CHARACTER: char = '?';
+
+const ATLAS_WIDTH: usize = 512;
+//Keeps the linear filter from bleeding the neighbouring glyphs in
+const ATLAS_PADDING: usize = 1;
+//Fully covered block in the corner of the atlas, rectangles and lines sample its center
+const SOLID_BLOCK_SIZE: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default, VertexLayout)]
+#[repr(C)]
+pub struct OverlayVertex {
+    //Normalized device coordinates
+    pub pos: Vector2<f32>,
+    pub uv: Vector2<f32>,
+    pub color: Vector4<f32>,
+}
+
+/// Where a glyph is in the atlas and how it's placed, in pixels at the rasterised size.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    pub uv_min: Vector2<f32>,
+    pub uv_max: Vector2<f32>,
+    pub size: Vector2<f32>,
+    //From the pen position on the baseline to the top left corner
+    pub offset: Vector2<f32>,
+    pub advance: f32,
+}
+
+/// The glyphs of a font rasterised once into a single texture.
+pub struct FontAtlas {
+    pub glyphs: HashMap<char, Glyph>,
+    //Size the glyphs were rasterised at, a scale of 1 draws them at this size
+    pub size: f32,
+    pub ascent: f32,
+    pub line_height: f32,
+    //Texture coordinates with full coverage
+    pub solid_uv: Vector2<f32>,
+    pub extent: vk::Extent2D,
+    //White, the alpha is the coverage
+    pub pixels: Vec<u8>,
+}
+
+impl FontAtlas {
+    /// Rasterises the printable ASCII characters of a TTF or OTF font.
+    pub fn new(font_data: &[u8], size: f32) -> Result<Self, RendererError> {
+        let font = fontdue::Font::from_bytes(font_data, fontdue::FontSettings::default())
+            .map_err(|err| renderer_error!("FontAtlas::new", "{}", err))?;
+        let line_metrics = font.horizontal_line_metrics(size).ok_or_else(|| {
+            renderer_error!("FontAtlas::new", "the font has no horizontal line metrics")
+        })?;
+
+        let rasterised = (FIRST_CHARACTER..=LAST_CHARACTER)
+            .map(|character| {
+                let character = character as char;
+                let (metrics, coverage) = font.rasterize(character, size);
+                (character, metrics, coverage)
+            })
+            .collect::<Vec<_>>();
+
+        //Glyphs are packed in rows, a row is as high as its highest glyph
+        let mut placements = Vec::with_capacity(rasterised.len());
+        let (mut x, mut y, mut row_height) = (
+            SOLID_BLOCK_SIZE + ATLAS_PADDING * 2,
+            ATLAS_PADDING,
+            SOLID_BLOCK_SIZE,
+        );
+        for (_, metrics, _) in &rasterised {
+            if x + metrics.width + ATLAS_PADDING > ATLAS_WIDTH {
+                x = ATLAS_PADDING;
+                y += row_height + ATLAS_PADDING;
+                row_height = 0;
+            }
+            placements.push((x, y));
+            x += metrics.width + ATLAS_PADDING;
+            row_height = row_height.max(metrics.height);
+        }
+        let height = (y + row_height + ATLAS_PADDING).next_power_of_two();
+
+        let mut pixels = [255, 255, 255, 0].repeat(ATLAS_WIDTH * height);
+        for row in 0..SOLID_BLOCK_SIZE {
+            for column in 0..SOLID_BLOCK_SIZE {
+                let pixel = (ATLAS_PADDING + row) * ATLAS_WIDTH + ATLAS_PADDING + column;
+                pixels[pixel * 4 + 3] = 255;
+            }
+        }
+        let solid_center = ATLAS_PADDING as f32 + SOLID_BLOCK_SIZE as f32 / 2.;
+
+        let mut glyphs = HashMap::with_capacity(rasterised.len());
+        for ((character, metrics, coverage), (x, y)) in rasterised.iter().zip(placements) {
+            for row in 0..metrics.height {
+                for column in 0..metrics.width {
+                    let pixel = (y + row) * ATLAS_WIDTH + x + column;
+                    pixels[pixel * 4 + 3] = coverage[row * metrics.width + column];
+                }
+            }
+
+            let atlas_size = Vector2::new(ATLAS_WIDTH as f32, height as f32);
+            let position = Vector2::new(x as f32, y as f32);
+            let glyph_size = Vector2::new(metrics.width as f32, metrics.height as f32);
+            glyphs.insert(
+                *character,
+                Glyph {
+                    uv_min: position.component_div(&atlas_size),
+                    uv_max: (position + glyph_size).component_div(&atlas_size),
+                    size: glyph_size,
+                    //The rasteriser's y goes up from the baseline, the screen's goes down
+                    offset: Vector2::new(
+                        metrics.xmin as f32,
+                        -(metrics.ymin as f32 + metrics.height as f32),
+                    ),
+                    advance: metrics.advance_width,
+                },
+            );
+        }
+
+        Ok(Self {
+            glyphs,
+            size,
+            ascent: line_metrics.ascent,
+            line_height: line_metrics.new_line_size,
+            solid_uv: Vector2::new(
+                solid_center / ATLAS_WIDTH as f32,
+                solid_center / height as f32,
+            ),
+            extent: vk::Extent2D {
+                width: ATLAS_WIDTH as u32,
+                height: height as u32,
+            },
+            pixels,
+        })
+    }
+
+    #[inline]
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&FALLBACK_CHARACTER))
+    }
+
+    /// Size of the text in pixels, lines are split on `\n`.
+    pub fn measure(&self, text: &str, scale: f32) -> Vector2<f32> {
+        let width = text
+            .split('\n')
+            .map(|line| {
+                line.chars()
+                    .filter_map(|character| self.glyph(character))
+                    .map(|glyph| glyph.advance)
+                    .sum::<f32>()
+            })
+            .fold(0., f32::max);
+        let lines = text.split('\n').count();
+
+        Vector2::new(width, lines as f32 * self.line_height) * scale
+    }
+}
+
+/// Index of a texture created with [`Renderer::create_overlay_texture`].
+pub type OverlayTextureId = usize;
+
+/// RGBA texture with one image per frame in flight,
+/// so an update never writes to an image that a previous frame still reads.
+struct OverlayTexture {
+    images: Vec<Image>,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    extent: vk::Extent2D,
+    pixels: Vec<u8>,
+    //Images that don't have the last pixels yet, by frame
+    outdated: [bool; MAX_FRAME_DRAWS],
+}
+
+/// Screen space text, rectangles, lines and images, drawn over everything else.
+/// They are collected during the frame, recorded after the debug lines and cleared.
+pub struct Overlay {
+    //Text, rectangles and lines need a font, they sample its atlas
+    font: Option<(FontAtlas, OverlayTextureId)>,
+    textures: Vec<OverlayTexture>,
+    sampler: vk::Sampler,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    vertices: Vec<OverlayVertex>,
+    //Consecutive quads with the same texture are drawn together, with their vertex count
+    batches: Vec<(OverlayTextureId, u32)>,
+    //One per frame in flight, host visible and mapped
+    vertex_buffers: Vec<Buffer>,
+}
+
+impl Overlay {
+    pub fn new(
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        render_pass: vk::RenderPass,
+    ) -> Result<Self, RendererError> {
+        let layout_bindings = descriptor_set_layout_bindings();
+
+        #[cfg(not(feature = "shader-hot-reload"))]
+        let (vertex_code, fragment_code) = (
+            include_shader!("../.compiled_shaders/overlay_vert.spv"),
+            include_shader!("../.compiled_shaders/overlay_frag.spv"),
+        );
+        //Falling back to the precompiled shaders, so a broken source doesn't stop the startup.
+        #[cfg(feature = "shader-hot-reload")]
+        let (vertex_code, fragment_code) = crate::shaders::compile_overlay_shaders()
+            .unwrap_or_else(|err| {
+                crate::msg!(warn, err.to_string());
+                (
+                    include_shader!("../.compiled_shaders/overlay_vert.spv"),
+                    include_shader!("../.compiled_shaders/overlay_frag.spv"),
+                )
+            });
+        validate_shaders(&vertex_code, &fragment_code)?;
+
+        let sampler = Image::create_filtered_sampler(
+            device,
+            vk::Filter::LINEAR,
+            vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        )?;
+
+        let descriptor_set_layout = create_descriptor_set_layout(device, &layout_bindings)?;
+        let max_sets = (MAX_OVERLAY_TEXTURES * MAX_FRAME_DRAWS) as u32;
+        let pool_sizes = layout_bindings.map(|binding| vk::DescriptorPoolSize {
+            ty: binding.descriptor_type,
+            descriptor_count: max_sets,
+        });
+        let descriptor_pool = create_descriptor_pool(device, &pool_sizes, max_sets)?;
+
+        let pipeline_layout = setup::create_pipeline_layout(device, descriptor_set_layout, &[])?;
+
+        let pipeline = create_pipeline(
+            device,
+            pipeline_layout,
+            render_pass,
+            &vertex_code,
+            &fragment_code,
+        )?;
+
+        let vertex_buffers = (0..MAX_FRAME_DRAWS)
+            .map(|_| {
+                Buffer::new(
+                    device,
+                    allocator,
+                    (MAX_OVERLAY_QUADS * VERTICES_PER_QUAD * size_of::<OverlayVertex>()) as u64,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            font: None,
+            textures: Vec::with_capacity(MAX_OVERLAY_TEXTURES),
+            sampler,
+            descriptor_pool,
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            vertices: Vec::with_capacity(MAX_OVERLAY_QUADS * VERTICES_PER_QUAD),
+            batches: Vec::new(),
+            vertex_buffers,
+        })
+    }
+
+    /// Replaces the pipeline with one built from the shader codes.
+    /// If the shaders don't match the overlay the error is logged and the current pipeline is kept.
+    /// Returns *true* if the pipeline was replaced.
+    #[cfg(feature = "shader-hot-reload")]
+    pub fn reload_pipeline(
+        &mut self,
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+        vertex_code: &[u32],
+        fragment_code: &[u32],
+    ) -> Result<bool, RendererError> {
+        let pipeline = match validate_shaders(vertex_code, fragment_code).and_then(|_| {
+            create_pipeline(
+                device,
+                self.pipeline_layout,
+                render_pass,
+                vertex_code,
+                fragment_code,
+            )
+        }) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                crate::msg!(warn, err.to_string());
+                return Ok(false);
+            }
+        };
+
+        //The old pipeline can still be used by the frames in flight.
+        unsafe {
+            device
+                .device_wait_idle()
+                .map_err(vk_error!("device_wait_idle"))?;
+            device.destroy_pipeline(self.pipeline, None);
+        }
+        self.pipeline = pipeline;
+
+        Ok(true)
+    }
+
+    /// Creates a texture from pixels in RGBA order, row by row from the top left corner.
+    pub fn create_texture(
+        &mut self,
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        upload_queue: &mut UploadQueue,
+        queue: vk::Queue,
+        extent: vk::Extent2D,
+        pixels: &[u8],
+    ) -> Result<OverlayTextureId, RendererError> {
+        check_pixel_count(extent, pixels)?;
+        if self.textures.len() >= MAX_OVERLAY_TEXTURES {
+            return Err(renderer_error!(
+                "create_texture",
+                "the overlay can't have more than {} textures",
+                MAX_OVERLAY_TEXTURES
+            ));
+        }
+
+        let images = create_texture_images(device, allocator, upload_queue, queue, extent, pixels)?;
+        let descriptor_sets =
+            create_descriptor_sets(device, self.descriptor_pool, self.descriptor_set_layout)?;
+        self.write_texture_descriptors(device, &images, &descriptor_sets);
+
+        self.textures.push(OverlayTexture {
+            images,
+            descriptor_sets,
+            extent,
+            pixels: pixels.to_vec(),
+            outdated: [false; MAX_FRAME_DRAWS],
+        });
+        Ok(self.textures.len() - 1)
+    }
+
+    fn write_texture_descriptors(
+        &self,
+        device: &ash::Device,
+        images: &[Image],
+        descriptor_sets: &[vk::DescriptorSet],
+    ) {
+        for (image, &descriptor_set) in images.iter().zip(descriptor_sets) {
+            let texture_descriptor = vk::DescriptorImageInfo {
+                image_view: image.view,
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ..Default::default()
+            };
+            let sampler_descriptor = vk::DescriptorImageInfo {
+                sampler: self.sampler,
+                ..Default::default()
+            };
+            let mut write_desc_sets = [
+                vk::WriteDescriptorSet {
+                    dst_binding: 0,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+                    p_image_info: &texture_descriptor,
+                    ..Default::default()
+                },
+                vk::WriteDescriptorSet {
+                    dst_binding: 1,
+                    descriptor_count: 1,
+                    descriptor_type: vk::DescriptorType::SAMPLER,
+                    p_image_info: &sampler_descriptor,
+                    ..Default::default()
+                },
+            ];
+            update_descriptor_sets(device, &[descriptor_set], &mut write_desc_sets);
+        }
+    }
+
+    /// Swaps the images of the texture for ones with a new size and frees the previous ones,
+    /// no frame in flight may still read them.
+    fn replace_texture_images(
+        &mut self,
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        id: OverlayTextureId,
+        images: Vec<Image>,
+        extent: vk::Extent2D,
+        pixels: &[u8],
+    ) -> Result<(), RendererError> {
+        let texture = self.texture(id)?;
+        let previous_images = std::mem::replace(&mut texture.images, images);
+        previous_images
+            .iter()
+            .for_each(|image| image.free(device, allocator));
+        texture.extent = extent;
+        texture.pixels = pixels.to_vec();
+        texture.outdated = [false; MAX_FRAME_DRAWS];
+
+        let texture = &self.textures[id];
+        self.write_texture_descriptors(device, &texture.images, &texture.descriptor_sets);
+        Ok(())
+    }
+
+    #[inline]
+    fn texture(&mut self, id: OverlayTextureId) -> Result<&mut OverlayTexture, RendererError> {
+        self.textures
+            .get_mut(id)
+            .ok_or_else(|| renderer_error!("overlay_texture", "there is no texture {}", id))
+    }
+
+    /// Replaces the pixels of the texture, the size can't change.
+    /// Each frame in flight sees the new pixels once its image was uploaded again.
+    pub fn update_texture(
+        &mut self,
+        id: OverlayTextureId,
+        pixels: &[u8],
+    ) -> Result<(), RendererError> {
+        let texture = self.texture(id)?;
+        check_pixel_count(texture.extent, pixels)?;
+
+        texture.pixels.copy_from_slice(pixels);
+        texture.outdated = [true; MAX_FRAME_DRAWS];
+        Ok(())
+    }
+
+    /// Uploads the updated textures of the frame, the fence of the frame must have been waited for.
+    pub fn upload_textures(
+        &mut self,
+        device: &ash::Device,
+        upload_queue: &mut UploadQueue,
+        queue: vk::Queue,
+        frame_index: usize,
+    ) -> Result<(), RendererError> {
+        for texture in self
+            .textures
+            .iter_mut()
+            .filter(|texture| texture.outdated[frame_index])
+        {
+            upload_queue.upload_image(
+                device,
+                queue,
+                &texture.pixels,
+                texture.images[frame_index].img,
+                texture.extent,
+            )?;
+            texture.outdated[frame_index] = false;
+        }
+        Ok(())
+    }
+
+    /// Replaces the font, its atlas texture is reused if it has the same size.
+    pub fn set_font(
+        &mut self,
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        upload_queue: &mut UploadQueue,
+        queue: vk::Queue,
+        atlas: FontAtlas,
+    ) -> Result<(), RendererError> {
+        let texture = match self.font.take() {
+            Some((previous, texture)) if previous.extent == atlas.extent => {
+                self.update_texture(texture, &atlas.pixels)?;
+                texture
+            }
+            //Keeps the texture id, the quads batched with it this frame stay valid
+            Some((_, texture)) => {
+                let images = create_texture_images(
+                    device,
+                    allocator,
+                    upload_queue,
+                    queue,
+                    atlas.extent,
+                    &atlas.pixels,
+                )?;
+                //The previous atlas can still be used by the frames in flight
+                unsafe {
+                    device
+                        .device_wait_idle()
+                        .map_err(vk_error!("device_wait_idle"))?;
+                }
+                self.replace_texture_images(
+                    device,
+                    allocator,
+                    texture,
+                    images,
+                    atlas.extent,
+                    &atlas.pixels,
+                )?;
+                texture
+            }
+            None => self.create_texture(
+                device,
+                allocator,
+                upload_queue,
+                queue,
+                atlas.extent,
+                &atlas.pixels,
+            )?,
+        };
+
+        self.font = Some((atlas, texture));
+        Ok(())
+    }
+
+    #[inline]
+    pub fn font(&self) -> Option<&FontAtlas> {
+        self.font.as_ref().map(|(atlas, _)| atlas)
+    }
+
+    /// Lays out the text from the top left corner of its first line, in pixels.
+    pub fn text(
+        &mut self,
+        text: &str,
+        position: Vector2<f32>,
+        scale: f32,
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) {
+        let (atlas, texture) = match self.font.take() {
+            Some(font) => font,
+            None => return,
+        };
+
+        let mut pen = Vector2::new(position.x, position.y + atlas.ascent * scale);
+        for character in text.chars() {
+            if character == '\n' {
+                pen.x = position.x;
+                pen.y += atlas.line_height * scale;
+                continue;
+            }
+            let glyph = match atlas.glyph(character) {
+                Some(glyph) => *glyph,
+                None => continue,
+            };
+
+            if glyph.size.x > 0. && glyph.size.y > 0. {
+                let min = pen + glyph.offset * scale;
+                let max = pen + (glyph.offset + glyph.size) * scale;
+                if !self.quad(
+                    texture,
+                    rect_corners(min, max),
+                    (glyph.uv_min, glyph.uv_max),
+                    color,
+                    screen_extent,
+                ) {
+                    break;
+                }
+            }
+            pen.x += glyph.advance * scale;
+        }
+
+        self.font = Some((atlas, texture));
+    }
+
+    /// Fills a rectangle, `position` is its top left corner in pixels.
+    #[inline]
+    pub fn rect(
+        &mut self,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) {
+        self.solid_quad(
+            rect_corners(position, position + size),
+            color,
+            screen_extent,
+        );
+    }
+
+    /// Draws a line between two points in pixels.
+    pub fn line(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        width: f32,
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) {
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length <= f32::EPSILON {
+            return;
+        }
+        //Half the width on both sides of the line
+        let side = Vector2::new(-direction.y, direction.x) / length * width / 2.;
+
+        self.solid_quad(
+            [start - side, start + side, end + side, end - side],
+            color,
+            screen_extent,
+        );
+    }
+
+    /// Draws the whole texture stretched over the rectangle, multiplied by the color.
+    #[inline]
+    pub fn image(
+        &mut self,
+        texture: OverlayTextureId,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) {
+        //Recording it would index a texture that doesn't exist
+        if texture >= self.textures.len() {
+            return;
+        }
+
+        self.quad(
+            texture,
+            rect_corners(position, position + size),
+            (Vector2::zeros(), Vector2::new(1., 1.)),
+            color,
+            screen_extent,
+        );
+    }
+
+    #[inline]
+    fn solid_quad(
+        &mut self,
+        corners: [Vector2<f32>; 4],
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) {
+        if let Some((atlas, texture)) = &self.font {
+            let (uv, texture) = (atlas.solid_uv, *texture);
+            self.quad(texture, corners, (uv, uv), color, screen_extent);
+        }
+    }
+
+    /// Adds a quad from its corners in pixels, returns *false* if the frame is full.
+    /// The corners go around the quad, starting with the one at the minimum texture coordinates
+    /// and continuing towards the one with the maximum v.
+    fn quad(
+        &mut self,
+        texture: OverlayTextureId,
+        corners: [Vector2<f32>; 4],
+        (uv_min, uv_max): (Vector2<f32>, Vector2<f32>),
+        color: Vector4<f32>,
+        screen_extent: vk::Extent2D,
+    ) -> bool {
+        if self.vertices.len() + VERTICES_PER_QUAD > MAX_OVERLAY_QUADS * VERTICES_PER_QUAD {
+            return false;
+        }
+
+        let screen_size = Vector2::new(screen_extent.width as f32, screen_extent.height as f32);
+        let uvs = [
+            uv_min,
+            Vector2::new(uv_min.x, uv_max.y),
+            uv_max,
+            Vector2::new(uv_max.x, uv_min.y),
+        ];
+        let vertex = |corner: usize| OverlayVertex {
+            pos: corners[corner].component_div(&screen_size) * 2. - Vector2::new(1., 1.),
+            uv: uvs[corner],
+            color,
+        };
+        self.vertices.extend([
+            vertex(0),
+            vertex(1),
+            vertex(2),
+            vertex(0),
+            vertex(2),
+            vertex(3),
+        ]);
+
+        match self.batches.last_mut() {
+            Some((batch_texture, count)) if *batch_texture == texture => {
+                *count += VERTICES_PER_QUAD as u32
+            }
+            _ => self.batches.push((texture, VERTICES_PER_QUAD as u32)),
+        }
+        true
+    }
+
+    /// Records the quads of the frame into the command buffer, then clears them.
+    pub fn record(
+        &mut self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame_index: usize,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = &self.vertex_buffers[frame_index];
+        unsafe {
+            //The fence of this frame was waited for, the GPU doesn't read this buffer anymore
+            copy_nonoverlapping(
+                self.vertices.as_ptr(),
+                vertex_buffer.allocation.mapped_ptr as *mut OverlayVertex,
+                self.vertices.len(),
+            );
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[vertex_buffer.buf], &[0]);
+
+            let mut first_vertex = 0;
+            for &(texture, vertex_count) in &self.batches {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.pipeline_layout,
+                    0,
+                    &[self.textures[texture].descriptor_sets[frame_index]],
+                    &[],
+                );
+                device.cmd_draw(command_buffer, vertex_count, 1, first_vertex, 0);
+                first_vertex += vertex_count;
+            }
+        }
+
+        self.vertices.clear();
+        self.batches.clear();
+    }
+
+    pub fn clean_up(&mut self, device: &ash::Device, allocator: &mut MemoryAllocator) {
+        self.vertex_buffers
+            .iter()
+            .for_each(|buffer| buffer.free(device, allocator));
+        self.textures
+            .iter()
+            .flat_map(|texture| &texture.images)
+            .for_each(|image| image.free(device, allocator));
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+            device.destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+/// One image per frame in flight, with the same pixels.
+fn create_texture_images(
+    device: &ash::Device,
+    allocator: &mut MemoryAllocator,
+    upload_queue: &mut UploadQueue,
+    queue: vk::Queue,
+    extent: vk::Extent2D,
+    pixels: &[u8],
+) -> Result<Vec<Image>, RendererError> {
+    (0..MAX_FRAME_DRAWS)
+        .map(|_| {
+            Image::texture(
+                device,
+                allocator,
+                upload_queue,
+                queue,
+                TextureData::Rgba { extent, pixels },
+            )
+        })
+        .collect()
+}
+
+/// Corners of an axis aligned rectangle, in the order [`Overlay`] quads take them.
+#[inline]
+fn rect_corners(min: Vector2<f32>, max: Vector2<f32>) -> [Vector2<f32>; 4] {
+    [
+        min,
+        Vector2::new(min.x, max.y),
+        max,
+        Vector2::new(max.x, min.y),
+    ]
+}
+
+#[inline]
+fn check_pixel_count(extent: vk::Extent2D, pixels: &[u8]) -> Result<(), RendererError> {
+    let expected = extent.width as usize * extent.height as usize * 4;
+    if pixels.len() != expected {
+        return Err(renderer_error!(
+            "overlay texture",
+            "{}x{} pixels need {} bytes, got {}",
+            extent.width,
+            extent.height,
+            expected,
+            pixels.len()
+        ));
+    }
+    Ok(())
+}
+
+fn validate_shaders(vertex_code: &[u32], fragment_code: &[u32]) -> Result<(), RendererError> {
+    let vertex_attributes = OverlayVertex::attribute_descriptions(0);
+    setup::validate_shader_interface(
+        vertex_code,
+        fragment_code,
+        &PipelineInterface {
+            push_constant_name: "none",
+            push_constants: &[],
+            push_constant_range: vk::PushConstantRange::default(),
+            vertex_binding: OverlayVertex::binding_description(0),
+            vertex_attributes: &vertex_attributes,
+            descriptor_bindings: &descriptor_set_layout_bindings(),
+        },
+    )
+}
+
+fn create_pipeline(
+    device: &ash::Device,
+    pipeline_layout: vk::PipelineLayout,
+    render_pass: vk::RenderPass,
+    vertex_code: &[u32],
+    fragment_code: &[u32],
+) -> Result<vk::Pipeline, RendererError> {
+    let vertex_shader_module = setup::create_shader_module(device, vertex_code)?;
+    let fragment_shader_module = setup::create_shader_module(device, fragment_code)
+        .inspect_err(|_| unsafe { device.destroy_shader_module(vertex_shader_module, None) })?;
+    let pipeline = setup::create_pipelines::<OverlayVertex>(
+        device,
+        vertex_shader_module,
+        fragment_shader_module,
+        pipeline_layout,
+        render_pass,
+        &MaterialCreateInfo::default()
+            .render_queue(RenderQueue::Overlay)
+            .blend_mode(BlendMode::Alpha)
+            .depth_test(false)
+            .depth_write(false),
+    );
+    unsafe {
+        device.destroy_shader_module(vertex_shader_module, None);
+        device.destroy_shader_module(fragment_shader_module, None);
+    }
+    pipeline
+}
+
+fn descriptor_set_layout_bindings() -> [vk::DescriptorSetLayoutBinding; 2] {
+    [
+        vk::DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        },
+        vk::DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::SAMPLER,
+            descriptor_count: 1,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            ..Default::default()
+        },
+    ]
+}
+
+impl Renderer {
+    /// Rasterises the font used by [`Renderer::draw_text`], `size` is in pixels per em at scale 1.
+    /// Replaces the previous font.
+    pub fn load_font(&mut self, font_data: &[u8], size: f32) -> Result<(), RendererError> {
+        let atlas = FontAtlas::new(font_data, size)?;
+        self.data.overlay.set_font(
+            &self.base.device,
+            &mut self.base.allocator,
+            &mut self.data.upload_queue,
+            self.base.queue,
+            atlas,
+        )
+    }
+
+    /// Creates a texture the overlay can draw with [`Renderer::draw_image`],
+    /// from `width * height` pixels in RGBA order.
+    pub fn create_overlay_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<OverlayTextureId, RendererError> {
+        self.data.overlay.create_texture(
+            &self.base.device,
+            &mut self.base.allocator,
+            &mut self.data.upload_queue,
+            self.base.queue,
+            vk::Extent2D { width, height },
+            pixels,
+        )
+    }
+
+    #[inline]
+    pub fn update_overlay_texture(
+        &mut self,
+        texture: OverlayTextureId,
+        pixels: &[u8],
+    ) -> Result<(), RendererError> {
+        self.data.overlay.update_texture(texture, pixels)
+    }
+
+    /// Draws the text this frame, `position` is the top left corner in pixels.
+    /// Does nothing if no font was loaded.
+    #[inline]
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        position: Vector2<f32>,
+        scale: f32,
+        color: Vector4<f32>,
+    ) {
+        let screen_extent = self.base.surface_extent;
+        self.data
+            .overlay
+            .text(text, position, scale, color, screen_extent);
+    }
+
+    /// Fills a rectangle this frame, in the same pass as the text, so text drawn after it is on top.
+    /// Does nothing if no font was loaded.
+    #[inline]
+    pub fn draw_rect(&mut self, position: Vector2<f32>, size: Vector2<f32>, color: Vector4<f32>) {
+        let screen_extent = self.base.surface_extent;
+        self.data.overlay.rect(position, size, color, screen_extent);
+    }
+
+    /// Draws a screen space line this frame, in pixels.
+    /// Does nothing if no font was loaded.
+    #[inline]
+    pub fn draw_line(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        width: f32,
+        color: Vector4<f32>,
+    ) {
+        let screen_extent = self.base.surface_extent;
+        self.data
+            .overlay
+            .line(start, end, width, color, screen_extent);
+    }
+
+    /// Draws an overlay texture this frame, `position` is the top left corner in pixels.
+    #[inline]
+    pub fn draw_image(
+        &mut self,
+        texture: OverlayTextureId,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+        color: Vector4<f32>,
+    ) {
+        let screen_extent = self.base.surface_extent;
+        self.data
+            .overlay
+            .image(texture, position, size, color, screen_extent);
+    }
+
+    /// Size of the text in pixels, zero if no font was loaded.
+    #[inline]
+    pub fn text_size(&self, text: &str, scale: f32) -> Vector2<f32> {
+        self.data
+            .overlay
+            .font()
+            .map_or_else(Vector2::zeros, |atlas| atlas.measure(text, scale))
+    }
+}
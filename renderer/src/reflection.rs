@@ -0,0 +1,990 @@
+use std::collections::HashMap;
+
+use ash::vk;
+
+use crate::{error::RendererError, renderer_error};
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+//Opcodes
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+//Decorations
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+//Storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+//Execution models
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+
+/// Offset and size of a field on the Rust side, see [`field_layouts!`](crate::field_layouts).
+#[derive(Debug, Clone, Copy)]
+pub struct FieldLayout {
+    pub name: &'static str,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[inline]
+pub fn field_size<T, F>(_field: fn(&T) -> &F) -> usize {
+    std::mem::size_of::<F>()
+}
+
+/// Lists the offset and size of the given fields of a struct.
+#[macro_export]
+macro_rules! field_layouts {
+    ($type:ty, $($field:ident),+ $(,)?) => {{
+        [$(
+            $crate::reflection::FieldLayout {
+                name: stringify!($field),
+                offset: $crate::offset_of!($type, $field) as u32,
+                size: $crate::reflection::field_size(|value: &$type| &value.$field) as u32,
+            }
+        ),+]
+    }};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumericKind {
+    Float,
+    Int,
+    Uint,
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct VertexInput {
+    pub name: String,
+    pub location: u32,
+    pub components: u32,
+    kind: NumericKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptorBinding {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+}
+
+/// What a shader expects from the pipeline it's used in.
+#[derive(Debug, Clone)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    pub push_constants: Vec<BlockMember>,
+    pub inputs: Vec<VertexInput>,
+    pub descriptor_bindings: Vec<DescriptorBinding>,
+}
+
+/// The Rust side of a pipeline, that the shaders are checked against.
+pub struct PipelineInterface<'a> {
+    pub push_constant_name: &'static str,
+    pub push_constants: &'a [FieldLayout],
+    pub push_constant_range: vk::PushConstantRange,
+    pub vertex_binding: vk::VertexInputBindingDescription,
+    pub vertex_attributes: &'a [vk::VertexInputAttributeDescription],
+    pub descriptor_bindings: &'a [vk::DescriptorSetLayoutBinding],
+}
+
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<(u32, u32), u32>,
+    flags: HashMap<u32, Vec<u32>>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<(u32, u32, u32)>,
+    execution_model: Option<u32>,
+}
+
+impl ShaderReflection {
+    pub fn new(code: &[u32]) -> Result<Self, RendererError> {
+        let module = Module::parse(code)?;
+
+        let stage = match module.execution_model {
+            Some(EXECUTION_MODEL_VERTEX) => vk::ShaderStageFlags::VERTEX,
+            Some(EXECUTION_MODEL_FRAGMENT) => vk::ShaderStageFlags::FRAGMENT,
+            Some(model) => {
+                return Err(renderer_error!(
+                    "reflect_shader",
+                    "unsupported execution model {}",
+                    model
+                ))
+            }
+            None => return Err(renderer_error!("reflect_shader", "missing entry point")),
+        };
+
+        let mut reflection = Self {
+            stage,
+            push_constants: Vec::new(),
+            inputs: Vec::new(),
+            descriptor_bindings: Vec::new(),
+        };
+
+        for &(pointer_type, id, storage_class) in &module.variables {
+            let pointee = match module.types.get(&pointer_type) {
+                Some(Type::Pointer { pointee }) => *pointee,
+                _ => continue,
+            };
+            let name = module.names.get(&id).cloned().unwrap_or_default();
+
+            match storage_class {
+                STORAGE_PUSH_CONSTANT => {
+                    reflection.push_constants = module.block_members(pointee)?;
+                }
+                STORAGE_INPUT if stage == vk::ShaderStageFlags::VERTEX => {
+                    if module.has_flag(id, DECORATION_BUILT_IN) {
+                        continue;
+                    }
+                    let location = module.decoration(id, DECORATION_LOCATION).ok_or_else(|| {
+                        renderer_error!("reflect_shader", "input `{}` has no location", name)
+                    })?;
+                    let (components, kind) = module.numeric_type(pointee).ok_or_else(|| {
+                        renderer_error!("reflect_shader", "input `{}` isn't a number or vector", name)
+                    })?;
+
+                    reflection.inputs.push(VertexInput {
+                        name,
+                        location,
+                        components,
+                        kind,
+                    });
+                }
+                STORAGE_UNIFORM | STORAGE_UNIFORM_CONSTANT | STORAGE_STORAGE_BUFFER => {
+                    let (element, count) = match module.types.get(&pointee) {
+                        Some(Type::Array { element, length }) => (*element, *length),
+                        Some(Type::RuntimeArray { element }) => (*element, 0),
+                        _ => (pointee, 1),
+                    };
+                    let descriptor_type = match (storage_class, module.types.get(&element)) {
+                        (STORAGE_UNIFORM, _) if module.has_flag(element, DECORATION_BUFFER_BLOCK) => {
+                            vk::DescriptorType::STORAGE_BUFFER
+                        }
+                        (STORAGE_UNIFORM, _) => vk::DescriptorType::UNIFORM_BUFFER,
+                        (STORAGE_STORAGE_BUFFER, _) => vk::DescriptorType::STORAGE_BUFFER,
+                        (_, Some(Type::SampledImage)) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        (_, Some(Type::Sampler)) => vk::DescriptorType::SAMPLER,
+                        (_, Some(Type::Image { sampled: 2 })) => vk::DescriptorType::STORAGE_IMAGE,
+                        (_, Some(Type::Image { .. })) => vk::DescriptorType::SAMPLED_IMAGE,
+                        _ => continue,
+                    };
+
+                    reflection.descriptor_bindings.push(DescriptorBinding {
+                        name,
+                        set: module.decoration(id, DECORATION_DESCRIPTOR_SET).unwrap_or(0),
+                        binding: module.decoration(id, DECORATION_BINDING).unwrap_or(0),
+                        descriptor_type,
+                        count,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(reflection)
+    }
+
+    /// Checks that the push constants, vertex attributes and descriptor bindings
+    /// used by the shader match the ones the pipeline is created with.
+    pub fn validate(&self, interface: &PipelineInterface) -> Result<(), RendererError> {
+        let stage = stage_name(self.stage);
+
+        if !self.push_constants.is_empty() {
+            let range = interface.push_constant_range;
+            if !range.stage_flags.contains(self.stage) {
+                return Err(renderer_error!(
+                    "validate_shader",
+                    "the {} shader uses push constants, but the push constant range is only visible to {:?}",
+                    stage,
+                    range.stage_flags
+                ));
+            }
+
+            for member in &self.push_constants {
+                if member.offset.saturating_add(member.size) > range.offset + range.size {
+                    return Err(renderer_error!(
+                        "validate_shader",
+                        "push constant `{}` in the {} shader ends at byte {}, but the push constant range ends at {}",
+                        member.name,
+                        stage,
+                        member.offset.saturating_add(member.size),
+                        range.offset + range.size
+                    ));
+                }
+
+                let field = interface
+                    .push_constants
+                    .iter()
+                    .find(|f| f.offset == member.offset);
+                match field {
+                    Some(field) if field.size == member.size => {}
+                    Some(field) => {
+                        return Err(renderer_error!(
+                            "validate_shader",
+                            "push constant `{}` in the {} shader is {} bytes, but {}::{} is {} bytes",
+                            member.name,
+                            stage,
+                            member.size,
+                            interface.push_constant_name,
+                            field.name,
+                            field.size
+                        ))
+                    }
+                    None => {
+                        return Err(renderer_error!(
+                            "validate_shader",
+                            "push constant `{}` in the {} shader is at offset {}, but no field of {} starts there ({})",
+                            member.name,
+                            stage,
+                            member.offset,
+                            interface.push_constant_name,
+                            describe_fields(interface.push_constants)
+                        ))
+                    }
+                }
+            }
+        }
+
+        for input in &self.inputs {
+            let attribute = interface
+                .vertex_attributes
+                .iter()
+                .find(|a| a.location == input.location)
+                .ok_or_else(|| {
+                    renderer_error!(
+                        "validate_shader",
+                        "vertex input `{}` at location {} has no matching vertex attribute",
+                        input.name,
+                        input.location
+                    )
+                })?;
+
+            if let Some((components, kind, size)) = format_info(attribute.format) {
+                if components != input.components || kind != input.kind {
+                    return Err(renderer_error!(
+                        "validate_shader",
+                        "vertex input `{}` at location {} is {} x {:?}, but the attribute format is {:?}",
+                        input.name,
+                        input.location,
+                        input.components,
+                        input.kind,
+                        attribute.format
+                    ));
+                }
+                if attribute.offset + size > interface.vertex_binding.stride {
+                    return Err(renderer_error!(
+                        "validate_shader",
+                        "vertex attribute at location {} ends at byte {}, past the vertex stride {}",
+                        attribute.location,
+                        attribute.offset + size,
+                        interface.vertex_binding.stride
+                    ));
+                }
+            }
+        }
+
+        for binding in &self.descriptor_bindings {
+            if binding.set != 0 {
+                return Err(renderer_error!(
+                    "validate_shader",
+                    "`{}` in the {} shader uses descriptor set {}, but the pipeline only has set 0",
+                    binding.name,
+                    stage,
+                    binding.set
+                ));
+            }
+
+            let layout_binding = interface
+                .descriptor_bindings
+                .iter()
+                .find(|b| b.binding == binding.binding)
+                .ok_or_else(|| {
+                    renderer_error!(
+                        "validate_shader",
+                        "`{}` in the {} shader uses binding {}, which isn't in the descriptor set layout",
+                        binding.name,
+                        stage,
+                        binding.binding
+                    )
+                })?;
+
+            if !descriptor_types_match(binding.descriptor_type, layout_binding.descriptor_type) {
+                return Err(renderer_error!(
+                    "validate_shader",
+                    "`{}` at binding {} is {:?} in the {} shader, but {:?} in the descriptor set layout",
+                    binding.name,
+                    binding.binding,
+                    binding.descriptor_type,
+                    stage,
+                    layout_binding.descriptor_type
+                ));
+            }
+            if binding.count > layout_binding.descriptor_count {
+                return Err(renderer_error!(
+                    "validate_shader",
+                    "`{}` at binding {} has {} descriptors in the {} shader, but {} in the descriptor set layout",
+                    binding.name,
+                    binding.binding,
+                    binding.count,
+                    stage,
+                    layout_binding.descriptor_count
+                ));
+            }
+            if !layout_binding.stage_flags.contains(self.stage) {
+                return Err(renderer_error!(
+                    "validate_shader",
+                    "`{}` at binding {} is used by the {} shader, but is only visible to {:?}",
+                    binding.name,
+                    binding.binding,
+                    stage,
+                    layout_binding.stage_flags
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Module {
+    fn parse(code: &[u32]) -> Result<Self, RendererError> {
+        if code.len() < 5 || code[0] != SPIRV_MAGIC {
+            return Err(renderer_error!("reflect_shader", "not a SPIR-V module"));
+        }
+
+        let mut module = Self::default();
+        let mut index = 5;
+        while index < code.len() {
+            let word_count = (code[index] >> 16) as usize;
+            let opcode = code[index] & 0xffff;
+            if word_count == 0 || index + word_count > code.len() {
+                return Err(renderer_error!(
+                    "reflect_shader",
+                    "malformed instruction at word {}",
+                    index
+                ));
+            }
+            let operands = &code[index + 1..index + word_count];
+            if operands.len() < min_operands(opcode) {
+                return Err(renderer_error!(
+                    "reflect_shader",
+                    "instruction {} at word {} has {} operands, it needs at least {}",
+                    opcode,
+                    index,
+                    operands.len(),
+                    min_operands(opcode)
+                ));
+            }
+            index += word_count;
+
+            match opcode {
+                OP_NAME => {
+                    module.names.insert(operands[0], parse_string(&operands[1..]));
+                }
+                OP_MEMBER_NAME => {
+                    module
+                        .member_names
+                        .insert((operands[0], operands[1]), parse_string(&operands[2..]));
+                }
+                OP_ENTRY_POINT if module.execution_model.is_none() => {
+                    module.execution_model = Some(operands[0]);
+                }
+                OP_DECORATE => match operands.get(2) {
+                    Some(&value) => {
+                        module.decorations.insert((operands[0], operands[1]), value);
+                    }
+                    None => module.flags.entry(operands[0]).or_default().push(operands[1]),
+                },
+                OP_MEMBER_DECORATE => {
+                    if let Some(&value) = operands.get(3) {
+                        module
+                            .member_decorations
+                            .insert((operands[0], operands[1], operands[2]), value);
+                    }
+                }
+                OP_TYPE_BOOL => {
+                    module.define_type(operands[0], Type::Bool)?;
+                }
+                OP_TYPE_INT => {
+                    module.define_type(
+                        operands[0],
+                        Type::Int {
+                            width: operands[1],
+                            signed: operands[2] != 0,
+                        },
+                    )?;
+                }
+                OP_TYPE_FLOAT => {
+                    module.define_type(operands[0], Type::Float { width: operands[1] })?;
+                }
+                OP_TYPE_VECTOR => {
+                    module.define_type(
+                        operands[0],
+                        Type::Vector {
+                            component: operands[1],
+                            count: operands[2],
+                        },
+                    )?;
+                }
+                OP_TYPE_MATRIX => {
+                    module.define_type(
+                        operands[0],
+                        Type::Matrix {
+                            column: operands[1],
+                            count: operands[2],
+                        },
+                    )?;
+                }
+                OP_TYPE_IMAGE => {
+                    module.define_type(
+                        operands[0],
+                        Type::Image {
+                            sampled: operands[6],
+                        },
+                    )?;
+                }
+                OP_TYPE_SAMPLER => {
+                    module.define_type(operands[0], Type::Sampler)?;
+                }
+                OP_TYPE_SAMPLED_IMAGE => {
+                    module.define_type(operands[0], Type::SampledImage)?;
+                }
+                OP_TYPE_ARRAY => {
+                    let length = module.constants.get(&operands[2]).copied().unwrap_or(0);
+                    module.define_type(
+                        operands[0],
+                        Type::Array {
+                            element: operands[1],
+                            length,
+                        },
+                    )?;
+                }
+                OP_TYPE_RUNTIME_ARRAY => {
+                    module.define_type(
+                        operands[0],
+                        Type::RuntimeArray {
+                            element: operands[1],
+                        },
+                    )?;
+                }
+                OP_TYPE_STRUCT => {
+                    module.define_type(
+                        operands[0],
+                        Type::Struct {
+                            members: operands[1..].to_vec(),
+                        },
+                    )?;
+                }
+                OP_TYPE_POINTER => {
+                    module.define_type(
+                        operands[0],
+                        Type::Pointer {
+                            pointee: operands[2],
+                        },
+                    )?;
+                }
+                OP_CONSTANT => {
+                    module.constants.insert(operands[1], operands[2]);
+                }
+                OP_VARIABLE => {
+                    module
+                        .variables
+                        .push((operands[0], operands[1], operands[2]));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(module)
+    }
+
+    /// Adds a type, the types it's made of have to be declared before it so there are no cycles.
+    fn define_type(&mut self, id: u32, value: Type) -> Result<(), RendererError> {
+        let dependencies = match &value {
+            Type::Vector { component, .. } => vec![*component],
+            Type::Matrix { column, .. } => vec![*column],
+            Type::Array { element, .. } | Type::RuntimeArray { element } => vec![*element],
+            Type::Struct { members } => members.clone(),
+            Type::Pointer { pointee } => vec![*pointee],
+            _ => Vec::new(),
+        };
+        if let Some(dependency) = dependencies.iter().find(|id| !self.types.contains_key(id)) {
+            return Err(renderer_error!(
+                "reflect_shader",
+                "type {} uses type {} before it's declared",
+                id,
+                dependency
+            ));
+        }
+        if self.types.insert(id, value).is_some() {
+            return Err(renderer_error!(
+                "reflect_shader",
+                "type {} is declared twice",
+                id
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).copied()
+    }
+
+    #[inline]
+    fn has_flag(&self, id: u32, decoration: u32) -> bool {
+        self.flags
+            .get(&id)
+            .is_some_and(|flags| flags.contains(&decoration))
+            || self.decorations.contains_key(&(id, decoration))
+    }
+
+    fn block_members(&self, struct_type: u32) -> Result<Vec<BlockMember>, RendererError> {
+        let members = match self.types.get(&struct_type) {
+            Some(Type::Struct { members }) => members,
+            _ => return Err(renderer_error!("reflect_shader", "block isn't a struct")),
+        };
+
+        //Some compilers wrap the block in a struct with the actual block as its only member.
+        if let [inner] = members.as_slice() {
+            if matches!(self.types.get(inner), Some(Type::Struct { .. })) {
+                return self.block_members(*inner);
+            }
+        }
+
+        members
+            .iter()
+            .enumerate()
+            .map(|(index, &member_type)| {
+                let index = index as u32;
+                let name = self
+                    .member_names
+                    .get(&(struct_type, index))
+                    .cloned()
+                    .unwrap_or_else(|| format!("member {}", index));
+                let offset = self
+                    .member_decorations
+                    .get(&(struct_type, index, DECORATION_OFFSET))
+                    .copied()
+                    .ok_or_else(|| {
+                        renderer_error!("reflect_shader", "block member `{}` has no offset", name)
+                    })?;
+                let matrix_stride = self
+                    .member_decorations
+                    .get(&(struct_type, index, DECORATION_MATRIX_STRIDE))
+                    .copied();
+                let size = self.size_of(member_type, matrix_stride);
+
+                Ok(BlockMember { name, offset, size })
+            })
+            .collect()
+    }
+
+    fn size_of(&self, type_id: u32, matrix_stride: Option<u32>) -> u32 {
+        match self.types.get(&type_id) {
+            Some(Type::Bool) => 4,
+            Some(Type::Int { width, .. }) | Some(Type::Float { width }) => width / 8,
+            Some(Type::Vector { component, count }) => {
+                self.size_of(*component, None).saturating_mul(*count)
+            }
+            Some(Type::Matrix { column, count }) => matrix_stride
+                .unwrap_or_else(|| self.size_of(*column, None))
+                .saturating_mul(*count),
+            Some(Type::Array { element, length }) => {
+                let stride = self
+                    .decoration(type_id, DECORATION_ARRAY_STRIDE)
+                    .unwrap_or_else(|| self.size_of(*element, matrix_stride));
+                stride.saturating_mul(*length)
+            }
+            Some(Type::Struct { members }) => members
+                .iter()
+                .enumerate()
+                .map(|(index, &member)| {
+                    let index = index as u32;
+                    let offset = self
+                        .member_decorations
+                        .get(&(type_id, index, DECORATION_OFFSET))
+                        .copied()
+                        .unwrap_or(0);
+                    let stride = self
+                        .member_decorations
+                        .get(&(type_id, index, DECORATION_MATRIX_STRIDE))
+                        .copied();
+                    offset.saturating_add(self.size_of(member, stride))
+                })
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn numeric_type(&self, type_id: u32) -> Option<(u32, NumericKind)> {
+        match self.types.get(&type_id)? {
+            Type::Float { .. } => Some((1, NumericKind::Float)),
+            Type::Int { signed: true, .. } => Some((1, NumericKind::Int)),
+            Type::Int { signed: false, .. } => Some((1, NumericKind::Uint)),
+            Type::Vector { component, count } => {
+                let (_, kind) = self.numeric_type(*component)?;
+                Some((*count, kind))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Operands the parsed instructions need, per the SPIR-V specification.
+#[inline]
+fn min_operands(opcode: u32) -> usize {
+    match opcode {
+        OP_TYPE_BOOL | OP_TYPE_SAMPLER | OP_TYPE_STRUCT => 1,
+        OP_NAME | OP_DECORATE | OP_TYPE_FLOAT | OP_TYPE_SAMPLED_IMAGE | OP_TYPE_RUNTIME_ARRAY => 2,
+        OP_MEMBER_NAME | OP_ENTRY_POINT | OP_MEMBER_DECORATE | OP_TYPE_INT | OP_TYPE_VECTOR
+        | OP_TYPE_MATRIX | OP_TYPE_ARRAY | OP_TYPE_POINTER | OP_CONSTANT | OP_VARIABLE => 3,
+        OP_TYPE_IMAGE => 8,
+        _ => 0,
+    }
+}
+
+fn parse_string(words: &[u32]) -> String {
+    let bytes = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Component count, numeric kind and size of the vertex formats we use.
+fn format_info(format: vk::Format) -> Option<(u32, NumericKind, u32)> {
+    let info = match format {
+        vk::Format::R32_SFLOAT => (1, NumericKind::Float, 4),
+        vk::Format::R32G32_SFLOAT => (2, NumericKind::Float, 8),
+        vk::Format::R32G32B32_SFLOAT => (3, NumericKind::Float, 12),
+        vk::Format::R32G32B32A32_SFLOAT => (4, NumericKind::Float, 16),
+        vk::Format::R32_SINT => (1, NumericKind::Int, 4),
+        vk::Format::R32G32_SINT => (2, NumericKind::Int, 8),
+        vk::Format::R32G32B32_SINT => (3, NumericKind::Int, 12),
+        vk::Format::R32G32B32A32_SINT => (4, NumericKind::Int, 16),
+        vk::Format::R32_UINT => (1, NumericKind::Uint, 4),
+        vk::Format::R32G32_UINT => (2, NumericKind::Uint, 8),
+        vk::Format::R32G32B32_UINT => (3, NumericKind::Uint, 12),
+        vk::Format::R32G32B32A32_UINT => (4, NumericKind::Uint, 16),
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SNORM => (4, NumericKind::Float, 4),
+        _ => return None,
+    };
+    Some(info)
+}
+
+#[inline]
+fn descriptor_types_match(shader: vk::DescriptorType, layout: vk::DescriptorType) -> bool {
+    match shader {
+        vk::DescriptorType::UNIFORM_BUFFER => matches!(
+            layout,
+            vk::DescriptorType::UNIFORM_BUFFER | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+        ),
+        vk::DescriptorType::STORAGE_BUFFER => matches!(
+            layout,
+            vk::DescriptorType::STORAGE_BUFFER | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+        ),
+        _ => shader == layout,
+    }
+}
+
+#[inline]
+fn stage_name(stage: vk::ShaderStageFlags) -> &'static str {
+    match stage {
+        vk::ShaderStageFlags::VERTEX => "vertex",
+        vk::ShaderStageFlags::FRAGMENT => "fragment",
+        _ => "unknown",
+    }
+}
+
+fn describe_fields(fields: &[FieldLayout]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{} at {}", f.name, f.offset))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::descriptor_set_layout_bindings,
+        include_shader,
+        setup::push_constant_range,
+        utils::{
+            buffer_data::PushConst,
+            vertex::{Vertex, VertexLayout},
+        },
+    };
+
+    fn compiled_shaders() -> [Vec<u32>; 5] {
+        [
+            include_shader!("../.compiled_shaders/vert.spv"),
+            include_shader!("../.compiled_shaders/frag.spv"),
+            include_shader!("../.compiled_shaders/debug_vert.spv"),
+            include_shader!("../.compiled_shaders/overlay_vert.spv"),
+            include_shader!("../.compiled_shaders/overlay_frag.spv"),
+        ]
+    }
+
+    fn members(members: &[BlockMember]) -> Vec<(&str, u32, u32)> {
+        members
+            .iter()
+            .map(|member| (member.name.as_str(), member.offset, member.size))
+            .collect()
+    }
+
+    fn bindings(bindings: &[DescriptorBinding]) -> Vec<(&str, u32, vk::DescriptorType)> {
+        bindings
+            .iter()
+            .map(|binding| {
+                (
+                    binding.name.as_str(),
+                    binding.binding,
+                    binding.descriptor_type,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reflects_the_object_shaders() {
+        let vertex =
+            ShaderReflection::new(&include_shader!("../.compiled_shaders/vert.spv")).unwrap();
+        assert_eq!(vertex.stage, vk::ShaderStageFlags::VERTEX);
+        assert_eq!(
+            members(&vertex.push_constants),
+            [
+                ("sun_direction", 0, 12),
+                ("sun_color", 16, 12),
+                ("lighting", 28, 4),
+                ("tint", 32, 16),
+                ("outline_width", 48, 4),
+            ]
+        );
+        assert_eq!(
+            bindings(&vertex.descriptor_bindings),
+            [
+                ("world_view", 0, vk::DescriptorType::UNIFORM_BUFFER),
+                ("model", 1, vk::DescriptorType::UNIFORM_BUFFER),
+            ]
+        );
+        let inputs = vertex
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), input.location, input.components))
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, [("pos", 0, 3), ("color", 1, 3), ("normal", 2, 3)]);
+
+        let fragment =
+            ShaderReflection::new(&include_shader!("../.compiled_shaders/frag.spv")).unwrap();
+        assert_eq!(fragment.stage, vk::ShaderStageFlags::FRAGMENT);
+        assert!(fragment.push_constants.is_empty());
+        assert!(fragment.descriptor_bindings.is_empty());
+    }
+
+    #[test]
+    fn reflects_uniform_block_offsets() {
+        let module = Module::parse(&include_shader!("../.compiled_shaders/vert.spv")).unwrap();
+        let block = |name: &str| {
+            let (&id, _) = module
+                .names
+                .iter()
+                .find(|(_, block_name)| block_name.as_str() == name)
+                .unwrap();
+            module.block_members(id).unwrap()
+        };
+
+        assert_eq!(
+            members(&block("_view")),
+            [("view", 0, 64), ("projection", 64, 64)]
+        );
+        assert_eq!(
+            members(&block("_model")),
+            [
+                ("transform", 0, 64),
+                ("tint", 64, 16),
+                ("parameters", 80, 16),
+                ("flags", 96, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn reflects_the_overlay_shaders() {
+        let vertex =
+            ShaderReflection::new(&include_shader!("../.compiled_shaders/overlay_vert.spv"))
+                .unwrap();
+        assert!(vertex.push_constants.is_empty());
+        let inputs = vertex
+            .inputs
+            .iter()
+            .map(|input| (input.name.as_str(), input.location, input.components))
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, [("pos", 0, 2), ("uv", 1, 2), ("color", 2, 4)]);
+
+        let fragment =
+            ShaderReflection::new(&include_shader!("../.compiled_shaders/overlay_frag.spv"))
+                .unwrap();
+        assert_eq!(
+            bindings(&fragment.descriptor_bindings),
+            [
+                ("overlay_texture", 0, vk::DescriptorType::SAMPLED_IMAGE),
+                ("overlay_sampler", 1, vk::DescriptorType::SAMPLER),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_the_push_constant_range() {
+        let vertex =
+            ShaderReflection::new(&include_shader!("../.compiled_shaders/vert.spv")).unwrap();
+        let push_constants = PushConst::field_layouts();
+        let vertex_attributes = Vertex::attribute_descriptions(0);
+        let descriptor_bindings = descriptor_set_layout_bindings();
+        let mut interface = PipelineInterface {
+            push_constant_name: "PushConst",
+            push_constants: &push_constants,
+            push_constant_range: push_constant_range(),
+            vertex_binding: Vertex::binding_description(0),
+            vertex_attributes: &vertex_attributes,
+            descriptor_bindings: &descriptor_bindings,
+        };
+        vertex.validate(&interface).unwrap();
+
+        interface.push_constant_range.size = 48;
+        assert!(vertex.validate(&interface).is_err());
+
+        interface.push_constant_range = push_constant_range();
+        interface.push_constant_range.stage_flags = vk::ShaderStageFlags::FRAGMENT;
+        assert!(vertex.validate(&interface).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_modules() {
+        for code in compiled_shaders() {
+            for length in 0..code.len() {
+                //Cut between instructions the module is still well formed, but mustn't panic
+                let _ = ShaderReflection::new(&code[..length]);
+            }
+        }
+        assert!(ShaderReflection::new(&[]).is_err());
+        assert!(ShaderReflection::new(&[SPIRV_MAGIC, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn rejects_instructions_missing_operands() {
+        let header = [SPIRV_MAGIC, 0x0001_0000, 0, 100, 0];
+        for opcode in [
+            OP_NAME,
+            OP_MEMBER_NAME,
+            OP_ENTRY_POINT,
+            OP_DECORATE,
+            OP_MEMBER_DECORATE,
+            OP_TYPE_INT,
+            OP_TYPE_IMAGE,
+            OP_TYPE_POINTER,
+            OP_CONSTANT,
+            OP_VARIABLE,
+        ] {
+            let code = [&header[..], &[1 << 16 | opcode]].concat();
+            assert!(Module::parse(&code).is_err(), "opcode {opcode}");
+        }
+    }
+
+    #[test]
+    fn rejects_cyclic_types() {
+        let code = [
+            SPIRV_MAGIC,
+            0x0001_0000,
+            0,
+            100,
+            0,
+            4 << 16 | OP_TYPE_VECTOR,
+            1,
+            1,
+            4,
+        ];
+        assert!(Module::parse(&code).is_err());
+    }
+
+    #[test]
+    fn survives_corrupted_words() {
+        for code in compiled_shaders() {
+            for index in 5..code.len() {
+                for value in [
+                    0,
+                    u32::MAX,
+                    code[index] ^ 0x0001_0000,
+                    code[index] & 0xffff,
+                    1,
+                ] {
+                    let mut corrupted = code.clone();
+                    corrupted[index] = value;
+                    let _ = ShaderReflection::new(&corrupted);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use std::{ffi::c_void, ptr::null_mut};
+
+use ash::vk;
+
+use crate::{error::RendererError, renderer_error, vk_error};
+
+use super::buffer::find_memory_type_index;
+
+/// Size of the device memory blocks resources are carved out of.
+/// Resources bigger than this get a dedicated block.
+pub const MEMORY_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Buffers and linear images can't share a block with optimal images without
+/// respecting `bufferImageGranularity`, so they are kept in separate blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocationKind {
+    Linear,
+    Optimal,
+}
+
+/// A range of device memory owned by a buffer or an image.
+#[derive(Clone, Copy, Debug)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: u64,
+    pub size: u64,
+    /// Points to the start of the allocation if the memory is host visible, null otherwise.
+    pub mapped_ptr: *mut c_void,
+    block_index: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FreeRange {
+    offset: u64,
+    size: u64,
+}
+
+/// Offsets handed out in a block, kept apart from the device memory so it can be tested without a device.
+#[derive(Clone, Debug)]
+struct FreeList {
+    size: u64,
+    //Sorted by offset, neighbouring ranges are always merged.
+    free_ranges: Vec<FreeRange>,
+    allocation_count: usize,
+}
+
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    memory_type_index: u32,
+    kind: AllocationKind,
+    dedicated: bool,
+    mapped_ptr: *mut c_void,
+    free_list: FreeList,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllocatorStatistics {
+    pub block_count: usize,
+    pub allocation_count: usize,
+    /// Bytes of device memory allocated from the driver.
+    pub reserved_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
+    pub largest_free_range: u64,
+    /// 0 if all free memory is contiguous, approaches 1 as it gets split into small ranges.
+    pub fragmentation: f32,
+}
+
+/// Sub-allocates buffers and images from large device memory blocks,
+/// so we don't run into `maxMemoryAllocationCount`.
+pub struct MemoryAllocator {
+    memory_props: vk::PhysicalDeviceMemoryProperties,
+    non_coherent_atom_size: u64,
+    blocks: Vec<Option<MemoryBlock>>,
+}
+
+impl MemoryAllocator {
+    pub fn new(
+        memory_props: vk::PhysicalDeviceMemoryProperties,
+        device_props: vk::PhysicalDeviceProperties,
+    ) -> Self {
+        Self {
+            memory_props,
+            non_coherent_atom_size: device_props.limits.non_coherent_atom_size.max(1),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn allocate(
+        &mut self,
+        device: &ash::Device,
+        requirements: vk::MemoryRequirements,
+        properties: vk::MemoryPropertyFlags,
+        kind: AllocationKind,
+    ) -> Result<Allocation, RendererError> {
+        let memory_type_index =
+            find_memory_type_index(self.memory_props, requirements.memory_type_bits, properties)?;
+
+        let mut alignment = requirements.alignment.max(1);
+        let mut size = requirements.size;
+        //Flushing non coherent memory works on whole atoms, so allocations can't share one.
+        if self.is_non_coherent(memory_type_index) {
+            alignment = alignment.max(self.non_coherent_atom_size);
+            size = align_up(size, self.non_coherent_atom_size);
+        }
+
+        if size <= MEMORY_BLOCK_SIZE {
+            for (block_index, block) in self.blocks.iter_mut().enumerate() {
+                let block = match block {
+                    Some(block) => block,
+                    None => continue,
+                };
+                if !block.can_hold(memory_type_index, kind) {
+                    continue;
+                }
+                if let Some(offset) = block.free_list.allocate(size, alignment) {
+                    return Ok(block.allocation(block_index, offset, size));
+                }
+            }
+        }
+
+        let dedicated = size > MEMORY_BLOCK_SIZE;
+        let block_size = if dedicated { size } else { MEMORY_BLOCK_SIZE };
+        let mut block = self.create_block(device, block_size, memory_type_index, kind, dedicated)?;
+        let offset = block
+            .free_list
+            .allocate(size, alignment)
+            .ok_or_else(|| {
+                renderer_error!(
+                    "allocate",
+                    "{} bytes with alignment {} don't fit a new block",
+                    size,
+                    alignment
+                )
+            })?;
+
+        let block_index = match self.blocks.iter().position(|b| b.is_none()) {
+            Some(index) => index,
+            None => {
+                self.blocks.push(None);
+                self.blocks.len() - 1
+            }
+        };
+        let allocation = block.allocation(block_index, offset, size);
+        self.blocks[block_index] = Some(block);
+
+        Ok(allocation)
+    }
+
+    /// Returns the allocation's range to its block.
+    /// Blocks that become empty are released, except the last block of a memory type.
+    pub fn free(&mut self, device: &ash::Device, allocation: &Allocation) {
+        let block = match self.blocks.get_mut(allocation.block_index) {
+            Some(Some(block)) => block,
+            _ => return,
+        };
+
+        block.free_list.release(allocation.offset, allocation.size);
+
+        if block.free_list.allocation_count > 0 {
+            return;
+        }
+
+        let (memory_type_index, kind, dedicated) =
+            (block.memory_type_index, block.kind, block.dedicated);
+        let blocks_of_same_type = self
+            .blocks
+            .iter()
+            .flatten()
+            .filter(|b| b.can_hold(memory_type_index, kind))
+            .count();
+
+        if dedicated || blocks_of_same_type > 1 {
+            if let Some(block) = self.blocks[allocation.block_index].take() {
+                block.free(device);
+            }
+        }
+    }
+
+    pub fn statistics(&self) -> AllocatorStatistics {
+        AllocatorStatistics::of(self.blocks.iter().flatten().map(|block| &block.free_list))
+    }
+
+    /// Frees every block, all resources allocated from them have to be destroyed before.
+    pub fn clean_up(&mut self, device: &ash::Device) {
+        self.blocks.drain(..).flatten().for_each(|block| block.free(device));
+    }
+
+    fn create_block(
+        &self,
+        device: &ash::Device,
+        size: u64,
+        memory_type_index: u32,
+        kind: AllocationKind,
+        dedicated: bool,
+    ) -> Result<MemoryBlock, RendererError> {
+        let alloc_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index)
+            .build();
+
+        let memory = unsafe {
+            device
+                .allocate_memory(&alloc_info, None)
+                .map_err(vk_error!("allocate_memory"))?
+        };
+
+        //Host visible blocks stay mapped for their whole lifetime, a memory object can only be mapped once.
+        let mapped_ptr = if self.memory_props.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+        {
+            unsafe {
+                device
+                    .map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                    .map_err(|result| {
+                        device.free_memory(memory, None);
+                        vk_error!("map_memory")(result)
+                    })?
+            }
+        } else {
+            null_mut()
+        };
+
+        Ok(MemoryBlock {
+            memory,
+            memory_type_index,
+            kind,
+            dedicated,
+            mapped_ptr,
+            free_list: FreeList::new(size),
+        })
+    }
+
+    fn is_non_coherent(&self, memory_type_index: u32) -> bool {
+        let flags = self.memory_props.memory_types[memory_type_index as usize].property_flags;
+        flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+            && !flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+}
+
+impl AllocatorStatistics {
+    fn of<'a>(free_lists: impl Iterator<Item = &'a FreeList>) -> Self {
+        let mut statistics = Self::default();
+
+        for free_list in free_lists {
+            let free_bytes = free_list.free_ranges.iter().map(|r| r.size).sum::<u64>();
+
+            statistics.block_count += 1;
+            statistics.allocation_count += free_list.allocation_count;
+            statistics.reserved_bytes += free_list.size;
+            statistics.free_bytes += free_bytes;
+            statistics.used_bytes += free_list.size - free_bytes;
+            statistics.largest_free_range = free_list
+                .free_ranges
+                .iter()
+                .map(|r| r.size)
+                .fold(statistics.largest_free_range, u64::max);
+        }
+
+        if statistics.free_bytes > 0 {
+            statistics.fragmentation =
+                1. - statistics.largest_free_range as f32 / statistics.free_bytes as f32;
+        }
+
+        statistics
+    }
+}
+
+impl FreeList {
+    fn new(size: u64) -> Self {
+        Self {
+            size,
+            free_ranges: vec![FreeRange { offset: 0, size }],
+            allocation_count: 0,
+        }
+    }
+
+    /// First-fit search, returns the offset of the allocated range.
+    fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let (index, aligned_offset) = self.free_ranges.iter().enumerate().find_map(|(i, r)| {
+            let aligned_offset = align_up(r.offset, alignment);
+            (aligned_offset + size <= r.offset + r.size).then_some((i, aligned_offset))
+        })?;
+
+        let range = self.free_ranges.remove(index);
+        let range_end = range.offset + range.size;
+        let allocation_end = aligned_offset + size;
+
+        //Keeping the parts of the range that weren't used, padding first to stay sorted.
+        if allocation_end < range_end {
+            self.free_ranges.insert(
+                index,
+                FreeRange {
+                    offset: allocation_end,
+                    size: range_end - allocation_end,
+                },
+            );
+        }
+        if aligned_offset > range.offset {
+            self.free_ranges.insert(
+                index,
+                FreeRange {
+                    offset: range.offset,
+                    size: aligned_offset - range.offset,
+                },
+            );
+        }
+
+        self.allocation_count += 1;
+        Some(aligned_offset)
+    }
+
+    fn release(&mut self, offset: u64, size: u64) {
+        let index = self
+            .free_ranges
+            .iter()
+            .position(|r| r.offset > offset)
+            .unwrap_or(self.free_ranges.len());
+        self.free_ranges.insert(index, FreeRange { offset, size });
+
+        //Merging with the following range
+        if index + 1 < self.free_ranges.len()
+            && self.free_ranges[index].offset + self.free_ranges[index].size
+                == self.free_ranges[index + 1].offset
+        {
+            self.free_ranges[index].size += self.free_ranges.remove(index + 1).size;
+        }
+        //Merging with the previous range
+        if index > 0
+            && self.free_ranges[index - 1].offset + self.free_ranges[index - 1].size
+                == self.free_ranges[index].offset
+        {
+            self.free_ranges[index - 1].size += self.free_ranges.remove(index).size;
+        }
+
+        self.allocation_count -= 1;
+    }
+}
+
+impl MemoryBlock {
+    /// Returns *true* if an allocation of the memory type and kind can be carved out of the block.
+    /// Dedicated blocks only hold the resource they were created for.
+    fn can_hold(&self, memory_type_index: u32, kind: AllocationKind) -> bool {
+        !self.dedicated && self.memory_type_index == memory_type_index && self.kind == kind
+    }
+
+    fn allocation(&self, block_index: usize, offset: u64, size: u64) -> Allocation {
+        let mapped_ptr = if self.mapped_ptr.is_null() {
+            null_mut()
+        } else {
+            (self.mapped_ptr as usize + offset as usize) as *mut c_void
+        };
+
+        Allocation {
+            memory: self.memory,
+            offset,
+            size,
+            mapped_ptr,
+            block_index,
+        }
+    }
+
+    fn free(self, device: &ash::Device) {
+        unsafe {
+            if !self.mapped_ptr.is_null() {
+                device.unmap_memory(self.memory);
+            }
+            device.free_memory(self.memory, None);
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(offset: u64, size: u64) -> FreeRange {
+        FreeRange { offset, size }
+    }
+
+    #[test]
+    fn splits_the_first_range_that_fits() {
+        let mut free_list = FreeList::new(1024);
+        assert_eq!(free_list.allocate(256, 1), Some(0));
+        assert_eq!(free_list.allocate(256, 1), Some(256));
+        assert_eq!(free_list.free_ranges, [range(512, 512)]);
+        assert_eq!(free_list.allocate(1024, 1), None);
+
+        //The freed range at the start is reused before the end of the block
+        free_list.release(0, 256);
+        assert_eq!(free_list.allocate(128, 1), Some(0));
+        assert_eq!(free_list.free_ranges, [range(128, 128), range(512, 512)]);
+        assert_eq!(free_list.allocation_count, 2);
+    }
+
+    #[test]
+    fn keeps_the_alignment_padding_free() {
+        let mut free_list = FreeList::new(1024);
+        assert_eq!(free_list.allocate(100, 1), Some(0));
+        assert_eq!(free_list.allocate(100, 256), Some(256));
+        assert_eq!(free_list.free_ranges, [range(100, 156), range(356, 668)]);
+
+        //The padding is used by the allocations that fit in it
+        assert_eq!(free_list.allocate(150, 4), Some(100));
+        assert_eq!(free_list.free_ranges, [range(250, 6), range(356, 668)]);
+    }
+
+    #[test]
+    fn merges_released_neighbours() {
+        let mut free_list = FreeList::new(400);
+        let offsets: Vec<u64> = (0..4)
+            .map(|_| free_list.allocate(100, 1).unwrap())
+            .collect();
+        assert!(free_list.free_ranges.is_empty());
+
+        free_list.release(offsets[0], 100);
+        free_list.release(offsets[2], 100);
+        assert_eq!(free_list.free_ranges, [range(0, 100), range(200, 100)]);
+
+        //Merged with the previous and the following range at once
+        free_list.release(offsets[1], 100);
+        assert_eq!(free_list.free_ranges, [range(0, 300)]);
+
+        free_list.release(offsets[3], 100);
+        assert_eq!(free_list.free_ranges, [range(0, 400)]);
+        assert_eq!(free_list.allocation_count, 0);
+    }
+
+    #[test]
+    fn fragmentation_goes_back_to_zero() {
+        let mut free_list = FreeList::new(1000);
+        let offsets: Vec<u64> = (0..10)
+            .map(|_| free_list.allocate(100, 1).unwrap())
+            .collect();
+        for &offset in offsets.iter().step_by(2) {
+            free_list.release(offset, 100);
+        }
+
+        let statistics = AllocatorStatistics::of([&free_list].into_iter());
+        assert_eq!(statistics.allocation_count, 5);
+        assert_eq!(statistics.used_bytes, 500);
+        assert_eq!(statistics.largest_free_range, 100);
+        assert!((statistics.fragmentation - 0.8).abs() < 1e-6);
+
+        for &offset in offsets.iter().skip(1).step_by(2) {
+            free_list.release(offset, 100);
+        }
+        let statistics = AllocatorStatistics::of([&free_list].into_iter());
+        assert_eq!(statistics.allocation_count, 0);
+        assert_eq!(statistics.free_bytes, 1000);
+        assert_eq!(statistics.fragmentation, 0.);
+    }
+
+    #[test]
+    fn keeps_linear_and_optimal_blocks_apart() {
+        let block = |kind, dedicated| MemoryBlock {
+            memory: vk::DeviceMemory::null(),
+            memory_type_index: 1,
+            kind,
+            dedicated,
+            mapped_ptr: null_mut(),
+            free_list: FreeList::new(MEMORY_BLOCK_SIZE),
+        };
+        let linear = block(AllocationKind::Linear, false);
+        assert!(linear.can_hold(1, AllocationKind::Linear));
+        assert!(!linear.can_hold(1, AllocationKind::Optimal));
+        assert!(!linear.can_hold(0, AllocationKind::Linear));
+        assert!(block(AllocationKind::Optimal, false).can_hold(1, AllocationKind::Optimal));
+        assert!(!block(AllocationKind::Optimal, true).can_hold(1, AllocationKind::Optimal));
+    }
+}
@@ -2,17 +2,22 @@ use std::{ffi::c_void, mem::size_of, ptr::copy_nonoverlapping};
 
 use ash::vk::{self};
 
-use crate::parse_error;
+use crate::{error::RendererError, renderer_error, vk_error};
+
+use super::{
+    allocator::{Allocation, AllocationKind, MemoryAllocator},
+    upload::UploadQueue,
+};
 
 // #[derive(Copy, Clone, Debug)]
 pub struct Buffer {
-    pub mem: vk::DeviceMemory,
+    pub allocation: Allocation,
     pub buf: vk::Buffer,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct UniformBuffer {
-    pub mem: vk::DeviceMemory,
+    pub allocation: Allocation,
     pub buf: vk::Buffer,
     pub buffer_pointer: *mut c_void,
     pub size: u64,
@@ -21,7 +26,7 @@ pub struct UniformBuffer {
 
 #[derive(Copy, Clone, Debug)]
 pub struct DynamicUniformBuffer {
-    pub mem: vk::DeviceMemory,
+    pub allocation: Allocation,
     pub buf: vk::Buffer,
     pub buffer_pointer: *mut c_void,
     pub size: u64,
@@ -30,12 +35,11 @@ pub struct DynamicUniformBuffer {
 }
 
 impl UniformBuffer {
-    pub fn free(&self, device: &ash::Device) {
+    pub fn free(&self, device: &ash::Device, allocator: &mut MemoryAllocator) {
         unsafe {
             device.destroy_buffer(self.buf, None);
-            device.unmap_memory(self.mem);
-            device.free_memory(self.mem, None);
         }
+        allocator.free(device, &self.allocation);
     }
 
     #[inline]
@@ -70,21 +74,20 @@ impl UniformBuffer {
 }
 
 impl DynamicUniformBuffer {
-    pub fn free(&self, device: &ash::Device) {
+    pub fn free(&self, device: &ash::Device, allocator: &mut MemoryAllocator) {
         unsafe {
             device.destroy_buffer(self.buf, None);
-            device.unmap_memory(self.mem);
-            device.free_memory(self.mem, None);
         }
+        allocator.free(device, &self.allocation);
     }
 
     #[inline]
     pub fn update(&self, device: &ash::Device, descriptor_sets: &[vk::DescriptorSet]) {
         unsafe {
             let mem_range = vk::MappedMemoryRange::builder()
-                .memory(self.mem)
-                .offset(0)
-                .size(self.size * self.alignment as u64)
+                .memory(self.allocation.memory)
+                .offset(self.allocation.offset)
+                .size(self.allocation.size)
                 .build();
 
             let _ = device.flush_mapped_memory_ranges(&[mem_range]);
@@ -119,8 +122,8 @@ impl DynamicUniformBuffer {
     ) {
         unsafe {
             let mem_range = vk::MappedMemoryRange::builder()
-                .memory(self.mem)
-                .offset(mesh_index as u64 * self.alignment as u64)
+                .memory(self.allocation.memory)
+                .offset(self.allocation.offset + mesh_index as u64 * self.alignment as u64)
                 .size(self.alignment as u64)
                 .build();
 
@@ -152,35 +155,26 @@ impl Buffer {
     #[inline]
     pub fn uniform_buffer<T>(
         device: &ash::Device,
+        allocator: &mut MemoryAllocator,
         data: *const c_void,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
         binding: u32,
-    ) -> Result<UniformBuffer, String> {
+    ) -> Result<UniformBuffer, RendererError> {
         let uniform_buffer = Buffer::new(
             device,
+            allocator,
             size_of::<T>() as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
-            memory_props,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
         )?;
 
-        let buffer_pointer = unsafe {
-            device
-                .map_memory(
-                    uniform_buffer.mem,
-                    0,
-                    size_of::<T>() as u64,
-                    vk::MemoryMapFlags::empty(),
-                )
-                .map_err(|err| format!("{err}"))?
-        };
+        let buffer_pointer = uniform_buffer.allocation.mapped_ptr;
 
         unsafe {
             copy_nonoverlapping(data, buffer_pointer, size_of::<T>());
         }
 
         Ok(UniformBuffer {
-            mem: uniform_buffer.mem,
+            allocation: uniform_buffer.allocation,
             buf: uniform_buffer.buf,
             buffer_pointer,
             size: size_of::<T>() as u64,
@@ -190,11 +184,11 @@ impl Buffer {
 
     pub fn dynamic_uniform_buffer<T>(
         device: &ash::Device,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
+        allocator: &mut MemoryAllocator,
         device_props: vk::PhysicalDeviceProperties,
         size: usize,
         binding: u32,
-    ) -> Result<DynamicUniformBuffer, String> {
+    ) -> Result<DynamicUniformBuffer, RendererError> {
         let min_buffer_alignment = device_props.limits.min_uniform_buffer_offset_alignment as usize;
         let mut dynamic_alignment = size_of::<T>();
         if min_buffer_alignment > 0 {
@@ -204,81 +198,44 @@ impl Buffer {
 
         let uniform_buffer = Buffer::new(
             device,
+            allocator,
             dynamic_alignment as u64 * size as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
-            memory_props,
             vk::MemoryPropertyFlags::HOST_VISIBLE,
         )?;
 
-        let buffer_pointer = unsafe {
-            device
-                .map_memory(
-                    uniform_buffer.mem,
-                    0,
-                    dynamic_alignment as u64 * size as u64,
-                    vk::MemoryMapFlags::empty(),
-                )
-                .map_err(|err| format!("{err}"))?
-        };
-
         Ok(DynamicUniformBuffer {
-            mem: uniform_buffer.mem,
+            allocation: uniform_buffer.allocation,
             buf: uniform_buffer.buf,
             size: size as u64,
-            buffer_pointer,
+            buffer_pointer: uniform_buffer.allocation.mapped_ptr,
             binding,
             alignment: dynamic_alignment,
         })
     }
 
     #[inline]
+    /// Creates a device local buffer, and queues the upload of the data into it.
+    /// The data is copied to the staging buffer right away, it doesn't have to outlive the call.
     pub fn device_local(
         device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        upload_queue: &mut UploadQueue,
+        queue: vk::Queue,
         data: *const c_void,
         buffer_size: u64,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
         usage: vk::BufferUsageFlags,
-        queue: vk::Queue,
-        command_pool: vk::CommandPool,
-    ) -> Result<Self, String> {
-        let staging_buffer = Buffer::new(
-            device,
-            buffer_size,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-            memory_props,
-            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        )?;
-
-        unsafe {
-            let mapped_data = device
-                .map_memory(
-                    staging_buffer.mem,
-                    0,
-                    buffer_size,
-                    vk::MemoryMapFlags::empty(),
-                )
-                .map_err(|err| format!("{err}"))?;
-            copy_nonoverlapping(data, mapped_data, buffer_size as usize);
-            device.unmap_memory(staging_buffer.mem);
-        };
-
+    ) -> Result<Self, RendererError> {
         let device_local_buffer = Buffer::new(
             device,
+            allocator,
             buffer_size,
             vk::BufferUsageFlags::TRANSFER_DST | usage,
-            memory_props,
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
         )?;
 
-        staging_buffer.copy(
-            device,
-            &device_local_buffer,
-            buffer_size,
-            queue,
-            command_pool,
-        )?;
-
-        staging_buffer.free(device);
+        let data = unsafe { std::slice::from_raw_parts(data as *const u8, buffer_size as usize) };
+        upload_queue.upload_buffer(device, queue, data, device_local_buffer.buf, 0)?;
 
         Ok(device_local_buffer)
     }
@@ -286,11 +243,11 @@ impl Buffer {
     #[inline]
     pub fn new(
         device: &ash::Device,
+        allocator: &mut MemoryAllocator,
         buffer_size: u64,
         buffer_usage: vk::BufferUsageFlags,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
         buffer_props: vk::MemoryPropertyFlags,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, RendererError> {
         let buffer_info = vk::BufferCreateInfo::builder()
             .size(buffer_size)
             .usage(buffer_usage)
@@ -300,33 +257,28 @@ impl Buffer {
         let buffer = unsafe {
             device
                 .create_buffer(&buffer_info, None)
-                .map_err(|msg| format!("{}", msg))?
+                .map_err(vk_error!("create_buffer"))?
         };
 
         let mem_req = unsafe { device.get_buffer_memory_requirements(buffer) };
 
-        let mem_type_index =
-            find_memory_type_index(memory_props, mem_req.memory_type_bits, buffer_props)?;
-
-        let alloc_info = vk::MemoryAllocateInfo::builder()
-            .allocation_size(mem_req.size)
-            .memory_type_index(mem_type_index);
-
-        let memory = unsafe {
-            device
-                .allocate_memory(&alloc_info, None)
-                .map_err(|msg| format!("{}", msg))?
-        };
+        let allocation = allocator
+            .allocate(device, mem_req, buffer_props, AllocationKind::Linear)
+            .inspect_err(|_| unsafe { device.destroy_buffer(buffer, None) })?;
 
         unsafe {
             device
-                .bind_buffer_memory(buffer, memory, 0)
-                .map_err(|msg| format!("{}", msg))?
+                .bind_buffer_memory(buffer, allocation.memory, allocation.offset)
+                .map_err(vk_error!("bind_buffer_memory"))
+                .inspect_err(|_| {
+                    allocator.free(device, &allocation);
+                    device.destroy_buffer(buffer, None);
+                })?
         };
 
         Ok(Self {
             buf: buffer,
-            mem: memory,
+            allocation,
         })
     }
 
@@ -338,7 +290,7 @@ impl Buffer {
         buffer_size: u64,
         queue: vk::Queue,
         command_pool: vk::CommandPool,
-    ) -> Result<(), String> {
+    ) -> Result<(), RendererError> {
         let allocate_info = vk::CommandBufferAllocateInfo::builder()
             .command_pool(command_pool)
             .command_buffer_count(1)
@@ -348,7 +300,7 @@ impl Buffer {
         let command_buffer = unsafe {
             device
                 .allocate_command_buffers(&allocate_info)
-                .map_err(|msg| format!("{}", msg))?[0]
+                .map_err(vk_error!("allocate_command_buffers"))?[0]
         };
 
         let begin_info = vk::CommandBufferBeginInfo::builder()
@@ -368,7 +320,7 @@ impl Buffer {
         unsafe {
             device
                 .begin_command_buffer(command_buffer, &begin_info)
-                .map_err(|msg| format!("{}", msg))?;
+                .map_err(vk_error!("begin_command_buffer"))?;
 
             device.cmd_copy_buffer(
                 command_buffer,
@@ -379,14 +331,14 @@ impl Buffer {
 
             device
                 .end_command_buffer(command_buffer)
-                .map_err(|msg| format!("{}", msg))?;
+                .map_err(vk_error!("end_command_buffer"))?;
 
             device
                 .queue_submit(queue, &[submit_info], vk::Fence::null())
-                .map_err(|msg| format!("{}", msg))?;
+                .map_err(vk_error!("queue_submit"))?;
             device
                 .queue_wait_idle(queue)
-                .map_err(|msg| format!("{}", msg))?;
+                .map_err(vk_error!("queue_wait_idle"))?;
             device.free_command_buffers(command_pool, &[command_buffer]);
         }
 
@@ -394,11 +346,11 @@ impl Buffer {
     }
 
     #[inline]
-    pub fn free(&self, device: &ash::Device) {
+    pub fn free(&self, device: &ash::Device, allocator: &mut MemoryAllocator) {
         unsafe {
             device.destroy_buffer(self.buf, None);
-            device.free_memory(self.mem, None);
         }
+        allocator.free(device, &self.allocation);
     }
 }
 
@@ -406,7 +358,7 @@ pub fn find_memory_type_index(
     memory_props: vk::PhysicalDeviceMemoryProperties,
     allowed_types: u32,
     props: vk::MemoryPropertyFlags,
-) -> Result<u32, String> {
+) -> Result<u32, RendererError> {
     match memory_props
         .memory_types
         .iter()
@@ -417,6 +369,11 @@ pub fn find_memory_type_index(
         .map(|(i, _)| i as u32)
     {
         Some(ind) => Ok(ind),
-        None => Err(parse_error!("Failed to find suitable memory")),
+        None => Err(renderer_error!(
+            "find_memory_type_index",
+            "no memory type with {:?} in {:#b}",
+            props,
+            allowed_types
+        )),
     }
 }
@@ -1,6 +1,6 @@
 use ash::vk;
 
-use crate::utils::MAX_FRAME_DRAWS;
+use crate::{error::RendererError, utils::MAX_FRAME_DRAWS, vk_error};
 
 use super::buffer::{DynamicUniformBuffer, UniformBuffer};
 
@@ -17,7 +17,7 @@ pub fn create_descriptor_sets(
     device: &ash::Device,
     descriptor_pool: vk::DescriptorPool,
     layout: vk::DescriptorSetLayout,
-) -> Result<Vec<vk::DescriptorSet>, String> {
+) -> Result<Vec<vk::DescriptorSet>, RendererError> {
     let layouts = [layout; MAX_FRAME_DRAWS];
     let desc_alloc_info = vk::DescriptorSetAllocateInfo::builder()
         .descriptor_pool(descriptor_pool)
@@ -26,7 +26,7 @@ pub fn create_descriptor_sets(
     let descriptor_sets = unsafe {
         device
             .allocate_descriptor_sets(&desc_alloc_info)
-            .map_err(|err| format!("{err}"))?
+            .map_err(vk_error!("allocate_descriptor_sets"))?
     };
 
     Ok(descriptor_sets)
@@ -35,7 +35,7 @@ pub fn create_descriptor_sets(
 pub fn create_descriptor_set_layout(
     device: &ash::Device,
     layout_bindings: &[vk::DescriptorSetLayoutBinding]
-) -> Result<vk::DescriptorSetLayout, String> {
+) -> Result<vk::DescriptorSetLayout, RendererError> {
 
 
     let layout_create_info = vk::DescriptorSetLayoutCreateInfo::builder()
@@ -45,22 +45,22 @@ pub fn create_descriptor_set_layout(
     let layout = unsafe {
         device
             .create_descriptor_set_layout(&layout_create_info, None)
-            .map_err(|err| format!("{err}"))?
+            .map_err(vk_error!("create_descriptor_set_layout"))?
     };
 
     Ok(layout)
 }
 
-pub fn create_descriptor_pool(device: &ash::Device, pool_sizes: &[vk::DescriptorPoolSize]) -> Result<vk::DescriptorPool, String> {
+pub fn create_descriptor_pool(device: &ash::Device, pool_sizes: &[vk::DescriptorPoolSize], max_sets: u32) -> Result<vk::DescriptorPool, RendererError> {
     let create_info = vk::DescriptorPoolCreateInfo::builder()
-        .max_sets(MAX_FRAME_DRAWS as u32)
+        .max_sets(max_sets)
         .pool_sizes(&pool_sizes)
         .build();
 
     let descriptor_pool = unsafe {
         device
             .create_descriptor_pool(&create_info, None)
-            .map_err(|err| format!("{err}"))?
+            .map_err(vk_error!("create_descriptor_pool"))?
     };
 
     Ok(descriptor_pool)
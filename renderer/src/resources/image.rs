@@ -1,63 +1,61 @@
 use ash::vk;
 
-use crate::parse_error;
+use crate::{error::RendererError, vk_error};
 
-use super::buffer::find_memory_type_index;
+use super::allocator::{Allocation, AllocationKind, MemoryAllocator};
 
 pub struct Image {
     pub img: vk::Image,
-    pub mem: vk::DeviceMemory,
+    pub allocation: Allocation,
     pub view: vk::ImageView,
 }
 
 impl Image {
     pub fn new(
         device: &ash::Device,
+        allocator: &mut MemoryAllocator,
         extent: vk::Extent3D,
         format: vk::Format,
         tiling: vk::ImageTiling,
         usage: vk::ImageUsageFlags,
         aspect_mask: vk::ImageAspectFlags,
         properties: vk::MemoryPropertyFlags,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, RendererError> {
         let img = Self::create_image(device, extent, format, tiling, usage)?;
-        let mem = Self::create_mem(device, properties, memory_props, img)?;
+        let allocation = Self::create_mem(device, allocator, properties, img, tiling)?;
         let view = Self::create_image_view(device, img, format, aspect_mask)?;
 
-        Ok(Self { img, mem, view })
+        Ok(Self {
+            img,
+            allocation,
+            view,
+        })
     }
 
     #[inline]
     pub fn create_mem(
         device: &ash::Device,
+        allocator: &mut MemoryAllocator,
         properties: vk::MemoryPropertyFlags,
-        memory_props: vk::PhysicalDeviceMemoryProperties,
         img: vk::Image,
-    ) -> Result<vk::DeviceMemory, String> {
+        tiling: vk::ImageTiling,
+    ) -> Result<Allocation, RendererError> {
         let mem_reqs = unsafe { device.get_image_memory_requirements(img) };
 
-        let mem_type_index =
-            find_memory_type_index(memory_props, mem_reqs.memory_type_bits, properties)?;
-
-        let alloc_info = vk::MemoryAllocateInfo::builder()
-            .allocation_size(mem_reqs.size)
-            .memory_type_index(mem_type_index)
-            .build();
-
-        let mem = unsafe {
-            let mem = device
-                .allocate_memory(&alloc_info, None)
-                .map_err(|err| parse_error!(err))?;
+        let kind = match tiling {
+            vk::ImageTiling::LINEAR => AllocationKind::Linear,
+            _ => AllocationKind::Optimal,
+        };
+        let allocation = allocator.allocate(device, mem_reqs, properties, kind)?;
 
+        unsafe {
             device
-                .bind_image_memory(img, mem, 0)
-                .map_err(|err| parse_error!(err))?;
-
-            mem
-        };
+                .bind_image_memory(img, allocation.memory, allocation.offset)
+                .map_err(vk_error!("bind_image_memory"))
+                .inspect_err(|_| allocator.free(device, &allocation))?;
+        }
 
-        Ok(mem)
+        Ok(allocation)
     }
 
     #[inline]
@@ -67,7 +65,7 @@ impl Image {
         format: vk::Format,
         tiling: vk::ImageTiling,
         usage: vk::ImageUsageFlags,
-    ) -> Result<vk::Image, String> {
+    ) -> Result<vk::Image, RendererError> {
         let create_info = vk::ImageCreateInfo::builder()
             .extent(extent)
             .image_type(vk::ImageType::TYPE_2D)
@@ -84,7 +82,7 @@ impl Image {
         let img = unsafe {
             device
                 .create_image(&create_info, None)
-                .map_err(|err| parse_error!(err))?
+                .map_err(vk_error!("create_image"))?
         };
 
         Ok(img)
@@ -96,7 +94,7 @@ impl Image {
         image: vk::Image,
         format: vk::Format,
         aspect_mask: vk::ImageAspectFlags,
-    ) -> Result<vk::ImageView, String> {
+    ) -> Result<vk::ImageView, RendererError> {
         let create_info = vk::ImageViewCreateInfo::builder()
             .image(image)
             .view_type(vk::ImageViewType::TYPE_2D)
@@ -119,17 +117,17 @@ impl Image {
         let img_view = unsafe {
             device
                 .create_image_view(&create_info, None)
-                .map_err(|err| parse_error!(err))?
+                .map_err(vk_error!("create_image_view"))?
         };
 
         Ok(img_view)
     }
 
-    pub fn free(&self, device: &ash::Device) {
+    pub fn free(&self, device: &ash::Device, allocator: &mut MemoryAllocator) {
         unsafe {
             device.destroy_image(self.img, None);
             device.destroy_image_view(self.view, None);
-            device.free_memory(self.mem, None);
         }
+        allocator.free(device, &self.allocation);
     }
 }
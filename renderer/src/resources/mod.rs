@@ -1,10 +1,14 @@
+pub mod allocator;
 pub mod buffer;
 pub mod desriptors;
 pub mod image;
 pub mod texture;
+pub mod upload;
 
 use ash::vk;
 
+use crate::{error::RendererError, vk_error};
+
 use super::utils::MAX_FRAME_DRAWS;
 
 pub fn create_framebuffers(
@@ -13,7 +17,7 @@ pub fn create_framebuffers(
     render_pass: vk::RenderPass,
     framebuffer_extent: vk::Extent2D,
     depth_img_view: vk::ImageView,
-) -> Result<Vec<vk::Framebuffer>, String> {
+) -> Result<Vec<vk::Framebuffer>, RendererError> {
     let mut framebuffers = Vec::with_capacity(swapchain_image_views.len());
 
     for (i, &view) in swapchain_image_views.iter().enumerate() {
@@ -28,11 +32,11 @@ pub fn create_framebuffers(
             .build();
 
         let framebuffer = unsafe {
-            device.create_framebuffer(&create_info, None).map_err(|_| {
+            device.create_framebuffer(&create_info, None).map_err(|result| {
                 for &fb in &framebuffers {
                     device.destroy_framebuffer(fb, None);
                 }
-                format!("failed to create framebuffer {}", i)
+                vk_error!("create_framebuffer", "framebuffer {}", i)(result)
             })?
         };
 
@@ -45,7 +49,7 @@ pub fn create_framebuffers(
 pub fn create_semaphore(
     device: &ash::Device,
     object_name: &str,
-) -> Result<Vec<vk::Semaphore>, String> {
+) -> Result<Vec<vk::Semaphore>, RendererError> {
     let mut semaphores = Vec::<vk::Semaphore>::with_capacity(MAX_FRAME_DRAWS);
 
     for _ in 0..MAX_FRAME_DRAWS {
@@ -54,7 +58,7 @@ pub fn create_semaphore(
         let semaphore = unsafe {
             device
                 .create_semaphore(&create_info, None)
-                .map_err(|_| format!("failed to create {}", object_name))?
+                .map_err(vk_error!("create_semaphore", "{}", object_name))?
         };
 
         semaphores.push(semaphore);
@@ -63,7 +67,7 @@ pub fn create_semaphore(
     Ok(semaphores)
 }
 
-pub fn create_fences(device: &ash::Device) -> Result<Vec<vk::Fence>, String> {
+pub fn create_fences(device: &ash::Device) -> Result<Vec<vk::Fence>, RendererError> {
     let create_info = vk::FenceCreateInfo::builder()
         .flags(vk::FenceCreateFlags::SIGNALED)
         .build();
@@ -72,12 +76,12 @@ pub fn create_fences(device: &ash::Device) -> Result<Vec<vk::Fence>, String> {
 
     for i in 0..MAX_FRAME_DRAWS {
         let fence = unsafe {
-            device.create_fence(&create_info, None).map_err(|_| {
+            device.create_fence(&create_info, None).map_err(|result| {
                 for &f in &fences {
                     device.destroy_fence(f, None);
                 }
 
-                format!("failed to create fence {}", i)
+                vk_error!("create_fence", "fence {}", i)(result)
             })?
         };
 
@@ -93,7 +97,7 @@ pub fn end_and_submit_command_buffer(
     command_pool: vk::CommandPool,
     command_buffer: vk::CommandBuffer,
     queue: vk::Queue,
-) -> Result<(), String> {
+) -> Result<(), RendererError> {
     let submit_info = vk::SubmitInfo::builder()
         .command_buffers(&[command_buffer])
         .build();
@@ -101,14 +105,14 @@ pub fn end_and_submit_command_buffer(
     unsafe {
         device
             .end_command_buffer(command_buffer)
-            .map_err(|msg| format!("{}", msg))?;
+            .map_err(vk_error!("end_command_buffer"))?;
 
         device
             .queue_submit(queue, &[submit_info], vk::Fence::null())
-            .map_err(|msg| format!("{}", msg))?;
+            .map_err(vk_error!("queue_submit"))?;
         device
             .queue_wait_idle(queue)
-            .map_err(|msg| format!("{}", msg))?;
+            .map_err(vk_error!("queue_wait_idle"))?;
         device.free_command_buffers(command_pool, &[command_buffer]);
     }
 
@@ -119,7 +123,7 @@ pub fn end_and_submit_command_buffer(
 pub fn create_and_begin_command_buffer(
     device: &ash::Device,
     command_pool: vk::CommandPool,
-) -> Result<vk::CommandBuffer, String> {
+) -> Result<vk::CommandBuffer, RendererError> {
     let allocate_info = vk::CommandBufferAllocateInfo::builder()
         .command_pool(command_pool)
         .command_buffer_count(1)
@@ -129,7 +133,7 @@ pub fn create_and_begin_command_buffer(
     let command_buffer = unsafe {
         device
             .allocate_command_buffers(&allocate_info)
-            .map_err(|msg| format!("{}", msg))?[0]
+            .map_err(vk_error!("allocate_command_buffers"))?[0]
     };
 
     let begin_info = vk::CommandBufferBeginInfo::builder()
@@ -139,7 +143,7 @@ pub fn create_and_begin_command_buffer(
     unsafe {
         device
             .begin_command_buffer(command_buffer, &begin_info)
-            .map_err(|msg| format!("{}", msg))?;
+            .map_err(vk_error!("begin_command_buffer"))?;
     }
 
     Ok(command_buffer)
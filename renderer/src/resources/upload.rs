@@ -0,0 +1,501 @@
+use std::{collections::VecDeque, ptr::copy_nonoverlapping};
+
+use ash::vk;
+
+use crate::{error::RendererError, renderer_error, setup::create_command_pool, vk_error};
+
+use super::{
+    allocator::{align_up, MemoryAllocator},
+    buffer::Buffer,
+};
+
+/// Size of the ring buffer every upload is staged through.
+/// Buffers bigger than this are uploaded in chunks, images have to fit.
+pub const STAGING_BUFFER_SIZE: u64 = 32 * 1024 * 1024;
+
+//Satisfies both the buffer copy and the buffer-image copy offset requirements.
+const STAGING_ALIGNMENT: u64 = 16;
+
+/// Identifies the batch an upload was recorded into.
+/// Batches complete in order, so every id below a completed one is also done.
+pub type UploadId = u64;
+
+struct UploadBatch {
+    id: UploadId,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    //Ring position right after the last byte this batch stages
+    ring_end: u64,
+}
+
+/// Accumulates buffer and image uploads into a single staging ring buffer,
+/// and submits them together, so loading meshes doesn't stall on every copy.
+pub struct UploadQueue {
+    staging_buffer: Buffer,
+    command_pool: vk::CommandPool,
+
+    head: u64,
+    tail: u64,
+
+    recording: Option<(vk::CommandBuffer, vk::Fence)>,
+    recorded_copies: usize,
+    pending: VecDeque<UploadBatch>,
+    free_batches: Vec<(vk::CommandBuffer, vk::Fence)>,
+
+    next_id: UploadId,
+    completed_id: Option<UploadId>,
+}
+
+impl UploadQueue {
+    pub fn new(
+        device: &ash::Device,
+        allocator: &mut MemoryAllocator,
+        queue_family: u32,
+    ) -> Result<Self, RendererError> {
+        let staging_buffer = Buffer::new(
+            device,
+            allocator,
+            STAGING_BUFFER_SIZE,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        let command_pool = create_command_pool(device, queue_family)?;
+
+        Ok(Self {
+            staging_buffer,
+            command_pool,
+            head: 0,
+            tail: 0,
+            recording: None,
+            recorded_copies: 0,
+            pending: VecDeque::new(),
+            free_batches: Vec::new(),
+            next_id: 0,
+            completed_id: None,
+        })
+    }
+
+    /// Stages the data and records a copy into `dst_buffer` at `dst_offset`.
+    /// The copy happens once the batch is submitted.
+    pub fn upload_buffer(
+        &mut self,
+        device: &ash::Device,
+        queue: vk::Queue,
+        data: &[u8],
+        dst_buffer: vk::Buffer,
+        dst_offset: u64,
+    ) -> Result<UploadId, RendererError> {
+        //Halving the chunk size so a chunk can still fit when the ring wraps around.
+        let chunk_size = (STAGING_BUFFER_SIZE / 2) as usize;
+
+        for (chunk_index, chunk) in data.chunks(chunk_size).enumerate() {
+            let staging_offset = self.stage(device, queue, chunk)?;
+            let command_buffer = self.command_buffer(device)?;
+
+            let copy_region = vk::BufferCopy::builder()
+                .src_offset(staging_offset)
+                .dst_offset(dst_offset + (chunk_index * chunk_size) as u64)
+                .size(chunk.len() as u64)
+                .build();
+
+            unsafe {
+                device.cmd_copy_buffer(
+                    command_buffer,
+                    self.staging_buffer.buf,
+                    dst_buffer,
+                    &[copy_region],
+                );
+            }
+        }
+
+        Ok(self.next_id)
+    }
+
+    /// Stages tightly packed RGBA8 pixels and records the copy into `image`,
+    /// leaving it in `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn upload_image(
+        &mut self,
+        device: &ash::Device,
+        queue: vk::Queue,
+        data: &[u8],
+        image: vk::Image,
+        extent: vk::Extent2D,
+    ) -> Result<UploadId, RendererError> {
+        check_rgba_size(data, extent)?;
+        if data.len() as u64 > STAGING_BUFFER_SIZE / 2 {
+            return Err(renderer_error!(
+                "upload_image",
+                "{} bytes don't fit in the staging buffer",
+                data.len()
+            ));
+        }
+
+        let staging_offset = self.stage(device, queue, data)?;
+        let command_buffer = self.command_buffer(device)?;
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            level_count: 1,
+            layer_count: 1,
+            ..Default::default()
+        };
+
+        let transfer_barrier = vk::ImageMemoryBarrier {
+            dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+
+        let copy_region = vk::BufferImageCopy::builder()
+            .buffer_offset(staging_offset)
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1)
+                    .build(),
+            )
+            .image_extent(extent.into())
+            .build();
+
+        let shader_read_barrier = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            image,
+            subresource_range,
+            ..Default::default()
+        };
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[transfer_barrier],
+            );
+
+            device.cmd_copy_buffer_to_image(
+                command_buffer,
+                self.staging_buffer.buf,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[shader_read_barrier],
+            );
+        }
+
+        Ok(self.next_id)
+    }
+
+    /// Submits every upload recorded since the last submit.
+    /// Returns the id of the submitted batch, or *None* if there was nothing to submit.
+    pub fn submit(
+        &mut self,
+        device: &ash::Device,
+        queue: vk::Queue,
+    ) -> Result<Option<UploadId>, RendererError> {
+        let (command_buffer, fence) = match self.recording.take() {
+            Some(recording) => recording,
+            None => return Ok(None),
+        };
+
+        //Later submissions on the queue (rendering) have to see the uploaded data.
+        let memory_barrier = vk::MemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                | vk::AccessFlags::INDEX_READ
+                | vk::AccessFlags::UNIFORM_READ
+                | vk::AccessFlags::SHADER_READ,
+            ..Default::default()
+        };
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::VERTEX_INPUT
+                    | vk::PipelineStageFlags::VERTEX_SHADER
+                    | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+
+            device
+                .end_command_buffer(command_buffer)
+                .map_err(vk_error!("end_command_buffer"))?;
+
+            let command_buffers = [command_buffer];
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(&command_buffers)
+                .build();
+
+            device
+                .queue_submit(queue, &[submit_info], fence)
+                .map_err(vk_error!("queue_submit"))?;
+        }
+
+        let id = self.next_id;
+        self.pending.push_back(UploadBatch {
+            id,
+            command_buffer,
+            fence,
+            ring_end: self.head,
+        });
+        self.next_id += 1;
+        self.recorded_copies = 0;
+
+        Ok(Some(id))
+    }
+
+    /// Retires every finished batch, freeing up their part of the staging buffer.
+    pub fn poll(&mut self, device: &ash::Device) -> Result<(), RendererError> {
+        while let Some(batch) = self.pending.front() {
+            let finished = unsafe {
+                device
+                    .get_fence_status(batch.fence)
+                    .map_err(vk_error!("get_fence_status"))?
+            };
+
+            if !finished {
+                break;
+            }
+
+            self.retire_oldest(device)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Checks if the batch with the id has finished, call *poll()* to update.
+    pub fn is_complete(&self, id: UploadId) -> bool {
+        self.completed_id.is_some_and(|completed| id <= completed)
+    }
+
+    #[inline]
+    /// Checks if there are uploads that were not submitted yet.
+    pub fn has_recorded_uploads(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Submits the recorded uploads, and blocks until every batch is finished.
+    pub fn wait_idle(&mut self, device: &ash::Device, queue: vk::Queue) -> Result<(), RendererError> {
+        self.submit(device, queue)?;
+
+        while !self.pending.is_empty() {
+            self.wait_oldest(device)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn clean_up(&mut self, device: &ash::Device, allocator: &mut MemoryAllocator) {
+        unsafe {
+            self.pending
+                .drain(..)
+                .map(|batch| (batch.command_buffer, batch.fence))
+                .chain(self.recording.take())
+                .chain(self.free_batches.drain(..))
+                .for_each(|(_, fence)| device.destroy_fence(fence, None));
+
+            device.destroy_command_pool(self.command_pool, None);
+        }
+
+        self.staging_buffer.free(device, allocator);
+    }
+
+    /// Copies the data into the ring buffer, returns the offset it was copied to.
+    /// Submits and waits for older batches if the ring buffer is full.
+    fn stage(&mut self, device: &ash::Device, queue: vk::Queue, data: &[u8]) -> Result<u64, RendererError> {
+        let size = data.len() as u64;
+
+        let offset = loop {
+            if let Some(offset) = self.reserve(size) {
+                break offset;
+            }
+
+            if self.recorded_copies > 0 {
+                self.submit(device, queue)?;
+            }
+
+            if self.pending.is_empty() {
+                return Err(renderer_error!(
+                    "stage",
+                    "{} bytes don't fit in the staging buffer",
+                    data.len()
+                ));
+            }
+
+            self.wait_oldest(device)?;
+        };
+
+        unsafe {
+            copy_nonoverlapping(
+                data.as_ptr(),
+                (self.staging_buffer.allocation.mapped_ptr as *mut u8).add(offset as usize),
+                data.len(),
+            );
+        }
+
+        self.recorded_copies += 1;
+        Ok(offset)
+    }
+
+    fn reserve(&mut self, size: u64) -> Option<u64> {
+        if self.pending.is_empty() && self.recorded_copies == 0 {
+            self.head = 0;
+            self.tail = 0;
+        }
+
+        let aligned_head = align_up(self.head, STAGING_ALIGNMENT);
+
+        //Free space is split into [head, end) and [0, tail)
+        if self.head >= self.tail {
+            if aligned_head + size <= STAGING_BUFFER_SIZE {
+                self.head = aligned_head + size;
+                return Some(aligned_head);
+            }
+            //Wrapping around, head can't catch up with tail, that would mean an empty ring.
+            if size < self.tail {
+                self.head = size;
+                return Some(0);
+            }
+            return None;
+        }
+
+        //Free space is [head, tail)
+        if aligned_head + size < self.tail {
+            self.head = aligned_head + size;
+            return Some(aligned_head);
+        }
+
+        None
+    }
+
+    fn command_buffer(&mut self, device: &ash::Device) -> Result<vk::CommandBuffer, RendererError> {
+        if let Some((command_buffer, _)) = self.recording {
+            return Ok(command_buffer);
+        }
+
+        let (command_buffer, fence) = match self.free_batches.pop() {
+            Some(batch) => batch,
+            None => unsafe {
+                let allocate_info = vk::CommandBufferAllocateInfo::builder()
+                    .command_pool(self.command_pool)
+                    .command_buffer_count(1)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .build();
+
+                let command_buffer = device
+                    .allocate_command_buffers(&allocate_info)
+                    .map_err(vk_error!("allocate_command_buffers"))?[0];
+                let fence = device
+                    .create_fence(&vk::FenceCreateInfo::default(), None)
+                    .map_err(vk_error!("create_fence"))?;
+
+                (command_buffer, fence)
+            },
+        };
+
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+            .build();
+
+        unsafe {
+            device
+                .begin_command_buffer(command_buffer, &begin_info)
+                .map_err(vk_error!("begin_command_buffer"))?;
+        }
+
+        self.recording = Some((command_buffer, fence));
+        Ok(command_buffer)
+    }
+
+    fn wait_oldest(&mut self, device: &ash::Device) -> Result<(), RendererError> {
+        if let Some(batch) = self.pending.front() {
+            unsafe {
+                device
+                    .wait_for_fences(&[batch.fence], true, u64::MAX)
+                    .map_err(vk_error!("wait_for_fences"))?;
+            }
+            self.retire_oldest(device)?;
+        }
+
+        Ok(())
+    }
+
+    fn retire_oldest(&mut self, device: &ash::Device) -> Result<(), RendererError> {
+        if let Some(batch) = self.pending.pop_front() {
+            unsafe {
+                device
+                    .reset_fences(&[batch.fence])
+                    .map_err(vk_error!("reset_fences"))?;
+                device
+                    .reset_command_buffer(
+                        batch.command_buffer,
+                        vk::CommandBufferResetFlags::default(),
+                    )
+                    .map_err(vk_error!("reset_command_buffer"))?;
+            }
+
+            self.tail = batch.ring_end;
+            self.completed_id = Some(batch.id);
+            self.free_batches.push((batch.command_buffer, batch.fence));
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors if the pixels aren't the 4 bytes per pixel of an RGBA8 image of the extent,
+/// a short buffer would have the copy read past the staged data.
+pub(crate) fn check_rgba_size(pixels: &[u8], extent: vk::Extent2D) -> Result<(), RendererError> {
+    let expected = extent.width as u64 * extent.height as u64 * 4;
+    if pixels.len() as u64 != expected {
+        return Err(renderer_error!(
+            "check_rgba_size",
+            "{} bytes of pixels for a {}x{} image, expected {}",
+            pixels.len(),
+            extent.width,
+            extent.height,
+            expected
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_mismatched_pixel_sizes() {
+        let extent = vk::Extent2D {
+            width: 4,
+            height: 2,
+        };
+        assert!(check_rgba_size(&[0; 32], extent).is_ok());
+        assert!(check_rgba_size(&[0; 31], extent).is_err());
+        assert!(check_rgba_size(&[0; 8], extent).is_err());
+        assert!(check_rgba_size(&[0; 36], extent).is_err());
+    }
+}
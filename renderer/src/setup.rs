@@ -5,16 +5,19 @@ use ash::extensions::{ext, khr};
 use ash::vk::{self, PresentModeKHR};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 
+use crate::material::{BlendMode, MaterialCreateInfo, StencilMode};
+use crate::reflection::{PipelineInterface, ShaderReflection};
 use crate::utils::buffer_data::PushConst;
-use crate::utils::vertex::Vertex;
-use crate::{offset_of, parse_error};
+use crate::utils::vertex::{Vertex, VertexLayout};
+use crate::error::RendererError;
+use crate::{renderer_error, vk_error};
 
 use super::utils::vulkan_debug_callback;
 
 pub fn create_command_pool(
     device: &ash::Device,
     queue_family: u32,
-) -> Result<vk::CommandPool, String> {
+) -> Result<vk::CommandPool, RendererError> {
     let create_info = vk::CommandPoolCreateInfo::builder()
         .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
         .queue_family_index(queue_family);
@@ -22,20 +25,21 @@ pub fn create_command_pool(
     let command_pool = unsafe {
         device
             .create_command_pool(&create_info, None)
-            .map_err(|err| parse_error!(err))?
+            .map_err(vk_error!("create_command_pool"))?
     };
 
     Ok(command_pool)
 }
 
-pub fn create_pipelines(
+/// Creates the pipeline of a material, `V` is the vertex type of its meshes.
+pub fn create_pipelines<V: VertexLayout>(
     device: &ash::Device,
     vertex_shader_module: vk::ShaderModule,
     fragment_shader_module: vk::ShaderModule,
     pipeline_layout: vk::PipelineLayout,
     render_pass: vk::RenderPass,
-    mode: vk::PolygonMode,
-) -> Result<vk::Pipeline, String> {
+    material: &MaterialCreateInfo,
+) -> Result<vk::Pipeline, RendererError> {
     let shader_entry_name = std::ffi::CString::new("main").unwrap();
 
     let vs_state = vk::PipelineShaderStageCreateInfo::builder()
@@ -51,34 +55,81 @@ pub fn create_pipelines(
         .build();
 
     let ia_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+        .topology(material.topology)
         .build();
 
     let raster_state = vk::PipelineRasterizationStateCreateInfo::builder()
-        .polygon_mode(mode)
-        .cull_mode(vk::CullModeFlags::NONE)
+        .polygon_mode(material.polygon_mode)
+        .cull_mode(material.cull_mode)
         .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
         .line_width(1.0f32)
         .build();
 
-    let col_blend_attachment_state = vk::PipelineColorBlendAttachmentState::builder()
-        .blend_enable(false)
-        .color_write_mask(
+    let color_write_mask = match material.stencil_mode {
+        StencilMode::Mask => vk::ColorComponentFlags::empty(),
+        _ => {
             vk::ColorComponentFlags::R
                 | vk::ColorComponentFlags::G
                 | vk::ColorComponentFlags::B
-                | vk::ColorComponentFlags::A,
-        )
-        .build();
+                | vk::ColorComponentFlags::A
+        }
+    };
+
+    let col_blend_attachment_state = match material.blend_mode {
+        BlendMode::Opaque => vk::PipelineColorBlendAttachmentState::builder().blend_enable(false),
+        BlendMode::Alpha => vk::PipelineColorBlendAttachmentState::builder()
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD),
+        BlendMode::Additive => vk::PipelineColorBlendAttachmentState::builder()
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+            .alpha_blend_op(vk::BlendOp::ADD),
+    }
+    .color_write_mask(color_write_mask)
+    .build();
+
+    //Selected meshes write 1, their outline is only drawn where the value is not 1
+    let stencil_op_state = match material.stencil_mode {
+        StencilMode::Disabled => vk::StencilOpState::default(),
+        StencilMode::Mask => vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::REPLACE,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: vk::CompareOp::ALWAYS,
+            compare_mask: 1,
+            write_mask: 1,
+            reference: 1,
+        },
+        StencilMode::Outline => vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::KEEP,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: vk::CompareOp::NOT_EQUAL,
+            compare_mask: 1,
+            write_mask: 0,
+            reference: 1,
+        },
+    };
 
     let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
-        .depth_test_enable(true)
-        .depth_write_enable(true)
+        .depth_test_enable(material.depth_test)
+        .depth_write_enable(material.depth_write)
         .depth_compare_op(vk::CompareOp::LESS)
         .depth_bounds_test_enable(false)
         .min_depth_bounds(0.)
         .max_depth_bounds(1.)
-        .stencil_test_enable(false)
+        .stencil_test_enable(material.stencil_mode != StencilMode::Disabled)
+        .front(stencil_op_state)
+        .back(stencil_op_state)
         .build();
 
     let attachments = [col_blend_attachment_state];
@@ -107,32 +158,8 @@ pub fn create_pipelines(
 
     let dynamic_state_info = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&states);
 
-    let vertex_input_binding_descriptions = [vk::VertexInputBindingDescription {
-        binding: 0,
-        stride: size_of::<Vertex>() as u32,
-        input_rate: vk::VertexInputRate::VERTEX,
-    }];
-
-    let vertex_input_attribute_descriptions = [
-        vk::VertexInputAttributeDescription {
-            location: 0,
-            binding: 0,
-            format: vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, pos) as u32,
-        },
-        vk::VertexInputAttributeDescription {
-            location: 1,
-            binding: 0,
-            format: vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, color) as u32,
-        },
-        vk::VertexInputAttributeDescription {
-            location: 2,
-            binding: 0,
-            format: vk::Format::R32G32B32_SFLOAT,
-            offset: offset_of!(Vertex, normal) as u32,
-        },
-    ];
+    let vertex_input_binding_descriptions = [V::binding_description(0)];
+    let vertex_input_attribute_descriptions = V::attribute_descriptions(0);
 
     let vert_inp_state = vk::PipelineVertexInputStateCreateInfo::builder()
         .vertex_binding_descriptions(&vertex_input_binding_descriptions)
@@ -161,7 +188,7 @@ pub fn create_pipelines(
                 &[solid_pipeline_create_info],
                 None,
             )
-            .map_err(|_| String::from("failed to create pipelines"))?
+            .map_err(|(_, result)| vk_error!("create_graphics_pipelines")(result))?
     };
 
     let pipeline = pipelines[0];
@@ -174,24 +201,76 @@ pub fn create_pipelines(
     Ok(pipeline)
 }
 
+#[inline]
+pub fn push_constant_range() -> vk::PushConstantRange {
+    vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::VERTEX,
+        offset: 0,
+        size: size_of::<PushConst>() as u32,
+    }
+}
+
+pub fn create_shader_module(
+    device: &ash::Device,
+    code: &[u32],
+) -> Result<vk::ShaderModule, RendererError> {
+    let shader_info = vk::ShaderModuleCreateInfo::builder().code(code);
+
+    unsafe {
+        device
+            .create_shader_module(&shader_info, None)
+            .map_err(vk_error!("create_shader_module"))
+    }
+}
+
+/// Checks the shaders against the layouts the pipelines are created with,
+/// so a mismatch fails here instead of rendering garbage.
+pub fn validate_shaders(
+    vertex_code: &[u32],
+    fragment_code: &[u32],
+    descriptor_bindings: &[vk::DescriptorSetLayoutBinding],
+) -> Result<(), RendererError> {
+    let push_constants = PushConst::field_layouts();
+    let vertex_attributes = Vertex::attribute_descriptions(0);
+    let interface = PipelineInterface {
+        push_constant_name: "PushConst",
+        push_constants: &push_constants,
+        push_constant_range: push_constant_range(),
+        vertex_binding: Vertex::binding_description(0),
+        vertex_attributes: &vertex_attributes,
+        descriptor_bindings,
+    };
+
+    validate_shader_interface(vertex_code, fragment_code, &interface)
+}
+
+/// Same as [`validate_shaders`], for pipelines with their own layouts.
+pub fn validate_shader_interface(
+    vertex_code: &[u32],
+    fragment_code: &[u32],
+    interface: &PipelineInterface,
+) -> Result<(), RendererError> {
+    ShaderReflection::new(vertex_code)?.validate(interface)?;
+    ShaderReflection::new(fragment_code)?.validate(interface)?;
+
+    Ok(())
+}
+
 pub fn create_pipeline_layout(
     device: &ash::Device,
     descriptor_set_layout: vk::DescriptorSetLayout,
-) -> Result<vk::PipelineLayout, String> {
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> Result<vk::PipelineLayout, RendererError> {
     let layouts = [descriptor_set_layout];
     let create_info = vk::PipelineLayoutCreateInfo::builder()
         .set_layouts(&layouts)
-        .push_constant_ranges(&[vk::PushConstantRange {
-            stage_flags: vk::ShaderStageFlags::VERTEX,
-            offset: 0,
-            size: size_of::<PushConst>() as u32,
-        }])
+        .push_constant_ranges(push_constant_ranges)
         .build();
 
     let pipeline_layout = unsafe {
         device
             .create_pipeline_layout(&create_info, None)
-            .map_err(|_| String::from("failed to create pipeline layout"))?
+            .map_err(vk_error!("create_pipeline_layout"))?
     };
 
     Ok(pipeline_layout)
@@ -201,7 +280,7 @@ pub fn create_render_pass(
     device: &ash::Device,
     surface_format: vk::Format,
     depth_format: vk::Format,
-) -> Result<vk::RenderPass, String> {
+) -> Result<vk::RenderPass, RendererError> {
     let attachment_descriptions = [
         vk::AttachmentDescription {
             format: surface_format,
@@ -218,6 +297,8 @@ pub fn create_render_pass(
             samples: vk::SampleCountFlags::TYPE_1,
             load_op: vk::AttachmentLoadOp::CLEAR,
             store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::CLEAR,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
             initial_layout: vk::ImageLayout::UNDEFINED,
             final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
             ..Default::default()
@@ -268,7 +349,7 @@ pub fn create_render_pass(
     let render_pass = unsafe {
         device
             .create_render_pass(&create_info, None)
-            .map_err(|_| String::from("failed to create render pass"))?
+            .map_err(vk_error!("create_render_pass"))?
     };
 
     Ok(render_pass)
@@ -277,11 +358,11 @@ pub fn create_render_pass(
 pub fn get_swapchain_images(
     swapchain_loader: &khr::Swapchain,
     swapchain: vk::SwapchainKHR,
-) -> Result<Vec<vk::Image>, String> {
+) -> Result<Vec<vk::Image>, RendererError> {
     let swapchain_images = unsafe {
         swapchain_loader
             .get_swapchain_images(swapchain)
-            .map_err(|_| String::from("failed to get swapchain images"))?
+            .map_err(vk_error!("get_swapchain_images"))?
     };
 
     Ok(swapchain_images)
@@ -291,15 +372,13 @@ pub fn get_surface_format(
     physical_device: vk::PhysicalDevice,
     surface_loader: &khr::Surface,
     surface: vk::SurfaceKHR,
-) -> Result<vk::SurfaceFormatKHR, String> {
+) -> Result<vk::SurfaceFormatKHR, RendererError> {
     let formats = match unsafe {
         surface_loader.get_physical_device_surface_formats(physical_device, surface)
     } {
         Ok(formats) => formats,
-        Err(_) => {
-            return Err(String::from(
-                "failed to get physical device surface formats",
-            ));
+        Err(result) => {
+            return Err(vk_error!("get_physical_device_surface_formats")(result));
         }
     };
 
@@ -322,11 +401,12 @@ pub fn get_surface_format(
 pub fn get_depth_format(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-) -> Result<vk::Format, String> {
+) -> Result<vk::Format, RendererError> {
+    //Formats with a stencil come first, the selection outlines need it
     let formats = [
         vk::Format::D24_UNORM_S8_UINT,
-        vk::Format::D32_SFLOAT,
         vk::Format::D32_SFLOAT_S8_UINT,
+        vk::Format::D32_SFLOAT,
     ];
 
     let format = formats.into_iter().find(|&f| {
@@ -338,8 +418,36 @@ pub fn get_depth_format(
     });
 
     match format {
-        Some(f) => Ok(f),
-        None => Err(parse_error!("Failed to get proper depth format")),
+        Some(f) => {
+            if !has_stencil(f) {
+                crate::msg!(
+                    warn,
+                    "No depth format with a stencil is supported, selection outlines are unavailable"
+                );
+            }
+            Ok(f)
+        }
+        None => Err(renderer_error!(
+            "get_depth_format",
+            "no depth format supports optimal tiling"
+        )),
+    }
+}
+
+#[inline]
+pub fn has_stencil(depth_format: vk::Format) -> bool {
+    matches!(
+        depth_format,
+        vk::Format::D24_UNORM_S8_UINT | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+#[inline]
+pub fn depth_aspect_mask(depth_format: vk::Format) -> vk::ImageAspectFlags {
+    if has_stencil(depth_format) {
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    } else {
+        vk::ImageAspectFlags::DEPTH
     }
 }
 
@@ -377,11 +485,11 @@ pub fn get_surface_capabilities(
     surface_loader: &khr::Surface,
     physical_device: vk::PhysicalDevice,
     surface: vk::SurfaceKHR,
-) -> Result<vk::SurfaceCapabilitiesKHR, String> {
+) -> Result<vk::SurfaceCapabilitiesKHR, RendererError> {
     let surface_capabilities = unsafe {
         surface_loader
             .get_physical_device_surface_capabilities(physical_device, surface)
-            .map_err(|_| String::from("failed to get physical device surface capabilities"))?
+            .map_err(vk_error!("get_physical_device_surface_capabilities"))?
     };
 
     Ok(surface_capabilities)
@@ -392,7 +500,7 @@ pub fn get_queue_family(
     physical_device: vk::PhysicalDevice,
     surface_loader: &khr::Surface,
     surface: vk::SurfaceKHR,
-) -> Result<u32, String> {
+) -> Result<u32, RendererError> {
     let props = unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
 
     for (ind, p) in props.iter().enumerate() {
@@ -405,10 +513,8 @@ pub fn get_queue_family(
                 )
             } {
                 Ok(result) => result,
-                Err(_) => {
-                    return Err(String::from(
-                        "failed to get physical device surface_support",
-                    ))
+                Err(result) => {
+                    return Err(vk_error!("get_physical_device_surface_support")(result))
                 }
             };
 
@@ -418,30 +524,38 @@ pub fn get_queue_family(
         }
     }
 
-    Err(String::from(
-        "failed to find graphics queue with present support",
+    Err(renderer_error!(
+        "get_queue_family",
+        "no graphics queue with present support"
     ))
 }
 
-pub fn get_present_mode(
+/// Picks the first supported mode of the preferences, FIFO is always supported.
+pub fn get_supported_present_modes(
     physical_device: vk::PhysicalDevice,
     surface_loader: &khr::Surface,
     surface: vk::SurfaceKHR,
-) -> Result<vk::PresentModeKHR, String> {
-    let modes = match unsafe {
+) -> Result<Vec<PresentModeKHR>, RendererError> {
+    match unsafe {
         surface_loader.get_physical_device_surface_present_modes(physical_device, surface)
     } {
-        Ok(formats) => formats,
-        Err(_) => {
-            return Err(String::from(
-                "failed to get physical device surface present modes",
-            ));
-        }
-    };
+        Ok(modes) => Ok(modes),
+        Err(result) => Err(vk_error!("get_physical_device_surface_present_modes")(result)),
+    }
+}
+
+pub fn get_present_mode(
+    physical_device: vk::PhysicalDevice,
+    surface_loader: &khr::Surface,
+    surface: vk::SurfaceKHR,
+    preferences: &[PresentModeKHR],
+) -> Result<PresentModeKHR, RendererError> {
+    let modes = get_supported_present_modes(physical_device, surface_loader, surface)?;
 
-    Ok(modes
-        .into_iter()
-        .find(|mode| *mode == PresentModeKHR::MAILBOX)
+    Ok(preferences
+        .iter()
+        .copied()
+        .find(|mode| modes.contains(mode))
         .unwrap_or(PresentModeKHR::FIFO))
 }
 
@@ -449,15 +563,11 @@ fn check_required_device_extensions(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     required_extensions: &Vec<&std::ffi::CStr>,
-) -> Result<(), String> {
+) -> Result<(), RendererError> {
     let supported_device_extensions =
         match unsafe { instance.enumerate_device_extension_properties(physical_device) } {
             Ok(props) => props,
-            Err(_) => {
-                return Err(String::from(
-                    "failed to enumerate instance extension properies",
-                ))
-            }
+            Err(result) => return Err(vk_error!("enumerate_device_extension_properties")(result)),
         };
 
     let mut supported_device_extensions_set = std::collections::HashSet::new();
@@ -468,7 +578,8 @@ fn check_required_device_extensions(
 
     for extension_name in required_extensions {
         if !supported_device_extensions_set.contains(extension_name) {
-            return Err(format!(
+            return Err(renderer_error!(
+                "check_required_device_extensions",
                 "device extension {:?} is not supported",
                 extension_name
             ));
@@ -483,14 +594,15 @@ fn check_device_suitability(
     physical_device: vk::PhysicalDevice,
     required_extensions: &Vec<&std::ffi::CStr>,
     properties: &vk::PhysicalDeviceProperties,
-) -> Result<(), String> {
+) -> Result<(), RendererError> {
     // api version
 
     if vk::api_version_major(properties.api_version) < 1
         && vk::api_version_minor(properties.api_version) < 2
     {
-        return Err(String::from(
-            "the device does not support API version 1.2.0",
+        return Err(renderer_error!(
+            "check_device_suitability",
+            "the device does not support API version 1.2.0"
         ));
     }
 
@@ -499,14 +611,16 @@ fn check_device_suitability(
 
     // TODO pass as parameter
     if features.tessellation_shader == 0 {
-        return Err(String::from(
-            "the device does not support tesselation shader",
+        return Err(renderer_error!(
+            "check_device_suitability",
+            "the device does not support tesselation shader"
         ));
     }
 
     if features.fill_mode_non_solid == 0 {
-        return Err(String::from(
-            "the device does not support fill mode non solid",
+        return Err(renderer_error!(
+            "check_device_suitability",
+            "the device does not support fill mode non solid"
         ));
     }
 
@@ -518,10 +632,10 @@ fn check_device_suitability(
 pub fn get_physical_device<'a>(
     instance: &ash::Instance,
     required_device_extensions: &Vec<&'a std::ffi::CStr>,
-) -> Result<vk::PhysicalDevice, String> {
+) -> Result<vk::PhysicalDevice, RendererError> {
     let devices = match unsafe { instance.enumerate_physical_devices() } {
         Ok(devices) => devices,
-        Err(_) => return Err(String::from("failed to enumerate physical devices")),
+        Err(result) => return Err(vk_error!("enumerate_physical_devices")(result)),
     };
 
     for physical_device in devices {
@@ -539,7 +653,10 @@ pub fn get_physical_device<'a>(
         return Ok(physical_device);
     }
 
-    Err(String::from("failed to find suitable device"))
+    Err(renderer_error!(
+        "get_physical_device",
+        "no device supports the required features and extensions"
+    ))
 }
 
 pub fn create_swapchain(
@@ -550,7 +667,7 @@ pub fn create_swapchain(
     surface_extent: vk::Extent2D,
     present_mode: vk::PresentModeKHR,
     swapchain_loader: &khr::Swapchain,
-) -> Result<vk::SwapchainKHR, String> {
+) -> Result<vk::SwapchainKHR, RendererError> {
     let mut image_count = std::cmp::max(surface_capabilities.min_image_count, 3);
 
     if surface_capabilities.max_image_count != 0 {
@@ -576,7 +693,7 @@ pub fn create_swapchain(
     let swapchain = unsafe {
         swapchain_loader
             .create_swapchain(&create_info, None)
-            .map_err(|_| String::from("failed to create swapchain"))?
+            .map_err(vk_error!("create_swapchain"))?
     };
 
     if old_swapchain != vk::SwapchainKHR::null() {
@@ -590,7 +707,7 @@ pub fn create_surface(
     entry: &ash::Entry,
     instance: &ash::Instance,
     window: &winit::window::Window,
-) -> Result<vk::SurfaceKHR, String> {
+) -> Result<vk::SurfaceKHR, RendererError> {
     let surface = unsafe {
         ash_window::create_surface(
             &entry,
@@ -599,7 +716,7 @@ pub fn create_surface(
             window.raw_window_handle(),
             None,
         )
-        .map_err(|_| String::from("failed to create surface"))?
+        .map_err(vk_error!("create_surface"))?
     };
 
     Ok(surface)
@@ -610,7 +727,7 @@ pub fn create_logical_device<'a>(
     physical_device: vk::PhysicalDevice,
     queue_family: u32,
     device_extensions: &Vec<&'a std::ffi::CStr>,
-) -> Result<ash::Device, String> {
+) -> Result<ash::Device, RendererError> {
     let queue_indices = [queue_family];
 
     let mut queue_priorities = Vec::new();
@@ -647,7 +764,7 @@ pub fn create_logical_device<'a>(
     let device = unsafe {
         instance
             .create_device(physical_device, &create_info, None)
-            .map_err(|_| String::from("failed to create device"))?
+            .map_err(vk_error!("create_device"))?
     };
 
     return Ok(device);
@@ -655,7 +772,7 @@ pub fn create_logical_device<'a>(
 
 pub fn create_debug_call_back(
     debug_utils_loader: &ext::DebugUtils,
-) -> Result<vk::DebugUtilsMessengerEXT, String> {
+) -> Result<vk::DebugUtilsMessengerEXT, RendererError> {
     let debug_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
         .message_severity(
             vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
@@ -672,7 +789,7 @@ pub fn create_debug_call_back(
     let debug_call_back = unsafe {
         debug_utils_loader
             .create_debug_utils_messenger(&debug_info, None)
-            .map_err(|err| format!("{}", err))?
+            .map_err(vk_error!("create_debug_utils_messenger"))?
     };
 
     Ok(debug_call_back)
@@ -723,7 +840,7 @@ pub fn create_instance<'a>(
 
 pub fn get_required_instance_extensions(
     window: &winit::window::Window,
-) -> Result<Vec<&'static std::ffi::CStr>, String> {
+) -> Result<Vec<&'static std::ffi::CStr>, RendererError> {
     let mut instance_extensions =
         match ash_window::enumerate_required_extensions(window.raw_display_handle()) {
             Ok(extensions) => extensions
@@ -731,10 +848,8 @@ pub fn get_required_instance_extensions(
                 .into_iter()
                 .map(|name| unsafe { std::ffi::CStr::from_ptr(name) })
                 .collect::<Vec<&'static std::ffi::CStr>>(),
-            Err(_) => {
-                return Err(String::from(
-                    "failed to enumerate required instance extensions",
-                ))
+            Err(result) => {
+                return Err(vk_error!("enumerate_required_extensions")(result))
             }
         };
 
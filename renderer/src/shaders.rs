@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ash::vk;
+use naga::{
+    back::spv,
+    front::glsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+    ShaderStage,
+};
+
+use crate::{
+    data::descriptor_set_layout_bindings, error::RendererError, msg, renderer_error, setup,
+    utils::vertex::Vertex, vk_error, Renderer,
+};
+
+pub const SHADER_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/.shaders");
+pub const VERTEX_SHADER: &str = "vertex.vert";
+pub const FRAGMENT_SHADER: &str = "fragment.frag";
+pub const DEBUG_VERTEX_SHADER: &str = "debug.vert";
+pub const OVERLAY_VERTEX_SHADER: &str = "overlay.vert";
+pub const OVERLAY_FRAGMENT_SHADER: &str = "overlay.frag";
+
+/// SPIR-V of the vertex, fragment and debug line vertex shader.
+pub type ShaderCodes = (Vec<u32>, Vec<u32>, Vec<u32>);
+
+/// Compiles a GLSL file into SPIR-V words.
+pub fn compile_glsl(path: &Path, stage: ShaderStage) -> Result<Vec<u32>, RendererError> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| renderer_error!("compile_glsl", "{}: {}", path.display(), err))?;
+
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(stage), &source)
+        .map_err(|errors| {
+            let messages = errors
+                .iter()
+                .map(|err| {
+                    let location = err.meta.location(&source);
+                    format!(
+                        "{}:{}:{}: {}",
+                        path.display(),
+                        location.line_number,
+                        location.line_position,
+                        err
+                    )
+                })
+                .collect::<Vec<_>>();
+            renderer_error!("compile_glsl", "{}", messages.join("\n"))
+        })?;
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|err| {
+            renderer_error!("compile_glsl", "{}: {}", path.display(), err.as_inner())
+        })?;
+
+    //The GLSL is already written for Vulkan's coordinate space, it must not be flipped again.
+    //Debug info keeps the names, so reflection errors can point at the right field.
+    let options = spv::Options {
+        flags: spv::WriterFlags::DEBUG,
+        ..Default::default()
+    };
+
+    spv::write_vec(&module, &info, &options, None)
+        .map_err(|err| renderer_error!("compile_glsl", "{}: {}", path.display(), err))
+}
+
+/// Compiles the vertex, fragment and debug line vertex shader from the sources in [`SHADER_DIRECTORY`].
+pub fn compile_shaders() -> Result<ShaderCodes, RendererError> {
+    let directory = Path::new(SHADER_DIRECTORY);
+    let vertex_code = compile_glsl(&directory.join(VERTEX_SHADER), ShaderStage::Vertex)?;
+    let fragment_code = compile_glsl(&directory.join(FRAGMENT_SHADER), ShaderStage::Fragment)?;
+    let debug_vertex_code =
+        compile_glsl(&directory.join(DEBUG_VERTEX_SHADER), ShaderStage::Vertex)?;
+
+    Ok((vertex_code, fragment_code, debug_vertex_code))
+}
+
+/// Compiles the overlay vertex and fragment shader from the sources in [`SHADER_DIRECTORY`].
+pub fn compile_overlay_shaders() -> Result<(Vec<u32>, Vec<u32>), RendererError> {
+    let directory = Path::new(SHADER_DIRECTORY);
+    let vertex_code = compile_glsl(&directory.join(OVERLAY_VERTEX_SHADER), ShaderStage::Vertex)?;
+    let fragment_code = compile_glsl(
+        &directory.join(OVERLAY_FRAGMENT_SHADER),
+        ShaderStage::Fragment,
+    )?;
+
+    Ok((vertex_code, fragment_code))
+}
+
+/// Compiles the shaders, and checks them against the pipeline layout before creating the modules.
+/// Returns the vertex, fragment and debug line vertex shader modules.
+pub fn create_shader_modules(
+    device: &ash::Device,
+) -> Result<(vk::ShaderModule, vk::ShaderModule, vk::ShaderModule), RendererError> {
+    let (vertex_code, fragment_code, debug_vertex_code) = compile_shaders()?;
+    let descriptor_bindings = descriptor_set_layout_bindings();
+    setup::validate_shaders(&vertex_code, &fragment_code, &descriptor_bindings)?;
+    setup::validate_shaders(&debug_vertex_code, &fragment_code, &descriptor_bindings)?;
+
+    let vertex_shader_module = setup::create_shader_module(device, &vertex_code)?;
+    let fragment_shader_module = setup::create_shader_module(device, &fragment_code)
+        .inspect_err(|_| unsafe { device.destroy_shader_module(vertex_shader_module, None) })?;
+    let debug_vertex_shader_module = setup::create_shader_module(device, &debug_vertex_code)
+        .inspect_err(|_| unsafe {
+            device.destroy_shader_module(vertex_shader_module, None);
+            device.destroy_shader_module(fragment_shader_module, None);
+        })?;
+
+    Ok((
+        vertex_shader_module,
+        fragment_shader_module,
+        debug_vertex_shader_module,
+    ))
+}
+
+/// The pipelines built from the shader sources.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderPipelines {
+    /// The material pipelines and the debug lines.
+    Materials,
+    Overlay,
+}
+
+/// Returns the pipelines built from the shader source, [`None`] if no pipeline uses it.
+pub fn pipelines_using(path: &Path) -> Option<ShaderPipelines> {
+    match path.file_name()?.to_str()? {
+        VERTEX_SHADER | FRAGMENT_SHADER | DEBUG_VERTEX_SHADER => Some(ShaderPipelines::Materials),
+        OVERLAY_VERTEX_SHADER | OVERLAY_FRAGMENT_SHADER => Some(ShaderPipelines::Overlay),
+        _ => None,
+    }
+}
+
+/// Keeps track of the modification times of every file in [`SHADER_DIRECTORY`].
+pub struct ShaderWatcher {
+    sources: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self {
+            sources: shader_sources(),
+        }
+    }
+}
+
+impl ShaderWatcher {
+    /// Returns the sources added or changed since the last call.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let sources = shader_sources();
+        let changed = sources
+            .iter()
+            .filter(|(path, modified)| self.sources.get(*path) != Some(*modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.sources = sources;
+        changed
+    }
+}
+
+fn shader_sources() -> HashMap<PathBuf, Option<SystemTime>> {
+    fs::read_dir(SHADER_DIRECTORY)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            let modified = modification_time(&path);
+            (path, modified)
+        })
+        .collect()
+}
+
+#[inline]
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Renderer {
+    /// Rebuilds the pipelines whose shader sources changed, call it between frames.
+    /// If the shaders fail to compile the error is logged and the current pipelines are kept.
+    /// Returns *true* if any pipeline was rebuilt.
+    pub fn reload_shaders(&mut self) -> Result<bool, RendererError> {
+        let changed = self.shader_watcher.poll();
+        let uses = |pipelines| {
+            changed
+                .iter()
+                .any(|path| pipelines_using(path) == Some(pipelines))
+        };
+
+        let mut reloaded = false;
+        if uses(ShaderPipelines::Materials) {
+            reloaded |= self.reload_material_shaders()?;
+        }
+        if uses(ShaderPipelines::Overlay) {
+            reloaded |= self.reload_overlay_shaders()?;
+        }
+
+        if reloaded {
+            msg!(info, "Shaders reloaded");
+        }
+        Ok(reloaded)
+    }
+
+    fn reload_material_shaders(&mut self) -> Result<bool, RendererError> {
+        let (vertex_shader_module, fragment_shader_module, debug_vertex_shader_module) =
+            match create_shader_modules(&self.base.device) {
+                Ok(modules) => modules,
+                Err(err) => {
+                    msg!(warn, err.to_string());
+                    return Ok(false);
+                }
+            };
+
+        //The debug lines are rebuilt with the materials, they use the same fragment shader
+        let pipelines = self
+            .data
+            .materials
+            .iter()
+            .map(|material| (vertex_shader_module, &material.create_info))
+            .chain([(
+                debug_vertex_shader_module,
+                &self.data.debug_lines.material.create_info,
+            )])
+            .map(|(vertex_shader_module, create_info)| {
+                setup::create_pipelines::<Vertex>(
+                    &self.base.device,
+                    vertex_shader_module,
+                    fragment_shader_module,
+                    self.data.pipeline_layout,
+                    self.data.render_pass,
+                    create_info,
+                )
+            })
+            .collect::<Vec<_>>();
+        //The materials keep their modules to create new pipelines, the debug lines don't
+        unsafe {
+            self.base
+                .device
+                .destroy_shader_module(debug_vertex_shader_module, None);
+        }
+
+        if pipelines.iter().any(|pipeline| pipeline.is_err()) {
+            unsafe {
+                self.base
+                    .device
+                    .destroy_shader_module(vertex_shader_module, None);
+                self.base
+                    .device
+                    .destroy_shader_module(fragment_shader_module, None);
+            }
+            pipelines.into_iter().for_each(|pipeline| match pipeline {
+                Ok(pipeline) => unsafe { self.base.device.destroy_pipeline(pipeline, None) },
+                Err(err) => msg!(warn, err.to_string()),
+            });
+            return Ok(false);
+        }
+
+        //The old pipelines can still be used by the frames in flight.
+        unsafe {
+            self.base
+                .device
+                .device_wait_idle()
+                .map_err(vk_error!("device_wait_idle"))?;
+
+            let materials = self
+                .data
+                .materials
+                .iter_mut()
+                .chain([&mut self.data.debug_lines.material]);
+            for (material, pipeline) in materials.zip(pipelines) {
+                material.destroy(&self.base.device);
+                material.pipeline = pipeline?;
+            }
+
+            self.base
+                .device
+                .destroy_shader_module(self.data.shader_modules.0, None);
+            self.base
+                .device
+                .destroy_shader_module(self.data.shader_modules.1, None);
+        }
+        self.data.shader_modules = (vertex_shader_module, fragment_shader_module);
+
+        Ok(true)
+    }
+
+    fn reload_overlay_shaders(&mut self) -> Result<bool, RendererError> {
+        let (vertex_code, fragment_code) = match compile_overlay_shaders() {
+            Ok(codes) => codes,
+            Err(err) => {
+                msg!(warn, err.to_string());
+                return Ok(false);
+            }
+        };
+
+        self.data.overlay.reload_pipeline(
+            &self.base.device,
+            self.data.render_pass,
+            &vertex_code,
+            &fragment_code,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_shader_source_builds_a_pipeline() {
+        let sources = shader_sources();
+        assert!(!sources.is_empty());
+        for path in sources.keys() {
+            assert!(
+                pipelines_using(path).is_some(),
+                "{} isn't used by any pipeline",
+                path.display()
+            );
+        }
+    }
+}
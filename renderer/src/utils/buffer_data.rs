@@ -1,6 +1,8 @@
 use std::ffi::c_void;
 
-use nalgebra::{Matrix4, Vector3};
+use nalgebra::{Matrix4, Vector3, Vector4};
+
+use crate::{field_layouts, reflection::FieldLayout};
 
 pub trait BufferObject {
     fn as_void_ptr(&self) -> *const c_void {
@@ -11,26 +13,77 @@ pub trait BufferObject {
 #[derive(Debug, Default)]
 #[repr(C)]
 pub struct PushConst {
-    pub wh_ratio: f32,
-    pub min_z: f32,
-    pub max_z: f32,
-    pub ghost_value: f32,
     pub sun_direction: Vector3<f32>,
-    pub ghost_value_2: f32,
+    pub ghost_value: f32,
     pub sun_color: Vector3<f32>,
+    //Material parameters, overwritten for every material
+    pub lighting: f32,
+    pub tint: Vector4<f32>,
+    pub outline_width: f32,
+}
+
+impl PushConst {
+    /// Layout of the fields the shaders read, the ghost values are only padding.
+    pub fn field_layouts() -> [FieldLayout; 5] {
+        field_layouts!(
+            PushConst,
+            sun_direction,
+            sun_color,
+            lighting,
+            tint,
+            outline_width
+        )
+    }
+}
+
+/// Per object data in the dynamic uniform buffer.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ObjectUniform {
+    pub transform: Matrix4<f32>,
+    pub tint: Vector4<f32>,
+    //Free for gameplay effects, the shaders don't read them yet
+    pub parameters: Vector4<f32>,
+    pub flags: u32,
+}
+
+impl ObjectUniform {
+    pub const HIGHLIGHTED: u32 = 1;
+    pub const SELECTED: u32 = 1 << 1;
+
+    #[inline]
+    pub fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+impl Default for ObjectUniform {
+    fn default() -> Self {
+        Self {
+            transform: Matrix4::identity(),
+            tint: Vector4::new(1., 1., 1., 1.),
+            parameters: Vector4::zeros(),
+            flags: 0,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct WorldView {
     pub view: Matrix4<f32>,
-    pub rotation: Matrix4<f32>,
+    //From view space to clip space, owned by the application's camera
+    pub projection: Matrix4<f32>,
 }
 
 impl WorldView {
     pub fn new() -> Self {
         Self {
             view: Matrix4::identity(),
-            rotation: Matrix4::identity(),
+            projection: Matrix4::identity(),
         }
     }
 }
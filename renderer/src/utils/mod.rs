@@ -66,6 +66,17 @@ macro_rules! parse_error {
     }};
 }
 
+/// Reads a precompiled shader into SPIR-V words at compile time.
+#[macro_export]
+macro_rules! include_shader {
+    ($path:expr) => {{
+        use std::io::Cursor;
+
+        let mut spv_file = Cursor::new(&include_bytes!($path)[..]);
+        ash::util::read_spv(&mut spv_file).expect("Failed to read shader spv file")
+    }};
+}
+
 #[macro_export]
 macro_rules! create_shader {
     ($path:expr, $device:expr) => {{
@@ -1,6 +1,16 @@
+use ash::vk;
 use nalgebra::Vector3;
 
-#[derive(Debug, Clone, Copy)]
+pub use derive_vertex_layout::VertexLayout;
+
+/// Describes how a vertex type is laid out in a vertex buffer, usually derived.
+pub trait VertexLayout {
+    fn binding_description(binding: u32) -> vk::VertexInputBindingDescription;
+    fn attribute_descriptions(binding: u32) -> Vec<vk::VertexInputAttributeDescription>;
+}
+
+#[derive(Debug, Clone, Copy, VertexLayout)]
+#[repr(C)]
 pub struct Vertex {
     pub pos: Vector3<f32>,
     pub color: Vector3<f32>,
@@ -32,3 +42,59 @@ impl Default for Vertex {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Vector2, Vector4};
+
+    #[derive(Clone, Copy, VertexLayout)]
+    #[repr(C)]
+    struct MixedVertex {
+        position: Vector2<f32>,
+        id: u32,
+        color: [u8; 4],
+        weights: Vector4<f32>,
+        #[vertex(format = R16G16_SFLOAT)]
+        uv: [u16; 2],
+    }
+
+    fn layout(attributes: &[vk::VertexInputAttributeDescription]) -> Vec<(u32, vk::Format, u32)> {
+        attributes
+            .iter()
+            .map(|attribute| (attribute.location, attribute.format, attribute.offset))
+            .collect()
+    }
+
+    #[test]
+    fn vertex_layout() {
+        let binding = Vertex::binding_description(0);
+        assert_eq!(binding.stride, 36);
+        assert_eq!(binding.input_rate, vk::VertexInputRate::VERTEX);
+        assert_eq!(
+            layout(&Vertex::attribute_descriptions(0)),
+            [
+                (0, vk::Format::R32G32B32_SFLOAT, 0),
+                (1, vk::Format::R32G32B32_SFLOAT, 12),
+                (2, vk::Format::R32G32B32_SFLOAT, 24),
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_vertex_layout() {
+        assert_eq!(MixedVertex::binding_description(3).stride, 36);
+        let attributes = MixedVertex::attribute_descriptions(3);
+        assert!(attributes.iter().all(|attribute| attribute.binding == 3));
+        assert_eq!(
+            layout(&attributes),
+            [
+                (0, vk::Format::R32G32_SFLOAT, 0),
+                (1, vk::Format::R32_UINT, 8),
+                (2, vk::Format::R8G8B8A8_UNORM, 12),
+                (3, vk::Format::R32G32B32A32_SFLOAT, 16),
+                (4, vk::Format::R16G16_SFLOAT, 32),
+            ]
+        );
+    }
+}